@@ -5,14 +5,14 @@ _slab_completions() {
     local cur prev words cword
     _init_completion || return
 
-    local commands="chat run config models sessions test init completions"
-    local global_opts="-m --model -c --config -v --verbose --no-stream -h --help -V --version"
+    local commands="chat run config models pull sessions test diff highlight init completions"
+    local global_opts="-m --model -c --config -v --verbose --no-stream --host --ollama-host --no-color -h --help -V --version"
 
     # Determine position in command
     local cmd=""
     for ((i=1; i < cword; i++)); do
         case "${words[i]}" in
-            chat|run|config|models|sessions|test|init|completions)
+            chat|run|config|models|pull|sessions|test|diff|highlight|init|completions)
                 cmd="${words[i]}"
                 break
                 ;;
@@ -29,20 +29,43 @@ _slab_completions() {
                     COMPREPLY=($(compgen -W "$sessions" -- "$cur"))
                     return
                     ;;
+                --script)
+                    COMPREPLY=($(compgen -f -- "$cur"))
+                    return
+                    ;;
             esac
-            COMPREPLY=($(compgen -W "-C --continue -s --session $global_opts" -- "$cur"))
+            COMPREPLY=($(compgen -W "-C --continue -s --session --system --script --readonly --no-template $global_opts" -- "$cur"))
             ;;
         run)
-            COMPREPLY=($(compgen -W "$global_opts" -- "$cur"))
+            COMPREPLY=($(compgen -W "--system --stdin --json --max-tokens $global_opts" -- "$cur"))
             ;;
         config)
-            COMPREPLY=($(compgen -W "--show --init --set $global_opts" -- "$cur"))
+            COMPREPLY=($(compgen -W "get unset --show --init --set $global_opts" -- "$cur"))
             ;;
         models)
-            COMPREPLY=($(compgen -W "--names-only $global_opts" -- "$cur"))
+            case "$prev" in
+                --sort)
+                    COMPREPLY=($(compgen -W "name size" -- "$cur"))
+                    return
+                    ;;
+                --format)
+                    COMPREPLY=($(compgen -W "text json" -- "$cur"))
+                    return
+                    ;;
+            esac
+            COMPREPLY=($(compgen -W "--names-only --sort --filter --format $global_opts" -- "$cur"))
+            ;;
+        pull)
+            COMPREPLY=($(compgen -W "--set-default $global_opts" -- "$cur"))
             ;;
         sessions)
-            COMPREPLY=($(compgen -W "--names-only $global_opts" -- "$cur"))
+            case "$prev" in
+                --format)
+                    COMPREPLY=($(compgen -W "text json" -- "$cur"))
+                    return
+                    ;;
+            esac
+            COMPREPLY=($(compgen -W "--names-only --format $global_opts" -- "$cur"))
             ;;
         test)
             case "$prev" in
@@ -54,7 +77,21 @@ _slab_completions() {
                     return
                     ;;
             esac
-            COMPREPLY=($(compgen -W "--filter --model $global_opts" -- "$cur"))
+            COMPREPLY=($(compgen -W "--filter --model --watch --tag --fail-fast --bail -j --jobs $global_opts" -- "$cur"))
+            ;;
+        diff)
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W "--dry-run $global_opts" -- "$cur"))
+            else
+                _filedir
+            fi
+            ;;
+        highlight)
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W "-n --line-numbers $global_opts" -- "$cur"))
+            else
+                _filedir
+            fi
             ;;
         completions)
             COMPREPLY=($(compgen -W "bash zsh fish powershell" -- "$cur"))
@@ -77,6 +114,10 @@ _slab_completions() {
                     _filedir toml
                     return
                     ;;
+                --host|--ollama-host)
+                    # Free-form URL
+                    return
+                    ;;
             esac
             COMPREPLY=($(compgen -W "$commands $global_opts" -- "$cur"))
             ;;
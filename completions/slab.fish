@@ -17,7 +17,7 @@ end
 # Helper to check if a subcommand has been given
 function __slab_needs_command
     set -l cmd (commandline -opc)
-    set -l subcommands chat run config models sessions test init completions
+    set -l subcommands chat run config models pull sessions test diff highlight init completions
     for i in (seq 2 (count $cmd))
         if contains -- $cmd[$i] $subcommands
             return 1
@@ -29,7 +29,7 @@ end
 # Helper to check which subcommand is active
 function __slab_using_command
     set -l cmd (commandline -opc)
-    set -l subcommands chat run config models sessions test init completions
+    set -l subcommands chat run config models pull sessions test diff highlight init completions
     for i in (seq 2 (count $cmd))
         if contains -- $cmd[$i] $subcommands
             if test "$cmd[$i]" = "$argv[1]"
@@ -46,6 +46,8 @@ complete -c slab -l model -s m -d 'Model to use' -xa '(__slab_models)'
 complete -c slab -l config -s c -d 'Path to config file' -rF
 complete -c slab -l verbose -s v -d 'Enable verbose output'
 complete -c slab -l no-stream -d 'Disable streaming'
+complete -c slab -l host -l ollama-host -x -d 'Ollama host URL for this invocation'
+complete -c slab -l no-color -d 'Disable colored output'
 complete -c slab -l help -s h -d 'Print help'
 complete -c slab -l version -s V -d 'Print version'
 
@@ -54,29 +56,64 @@ complete -c slab -n __slab_needs_command -a chat -d 'Start interactive chat REPL
 complete -c slab -n __slab_needs_command -a run -d 'Run a single prompt and exit'
 complete -c slab -n __slab_needs_command -a config -d 'Show or edit configuration'
 complete -c slab -n __slab_needs_command -a models -d 'List available Ollama models'
+complete -c slab -n __slab_needs_command -a pull -d 'Download a model through Ollama'
 complete -c slab -n __slab_needs_command -a sessions -d 'List saved sessions'
 complete -c slab -n __slab_needs_command -a test -d 'Run prompt tests'
+complete -c slab -n __slab_needs_command -a diff -d 'Review file operations from a saved response'
+complete -c slab -n __slab_needs_command -a highlight -d 'Print a file with syntax highlighting'
 complete -c slab -n __slab_needs_command -a init -d 'Initialize project with .slab directory'
 complete -c slab -n __slab_needs_command -a completions -d 'Generate shell completions'
 
 # Chat options
 complete -c slab -n '__slab_using_command chat' -l continue -s C -d 'Continue from last session'
 complete -c slab -n '__slab_using_command chat' -l session -s s -d 'Use a named session' -xa '(__slab_sessions)'
+complete -c slab -n '__slab_using_command chat' -l system -d 'Override the system prompt' -x
+complete -c slab -n '__slab_using_command chat' -l script -d 'Feed REPL input from a file, then exit' -rF
+complete -c slab -n '__slab_using_command chat' -l readonly -d 'Never apply file operations or run commands'
+complete -c slab -n '__slab_using_command chat' -l no-template -d 'Do not send the configured default_template'
+
+# Run options
+complete -c slab -n '__slab_using_command run' -l system -d 'Override the system prompt' -x
+complete -c slab -n '__slab_using_command run' -l stdin -d 'Read piped stdin as the prompt (or as context with a prompt)'
+complete -c slab -n '__slab_using_command run' -l json -d 'Ask the model for valid JSON output'
+complete -c slab -n '__slab_using_command run' -l max-tokens -d 'Stop generating after this many tokens' -x
 
 # Config options
 complete -c slab -n '__slab_using_command config' -l show -d 'Show current configuration'
 complete -c slab -n '__slab_using_command config' -l init -d 'Initialize a new config file'
 complete -c slab -n '__slab_using_command config' -l set -d 'Set a config value (key=value)'
+complete -c slab -n '__slab_using_command config' -f -a get -d 'Print the value of a config key'
+complete -c slab -n '__slab_using_command config' -f -a unset -d 'Reset a config key to its default'
 
 # Models options
 complete -c slab -n '__slab_using_command models' -l names-only -d 'Output only model names'
+complete -c slab -n '__slab_using_command models' -l sort -d 'Sort models' -xa 'name size'
+complete -c slab -n '__slab_using_command models' -l filter -d 'Only show models matching text' -x
+complete -c slab -n '__slab_using_command models' -l format -d 'Output format' -xa 'text json'
+
+# Pull options
+complete -c slab -n '__slab_using_command pull' -l set-default -d 'Make the pulled model the default'
 
 # Sessions options
 complete -c slab -n '__slab_using_command sessions' -l names-only -d 'Output only session names'
+complete -c slab -n '__slab_using_command sessions' -l format -d 'Output format' -xa 'text json'
 
 # Test options
 complete -c slab -n '__slab_using_command test' -l filter -d 'Filter tests by pattern'
 complete -c slab -n '__slab_using_command test' -l model -d 'Run tests with specific model' -xa '(__slab_models)'
+complete -c slab -n '__slab_using_command test' -l watch -d 'Re-run tests when a test file changes'
+complete -c slab -n '__slab_using_command test' -l tag -d 'Only run tests with this tag (prefix ! to exclude)' -x
+complete -c slab -n '__slab_using_command test' -l fail-fast -d 'Stop at the first failing test'
+complete -c slab -n '__slab_using_command test' -l bail -d 'Stop after N failing tests' -x
+complete -c slab -n '__slab_using_command test' -l jobs -s j -d 'Run up to N tests at once' -x
+
+# Diff options
+complete -c slab -n '__slab_using_command diff' -rF
+complete -c slab -n '__slab_using_command diff' -l dry-run -d 'Only print the previews; never apply anything'
+
+# Highlight options
+complete -c slab -n '__slab_using_command highlight' -rF
+complete -c slab -n '__slab_using_command highlight' -l line-numbers -s n -d 'Prefix each line with its line number'
 
 # Completions options
 complete -c slab -n '__slab_using_command completions' -a 'bash zsh fish powershell' -d 'Shell type'
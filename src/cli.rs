@@ -26,10 +26,32 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub no_stream: bool,
 
+    /// Ollama host URL for this invocation (overrides OLLAMA_HOST and config)
+    #[arg(long, visible_alias = "ollama-host", global = true, value_name = "URL")]
+    pub host: Option<String>,
+
+    /// Disable colored output (also honored via the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigAction {
+    /// Print the value of a config key (empty if unset)
+    Get {
+        /// Key name, e.g. ollama_host or ui.theme
+        key: String,
+    },
+    /// Reset a config key to its default
+    Unset {
+        /// Key name, e.g. default_model
+        key: String,
+    },
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
 #[allow(clippy::enum_variant_names)]
 pub enum Shell {
@@ -39,6 +61,22 @@ pub enum Shell {
     PowerShell,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ModelSort {
+    /// Alphabetical by model name
+    Name,
+    /// Largest first
+    Size,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable listing
+    Text,
+    /// A JSON array, for scripts and `jq`
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Start interactive chat REPL
@@ -58,12 +96,30 @@ pub enum Commands {
         /// Apply a prompt template by name (e.g., "review", "explain")
         #[arg(short = 't', long = "template")]
         template: Option<String>,
+
+        /// Don't send the configured default_template
+        #[arg(long, conflicts_with = "template")]
+        no_template: bool,
+
+        /// Use this system prompt instead of the configured one
+        #[arg(long)]
+        system: Option<String>,
+
+        /// Feed REPL input from a file, one line at a time, then exit
+        #[arg(long, value_name = "FILE")]
+        script: Option<PathBuf>,
+
+        /// Never apply file operations or run commands, whatever the config says
+        #[arg(long)]
+        readonly: bool,
     },
 
     /// Run a single prompt and exit
     Run {
-        /// The prompt to send (or template variables like key=value when using --template)
-        prompt: String,
+        /// The prompt to send (or template variables like key=value when using --template).
+        /// `-` reads the prompt from stdin
+        #[arg(required_unless_present = "stdin")]
+        prompt: Option<String>,
 
         /// Add file(s) or directory to context
         #[arg(short = 'f', long = "file")]
@@ -72,6 +128,23 @@ pub enum Commands {
         /// Apply a prompt template by name (e.g., "review", "explain")
         #[arg(short = 't', long = "template")]
         template: Option<String>,
+
+        /// Use this system prompt instead of the configured one
+        #[arg(long)]
+        system: Option<String>,
+
+        /// Read piped stdin: the prompt when none is given, otherwise context as a
+        /// pseudo-file named "stdin"
+        #[arg(long)]
+        stdin: bool,
+
+        /// Ask the model for valid JSON output (sets the request format to "json")
+        #[arg(long)]
+        json: bool,
+
+        /// Stop generating after this many tokens
+        #[arg(long, value_name = "N")]
+        max_tokens: Option<usize>,
     },
 
     /// Show or edit configuration
@@ -87,6 +160,9 @@ pub enum Commands {
         /// Set a config value (key=value)
         #[arg(long)]
         set: Option<String>,
+
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
     },
 
     /// List available Ollama models
@@ -94,6 +170,28 @@ pub enum Commands {
         /// Output only model names (for shell completion scripts)
         #[arg(long)]
         names_only: bool,
+
+        /// Sort models by name or size
+        #[arg(long, value_enum)]
+        sort: Option<ModelSort>,
+
+        /// Only show models whose name contains this text
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with = "names_only")]
+        format: OutputFormat,
+    },
+
+    /// Download a model through Ollama with a progress bar
+    Pull {
+        /// Model to pull (e.g., "qwen2.5:7b")
+        model: String,
+
+        /// Make the pulled model the default_model in the global config
+        #[arg(long)]
+        set_default: bool,
     },
 
     /// List saved sessions
@@ -101,6 +199,10 @@ pub enum Commands {
         /// Output only session names (for shell completion scripts)
         #[arg(long)]
         names_only: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with = "names_only")]
+        format: OutputFormat,
     },
 
     /// Run prompt tests
@@ -109,9 +211,49 @@ pub enum Commands {
         #[arg(long)]
         filter: Option<String>,
 
+        /// Only run tests with this tag; prefix with '!' to exclude it (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
         /// Run tests with a specific model
         #[arg(long)]
         model: Option<String>,
+
+        /// Re-run the tests whenever a test file changes
+        #[arg(long)]
+        watch: bool,
+
+        /// Stop at the first failing test
+        #[arg(long, conflicts_with = "bail")]
+        fail_fast: bool,
+
+        /// Stop after N failing tests
+        #[arg(long, value_name = "N")]
+        bail: Option<usize>,
+
+        /// Run up to N tests at once
+        #[arg(short = 'j', long, value_name = "N", default_value_t = 4)]
+        jobs: usize,
+    },
+
+    /// Review file operations from a saved LLM response and apply them
+    Diff {
+        /// File containing the saved model response
+        response: PathBuf,
+
+        /// Only print the previews; never apply anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print a file with syntax highlighting
+    Highlight {
+        /// File to print
+        file: PathBuf,
+
+        /// Prefix each line with its line number (also on with ui.code_line_numbers)
+        #[arg(short = 'n', long)]
+        line_numbers: bool,
     },
 
     /// Initialize a new project with .slab directory
@@ -132,6 +274,10 @@ impl Cli {
             session: None,
             files: Vec::new(),
             template: None,
+            no_template: false,
+            system: None,
+            script: None,
+            readonly: false,
         })
     }
 }
@@ -144,38 +290,158 @@ impl Clone for Commands {
                 session,
                 files,
                 template,
+                no_template,
+                system,
+                script,
+                readonly,
             } => Commands::Chat {
                 r#continue: *r#continue,
                 session: session.clone(),
                 files: files.clone(),
                 template: template.clone(),
+                no_template: *no_template,
+                system: system.clone(),
+                script: script.clone(),
+                readonly: *readonly,
             },
             Commands::Run {
                 prompt,
                 files,
                 template,
+                system,
+                stdin,
+                json,
+                max_tokens,
             } => Commands::Run {
                 prompt: prompt.clone(),
                 files: files.clone(),
                 template: template.clone(),
+                system: system.clone(),
+                stdin: *stdin,
+                json: *json,
+                max_tokens: *max_tokens,
             },
-            Commands::Config { show, init, set } => Commands::Config {
+            Commands::Config {
+                show,
+                init,
+                set,
+                action,
+            } => Commands::Config {
                 show: *show,
                 init: *init,
                 set: set.clone(),
+                action: action.clone(),
             },
-            Commands::Models { names_only } => Commands::Models {
+            Commands::Models {
+                names_only,
+                sort,
+                filter,
+                format,
+            } => Commands::Models {
                 names_only: *names_only,
+                sort: *sort,
+                filter: filter.clone(),
+                format: *format,
+            },
+            Commands::Pull { model, set_default } => Commands::Pull {
+                model: model.clone(),
+                set_default: *set_default,
             },
-            Commands::Sessions { names_only } => Commands::Sessions {
+            Commands::Sessions { names_only, format } => Commands::Sessions {
                 names_only: *names_only,
+                format: *format,
             },
-            Commands::Test { filter, model } => Commands::Test {
+            Commands::Test {
+                filter,
+                tags,
+                model,
+                watch,
+                fail_fast,
+                bail,
+                jobs,
+            } => Commands::Test {
                 filter: filter.clone(),
+                tags: tags.clone(),
                 model: model.clone(),
+                watch: *watch,
+                fail_fast: *fail_fast,
+                bail: *bail,
+                jobs: *jobs,
+            },
+            Commands::Diff { response, dry_run } => Commands::Diff {
+                response: response.clone(),
+                dry_run: *dry_run,
+            },
+            Commands::Highlight { file, line_numbers } => Commands::Highlight {
+                file: file.clone(),
+                line_numbers: *line_numbers,
             },
             Commands::Init => Commands::Init,
             Commands::Completions { shell } => Commands::Completions { shell: *shell },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_flag_is_global() {
+        let cli =
+            Cli::try_parse_from(["slab", "--host", "http://gpu-box:11434", "models"]).unwrap();
+        assert_eq!(cli.host.as_deref(), Some("http://gpu-box:11434"));
+
+        let cli = Cli::try_parse_from(["slab", "chat", "--ollama-host", "http://remote"]).unwrap();
+        assert_eq!(cli.host.as_deref(), Some("http://remote"));
+
+        let cli = Cli::try_parse_from(["slab", "models"]).unwrap();
+        assert_eq!(cli.host, None);
+    }
+
+    #[test]
+    fn test_run_prompt_optional_with_stdin() {
+        let cli = Cli::try_parse_from(["slab", "run", "--stdin"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Run {
+                prompt: None,
+                stdin: true,
+                ..
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["slab", "run", "-"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Run { prompt: Some(p), stdin: false, .. }) if p == "-"
+        ));
+
+        assert!(Cli::try_parse_from(["slab", "run"]).is_err());
+    }
+
+    #[test]
+    fn test_format_flag() {
+        let cli = Cli::try_parse_from(["slab", "sessions", "--format", "json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Sessions {
+                format: OutputFormat::Json,
+                ..
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["slab", "models"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Models {
+                format: OutputFormat::Text,
+                ..
+            })
+        ));
+
+        assert!(
+            Cli::try_parse_from(["slab", "models", "--names-only", "--format", "json"]).is_err()
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::session::Session;
+
 /// Represents a single completion suggestion
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -108,25 +110,50 @@ pub struct CompletionEngine {
     command_list_completer: CommandCompleter,
     /// Whether fuzzy matching is enabled
     fuzzy_enabled: bool,
+    /// Cap on file path completions (unlimited when unset)
+    max_file_items: Option<usize>,
 }
 
 impl CompletionEngine {
     pub fn new() -> Self {
+        Self::with_fuzzy(true)
+    }
+
+    /// Create an engine; with `fuzzy_enabled` off only prefix and substring matches are kept
+    pub fn with_fuzzy(fuzzy_enabled: bool) -> Self {
         let mut engine = Self {
             command_completers: HashMap::new(),
             command_list_completer: CommandCompleter::new(),
-            fuzzy_enabled: true,
+            fuzzy_enabled,
+            max_file_items: None,
         };
 
         // Register default completers
         engine.register("model", Box::new(ModelCompleter));
-        engine.register("add", Box::new(FileCompleter::new()));
+        engine.register("add", Box::new(engine.file_completer()));
         engine.register("remove", Box::new(ContextFileCompleter));
         engine.register("help", Box::new(HelpCompleter));
+        engine.register("load", Box::new(SessionCompleter::new()));
 
         engine
     }
 
+    /// Return at most `max` file path completions
+    pub fn with_max_file_items(mut self, max: usize) -> Self {
+        self.max_file_items = Some(max);
+        self.register("add", Box::new(self.file_completer()));
+        self
+    }
+
+    /// A file completer following this engine's fuzzy and size settings
+    fn file_completer(&self) -> FileCompleter {
+        let completer = FileCompleter::new().with_fuzzy(self.fuzzy_enabled);
+        match self.max_file_items {
+            Some(max) => completer.with_max_items(max),
+            None => completer,
+        }
+    }
+
     /// Register a completer for a specific command
     pub fn register(&mut self, command: &str, completer: Box<dyn Completer>) {
         self.command_completers
@@ -138,6 +165,11 @@ impl CompletionEngine {
         self.command_list_completer.add_templates(templates);
     }
 
+    /// Replace the template commands offered for completion
+    pub fn set_template_commands(&mut self, templates: Vec<(String, String)>) {
+        self.command_list_completer.set_templates(templates);
+    }
+
     /// Get completions for the current input
     pub fn complete(&self, input: &str, context: &CompletionContext) -> Vec<Completion> {
         let input = input.trim_start();
@@ -211,10 +243,34 @@ impl CompletionEngine {
             .find(|(pos, _)| *pos == 0 || input.as_bytes().get(pos - 1) == Some(&b' '));
 
         let (at_pos, _) = at_pos?;
-        let query = &input[at_pos + 1..];
-        let prefix = &input[..at_pos];
+        self.complete_at_token(input, at_pos..input.len(), input.len(), context)
+    }
+
+    /// Complete the `@` reference under the cursor, leaving the rest of the line intact.
+    /// Each completion's text is the full rebuilt line.
+    pub fn complete_at_cursor(
+        &self,
+        input: &str,
+        cursor: usize,
+        context: &CompletionContext,
+    ) -> Option<Vec<Completion>> {
+        let token = at_token_at(input, cursor)?;
+        self.complete_at_token(input, token, cursor, context)
+    }
+
+    /// Complete the `@path` token spanning `token`, using the text up to `cursor` as the query
+    fn complete_at_token(
+        &self,
+        input: &str,
+        token: std::ops::Range<usize>,
+        cursor: usize,
+        context: &CompletionContext,
+    ) -> Option<Vec<Completion>> {
+        let query = &input[token.start + 1..cursor];
+        let prefix = &input[..token.start];
+        let suffix = &input[token.end..];
 
-        let file_completer = FileCompleter::new();
+        let file_completer = self.file_completer();
         let mut completions = file_completer.complete(query, context);
 
         // Also include files already in context
@@ -232,7 +288,7 @@ impl CompletionEngine {
 
         // Rebuild full input with @completed_filename
         for completion in &mut completions {
-            completion.text = format!("{}@{}", prefix, completion.text);
+            completion.text = format!("{}@{}{}", prefix, completion.text, suffix);
         }
 
         self.apply_fuzzy_scoring(&mut completions, query);
@@ -262,9 +318,6 @@ impl CompletionEngine {
         }
 
         let mut completions = self.command_list_completer.complete(command_part, context);
-        if completions.is_empty() {
-            return None;
-        }
 
         // Rebuild full input: prefix + completed command
         for completion in &mut completions {
@@ -272,13 +325,17 @@ impl CompletionEngine {
         }
 
         self.apply_fuzzy_scoring(&mut completions, command_part);
+        if completions.is_empty() {
+            return None;
+        }
         self.sort_completions(&mut completions);
         Some(completions)
     }
 
-    /// Apply fuzzy scoring to completions
+    /// Score completions against `query` and drop the ones that don't match.
+    /// Fuzzy (subsequence) matches are only kept when fuzzy matching is enabled.
     fn apply_fuzzy_scoring(&self, completions: &mut Vec<Completion>, query: &str) {
-        if !self.fuzzy_enabled || query.is_empty() {
+        if query.is_empty() {
             return;
         }
 
@@ -298,6 +355,8 @@ impl CompletionEngine {
             // Fuzzy match - calculate score
             else if self.fuzzy_enabled {
                 completion.score = fuzzy_score(&query_lower, &text_lower);
+            } else {
+                completion.score = 0.0;
             }
         }
 
@@ -359,11 +418,57 @@ fn fuzzy_score(query: &str, target: &str) -> f32 {
     (match_ratio * 0.5 + consecutive_bonus).min(0.6) // Cap fuzzy matches below exact/contains
 }
 
+/// Byte range of the `@path` token containing `cursor`, from the `@` up to the next
+/// whitespace. The `@` must start the input or follow whitespace.
+pub fn at_token_at(input: &str, cursor: usize) -> Option<std::ops::Range<usize>> {
+    if cursor > input.len() || !input.is_char_boundary(cursor) {
+        return None;
+    }
+    let start = input[..cursor]
+        .rfind(char::is_whitespace)
+        .map(|i| i + input[i..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(0);
+    if !input[start..].starts_with('@') {
+        return None;
+    }
+    let end = input[cursor..]
+        .find(char::is_whitespace)
+        .map_or(input.len(), |i| cursor + i);
+    Some(start..end)
+}
+
+/// Candidates that look like what the user meant by `query`, best first.
+/// Compares full names and the part before any `:tag`, so `llama3:8b` suggests `llama3:latest`.
+pub fn suggest_similar<'a>(query: &str, candidates: &'a [String], limit: usize) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+    let query_base = query.split(':').next().unwrap_or_default();
+
+    let mut scored: Vec<(f32, &str)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let base = lower.split(':').next().unwrap_or_default();
+            let mut score = fuzzy_score(&query, &lower);
+            if !query_base.is_empty() {
+                score = score
+                    .max(fuzzy_score(query_base, base) * 0.9)
+                    .max(fuzzy_score(base, query_base) * 0.8);
+            }
+            (score > 0.0).then_some((score, candidate.as_str()))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    scored.into_iter().take(limit).map(|(_, c)| c).collect()
+}
+
 // ============== Built-in Completers ==============
 
 /// Completes REPL command names
 pub struct CommandCompleter {
     commands: Vec<(String, String)>, // (command, description)
+    /// Template commands, kept apart from the built-ins so they can be replaced on reload
+    templates: Vec<(String, String)>,
 }
 
 impl CommandCompleter {
@@ -374,11 +479,25 @@ impl CommandCompleter {
                 ("exit".into(), "Exit the REPL".into()),
                 ("quit".into(), "Exit the REPL".into()),
                 ("clear".into(), "Clear conversation".into()),
+                ("compact".into(), "Summarize old messages".into()),
+                ("edit".into(), "Edit and re-send last message".into()),
+                ("retry".into(), "Regenerate last response".into()),
                 ("model".into(), "Show/set current model".into()),
                 ("context".into(), "Show context summary".into()),
                 ("tokens".into(), "Show token usage".into()),
+                ("cost".into(), "Estimate session token usage".into()),
+                ("temp".into(), "Override temperature".into()),
+                ("top_p".into(), "Override top-p".into()),
+                ("json".into(), "Force JSON output".into()),
+                ("resend-with".into(), "Ask another model once".into()),
+                ("mark".into(), "Bookmark this point".into()),
+                ("theme".into(), "Switch color theme".into()),
+                ("marks".into(), "List bookmarks".into()),
+                ("load".into(), "Load a saved session".into()),
                 ("files".into(), "List files in context".into()),
+                ("tree".into(), "Show context files as a tree".into()),
                 ("add".into(), "Add file or directory to context".into()),
+                ("add-url".into(), "Fetch a URL into context".into()),
                 ("remove".into(), "Remove file from context".into()),
                 ("fileops".into(), "Toggle file operations".into()),
                 (
@@ -388,19 +507,41 @@ impl CommandCompleter {
                 ("templates".into(), "List available templates".into()),
                 ("rules".into(), "Show loaded rules".into()),
                 ("exec".into(), "Run a shell command".into()),
-                ("export".into(), "Export chat to a text file".into()),
+                ("undo".into(), "Revert last applied file changes".into()),
+                (
+                    "explain-diff".into(),
+                    "Explain last proposed changes".into(),
+                ),
+                ("replay".into(), "Re-run last exec blocks".into()),
+                (
+                    "export".into(),
+                    "Export chat to a text or markdown file".into(),
+                ),
+                ("history".into(), "Show or search input history".into()),
             ],
+            templates: Vec::new(),
         }
     }
 
     pub fn add_templates(&mut self, templates: Vec<(String, String)>) {
         for (cmd, desc) in templates {
             let cmd = cmd.trim_start_matches('/').to_string();
-            if !self.commands.iter().any(|(c, _)| c == &cmd) {
-                self.commands.push((cmd, desc));
+            if !self
+                .commands
+                .iter()
+                .chain(&self.templates)
+                .any(|(c, _)| c == &cmd)
+            {
+                self.templates.push((cmd, desc));
             }
         }
     }
+
+    /// Replace all template commands, e.g. after `/templates reload`
+    pub fn set_templates(&mut self, templates: Vec<(String, String)>) {
+        self.templates.clear();
+        self.add_templates(templates);
+    }
 }
 
 impl Default for CommandCompleter {
@@ -415,7 +556,9 @@ impl Completer for CommandCompleter {
 
         self.commands
             .iter()
-            .filter(|(cmd, _)| cmd.starts_with(&input_lower) || cmd.contains(&input_lower))
+            .chain(&self.templates)
+            // Loose subsequence filter; the engine decides whether fuzzy matches are kept
+            .filter(|(cmd, _)| fuzzy_score(&input_lower, cmd) > 0.0)
             .map(|(cmd, desc)| {
                 Completion::new(format!("/{}", cmd), CompletionKind::Command)
                     .with_description(desc.clone())
@@ -431,11 +574,59 @@ impl Completer for CommandCompleter {
 /// Completes file and directory paths
 pub struct FileCompleter {
     show_hidden: bool,
+    /// Fall back to subsequence matching (including one level of subdirectories)
+    /// when nothing in the search directory starts with the typed name
+    fuzzy: bool,
+    max_items: Option<usize>,
 }
 
 impl FileCompleter {
     pub fn new() -> Self {
-        Self { show_hidden: false }
+        Self {
+            show_hidden: false,
+            fuzzy: false,
+            max_items: None,
+        }
+    }
+
+    pub fn with_fuzzy(mut self, fuzzy: bool) -> Self {
+        self.fuzzy = fuzzy;
+        self
+    }
+
+    pub fn with_max_items(mut self, max: usize) -> Self {
+        self.max_items = Some(max);
+        self
+    }
+
+    /// Entries of `dir` whose names fuzzy-match `query`, as (name relative to
+    /// `dir`, is_dir, score). With `descend`, non-hidden subdirectories are
+    /// searched one level deep too.
+    fn fuzzy_entries(&self, dir: &Path, query: &str, descend: bool) -> Vec<(PathBuf, bool, f32)> {
+        let mut found = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return found;
+        };
+        let query = query.to_lowercase();
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+            if !self.show_hidden && !query.starts_with('.') && name_str.starts_with('.') {
+                continue;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let score = fuzzy_score(&query, &name_str.to_lowercase());
+            if score > 0.0 {
+                found.push((PathBuf::from(&name), is_dir, score));
+            }
+            if descend && is_dir && !name_str.starts_with('.') {
+                for (child, child_is_dir, score) in self.fuzzy_entries(&entry.path(), &query, false)
+                {
+                    found.push((Path::new(&name).join(child), child_is_dir, score));
+                }
+            }
+        }
+        found
     }
 }
 
@@ -520,12 +711,42 @@ impl Completer for FileCompleter {
             }
         }
 
-        // Sort: directories first, then alphabetically
-        completions.sort_by(|a, b| match (a.kind, b.kind) {
-            (CompletionKind::Directory, CompletionKind::File) => std::cmp::Ordering::Less,
-            (CompletionKind::File, CompletionKind::Directory) => std::cmp::Ordering::Greater,
-            _ => a.text.to_lowercase().cmp(&b.text.to_lowercase()),
+        // Nothing starts with the typed name: try subsequence matches, e.g. `main` → `src/main.rs`
+        if completions.is_empty() && self.fuzzy && !prefix.is_empty() {
+            let parent = if input.contains('/') || input.contains(std::path::MAIN_SEPARATOR) {
+                Path::new(input).parent().unwrap_or(Path::new(""))
+            } else {
+                Path::new("")
+            };
+            for (relative, is_dir, score) in self.fuzzy_entries(&search_dir, &prefix, true) {
+                let mut path = parent.join(relative);
+                let kind = if is_dir {
+                    path.push("");
+                    CompletionKind::Directory
+                } else {
+                    CompletionKind::File
+                };
+                completions.push(Completion::new(path.to_string_lossy(), kind).with_score(score));
+            }
+        }
+
+        // Sort: directories first, then best fuzzy score, then alphabetically.
+        // Prefix matches all score 1.0, so only fuzzy results are reordered
+        // by score and the cap below keeps the closest ones.
+        completions.sort_by(|a, b| {
+            let is_file = |c: &Completion| c.kind != CompletionKind::Directory;
+            is_file(a)
+                .cmp(&is_file(b))
+                .then_with(|| {
+                    b.score
+                        .partial_cmp(&a.score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .then_with(|| a.text.to_lowercase().cmp(&b.text.to_lowercase()))
         });
+        if let Some(max) = self.max_items {
+            completions.truncate(max);
+        }
 
         completions
     }
@@ -561,6 +782,55 @@ impl Completer for ModelCompleter {
     }
 }
 
+/// Completes saved session names (for /load)
+pub struct SessionCompleter {
+    /// Directory to list; the project's session directory when unset
+    dir: Option<PathBuf>,
+}
+
+impl SessionCompleter {
+    pub fn new() -> Self {
+        Self { dir: None }
+    }
+
+    #[cfg(test)]
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+}
+
+impl Default for SessionCompleter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Completer for SessionCompleter {
+    fn complete(&self, input: &str, _context: &CompletionContext) -> Vec<Completion> {
+        let Some(dir) = self.dir.clone().or_else(Session::session_dir) else {
+            return Vec::new();
+        };
+        let input_lower = input.to_lowercase();
+
+        // Most recently updated first
+        Session::summaries_in(&dir)
+            .into_iter()
+            .filter(|s| s.name.to_lowercase().starts_with(&input_lower))
+            .map(|s| {
+                let count = s.message_count.map_or("?".to_string(), |n| n.to_string());
+                Completion::new(s.name, CompletionKind::Session)
+                    .with_description(format!("{} message(s)", count))
+            })
+            .collect()
+    }
+
+    fn name(&self) -> &'static str {
+        "SessionCompleter"
+    }
+}
+
 /// Completes files currently in context (for /remove)
 pub struct ContextFileCompleter;
 
@@ -594,15 +864,33 @@ impl Completer for HelpCompleter {
             ("help", "Show help information"),
             ("exit", "Exit the REPL"),
             ("clear", "Clear conversation history"),
+            ("compact", "Summarize old messages"),
+            ("edit", "Edit and re-send last message"),
+            ("retry", "Regenerate last response"),
             ("model", "Show or change model"),
             ("context", "Show context summary"),
             ("tokens", "Show token usage"),
+            ("cost", "Estimate session token usage"),
+            ("temp", "Override temperature"),
+            ("top_p", "Override top-p"),
+            ("json", "Force JSON output"),
+            ("resend-with", "Ask another model once"),
+            ("mark", "Bookmark this point"),
+            ("theme", "Switch color theme"),
+            ("marks", "List bookmarks"),
+            ("load", "Load a saved session"),
             ("files", "List files in context"),
+            ("tree", "Show context files as a tree"),
             ("add", "Add file to context"),
+            ("add-url", "Fetch a URL into context"),
             ("remove", "Remove file from context"),
             ("fileops", "Toggle file operations"),
             ("templates", "List templates"),
             ("rules", "Show loaded rules"),
+            ("undo", "Revert last applied file changes"),
+            ("explain-diff", "Explain last proposed changes"),
+            ("replay", "Re-run last exec blocks"),
+            ("history", "Show or search input history"),
         ];
 
         let input_lower = input.to_lowercase();
@@ -630,14 +918,9 @@ impl Completer for HistoryCompleter {
         let input_lower = input.to_lowercase();
 
         // Get unique history entries that match, most recent first
-        let mut seen = std::collections::HashSet::new();
-        context
-            .history
-            .iter()
-            .rev() // Most recent first
-            .filter(|h| h.to_lowercase().starts_with(&input_lower) && seen.insert(h.to_lowercase()))
+        matching_history(context.history, |h| h.starts_with(&input_lower))
             .take(5) // Limit history suggestions
-            .map(|h| {
+            .map(|(_, h)| {
                 Completion::new(h.clone(), CompletionKind::History)
                     .with_description("from history".to_string())
                     .with_score(0.8) // Slightly lower than exact matches
@@ -650,6 +933,25 @@ impl Completer for HistoryCompleter {
     }
 }
 
+/// Unique history entries whose lowercased text satisfies `pred`, most recent first,
+/// paired with their index in `history`
+fn matching_history<'a>(
+    history: &'a [String],
+    pred: impl Fn(&str) -> bool + 'a,
+) -> impl Iterator<Item = (usize, &'a String)> + 'a {
+    let mut seen = std::collections::HashSet::new();
+    history.iter().enumerate().rev().filter(move |(_, h)| {
+        let lower = h.to_lowercase();
+        pred(&lower) && seen.insert(lower)
+    })
+}
+
+/// Search history for entries containing `query` (case-insensitive), most recent first
+pub fn search_history<'a>(history: &'a [String], query: &str) -> Vec<(usize, &'a String)> {
+    let query = query.to_lowercase();
+    matching_history(history, move |h| h.contains(&query)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -700,6 +1002,140 @@ mod tests {
         assert!(completions.iter().any(|c| c.text == "/model llama3"));
     }
 
+    #[test]
+    fn test_completion_engine_fuzzy_toggle() {
+        let context = CompletionContext {
+            context_files: vec![],
+            cwd: Path::new("."),
+            models: None,
+            history: &[],
+        };
+
+        let fuzzy = CompletionEngine::with_fuzzy(true);
+        let completions = fuzzy.complete("/hlp", &context);
+        assert!(completions.iter().any(|c| c.text == "/help"));
+
+        // With fuzzy off, a subsequence-only query matches nothing
+        let strict = CompletionEngine::with_fuzzy(false);
+        assert!(strict.complete("/hlp", &context).is_empty());
+        // Prefix and substring matches still work
+        assert!(strict
+            .complete("/he", &context)
+            .iter()
+            .any(|c| c.text == "/help"));
+        assert!(strict
+            .complete("/plat", &context)
+            .iter()
+            .any(|c| c.text == "/templates"));
+    }
+
+    #[test]
+    fn test_file_completer_fuzzy_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/repl")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/mod_a.rs"), "").unwrap();
+        std::fs::write(dir.path().join("Makefile"), "").unwrap();
+        let context = CompletionContext {
+            context_files: vec![],
+            cwd: dir.path(),
+            models: None,
+            history: &[],
+        };
+        let texts = |completions: Vec<Completion>| -> Vec<String> {
+            completions.into_iter().map(|c| c.text).collect()
+        };
+
+        // A prefix match wins, so no fuzzy results are mixed in
+        let fuzzy = FileCompleter::new().with_fuzzy(true);
+        assert_eq!(texts(fuzzy.complete("Ma", &context)), vec!["Makefile"]);
+
+        // No prefix match: subsequence matches one level down, directories first
+        let found = texts(fuzzy.complete("main", &context));
+        assert_eq!(found, vec!["src/main.rs"]);
+        let found = texts(fuzzy.complete("rpl", &context));
+        assert_eq!(found, vec!["src/repl/"]);
+
+        // Results are capped
+        let capped = FileCompleter::new().with_fuzzy(true).with_max_items(1);
+        assert_eq!(capped.complete("s", &context).len(), 1);
+
+        // The cap keeps the best-scoring fuzzy match, not the first alphabetically
+        let ranked = tempfile::tempdir().unwrap();
+        std::fs::write(ranked.path().join("amxoxd.txt"), "").unwrap();
+        std::fs::write(ranked.path().join("xmod.rs"), "").unwrap();
+        let ranked_context = CompletionContext {
+            context_files: vec![],
+            cwd: ranked.path(),
+            models: None,
+            history: &[],
+        };
+        let found = texts(capped.complete("mod", &ranked_context));
+        assert_eq!(found, vec!["xmod.rs"]);
+
+        // Directories still come first, on equal scores and before the cap
+        let mixed = tempfile::tempdir().unwrap();
+        std::fs::create_dir(mixed.path().join("zmod")).unwrap();
+        std::fs::write(mixed.path().join("amod"), "").unwrap();
+        std::fs::create_dir(mixed.path().join("xmxoxd")).unwrap();
+        let mixed_context = CompletionContext {
+            context_files: vec![],
+            cwd: mixed.path(),
+            models: None,
+            history: &[],
+        };
+        let found = texts(fuzzy.complete("mod", &mixed_context));
+        assert_eq!(found, vec!["zmod/", "xmxoxd/", "amod"]);
+        std::fs::remove_dir(mixed.path().join("zmod")).unwrap();
+        let found = texts(capped.complete("mod", &mixed_context));
+        assert_eq!(found, vec!["xmxoxd/"]);
+
+        // Without fuzzy matching there is no fallback
+        assert!(FileCompleter::new().complete("main", &context).is_empty());
+
+        // Through the engine, `/add main` finds the nested file
+        let engine = CompletionEngine::with_fuzzy(true).with_max_file_items(10);
+        assert!(
+            texts(engine.complete("/add main", &context)).contains(&"/add src/main.rs".to_string())
+        );
+    }
+
+    #[test]
+    fn test_session_completer_lists_saved_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["work", "wip-parser", ".autosave"] {
+            let session = Session::new(name, "qwen2.5:7b");
+            std::fs::write(
+                dir.path().join(format!("{}.json", name)),
+                serde_json::to_string(&session).unwrap(),
+            )
+            .unwrap();
+        }
+        let context = CompletionContext {
+            context_files: vec![],
+            cwd: dir.path(),
+            models: None,
+            history: &[],
+        };
+        let completer = SessionCompleter::in_dir(dir.path());
+
+        let mut names: Vec<String> = completer
+            .complete("w", &context)
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["wip-parser", "work"]);
+
+        let all = completer.complete("", &context);
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|c| c.kind == CompletionKind::Session));
+        assert_eq!(all[0].description.as_deref(), Some("0 message(s)"));
+
+        // The autosave is never offered, even for a dot prefix
+        assert!(completer.complete(".", &context).is_empty());
+    }
+
     #[test]
     fn test_at_reference_after_command() {
         let engine = CompletionEngine::new();
@@ -747,4 +1183,64 @@ mod tests {
             completions.iter().map(|c| &c.text).collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn test_at_token_at_cursor() {
+        let input = "explain @src/ma and @README.md please";
+        // Cursor right after "@src/ma"
+        assert_eq!(at_token_at(input, 15), Some(8..15));
+        // Cursor in the middle of the second reference
+        assert_eq!(at_token_at(input, 23), Some(20..30));
+        assert_eq!(&input[20..30], "@README.md");
+        // Cursor on a plain word or whitespace
+        assert_eq!(at_token_at(input, 3), None);
+        assert_eq!(at_token_at(input, 34), None);
+        // An email-style @ is not a reference
+        assert_eq!(at_token_at("mail me@host", 12), None);
+        assert_eq!(at_token_at("@", 1), Some(0..1));
+    }
+
+    #[test]
+    fn test_suggest_similar_models() {
+        let models: Vec<String> = vec![
+            "qwen2.5:7b".into(),
+            "qwen2.5-coder:14b".into(),
+            "llama3:latest".into(),
+            "mistral:7b".into(),
+        ];
+
+        // Wrong tag on an existing model
+        assert_eq!(
+            suggest_similar("llama3:8b", &models, 3),
+            vec!["llama3:latest"]
+        );
+        // Abbreviated name
+        let qwen = suggest_similar("qwen:7b", &models, 3);
+        assert_eq!(qwen.first(), Some(&"qwen2.5:7b"));
+        assert!(!qwen.contains(&"llama3:latest"));
+        // Nothing close
+        assert!(suggest_similar("phi3", &models, 3).is_empty());
+    }
+
+    #[test]
+    fn test_search_history_substring() {
+        let history: Vec<String> = vec![
+            "explain @main.rs".into(),
+            "/add src/".into(),
+            "Explain the parser".into(),
+            "/model qwen".into(),
+            "explain @main.rs".into(),
+        ];
+
+        let matches = search_history(&history, "explain");
+        let indices: Vec<usize> = matches.iter().map(|(i, _)| *i).collect();
+        // Most recent first, duplicates collapsed, case-insensitive
+        assert_eq!(indices, vec![4, 2]);
+
+        let matches = search_history(&history, "src");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].1, "/add src/");
+
+        assert!(search_history(&history, "nothing").is_empty());
+    }
 }
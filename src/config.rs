@@ -4,6 +4,73 @@ use std::path::PathBuf;
 
 use crate::error::{Result, SlabError};
 
+/// Keys accepted by `slab config --set/get/unset`
+pub const CONFIG_KEYS: &[&str] = &[
+    "ollama_host",
+    "backend",
+    "api_key",
+    "default_model",
+    "default_template",
+    "auto_prune",
+    "system_prompt_file",
+    "context_limit",
+    "ollama.max_retries",
+    "ollama.retry_base_ms",
+    "ui.streaming",
+    "ui.auto_apply_file_ops",
+    "ui.inline_completion_preview",
+    "ui.fuzzy_completion",
+    "ui.max_completion_items",
+    "ui.theme",
+    "ui.box_style",
+    "ui.show_status_bar",
+    "ui.show_banner",
+    "ui.code_block_style",
+    "ui.code_line_numbers",
+    "ui.diff_style",
+    "ui.allowed_file_ops",
+    "ui.show_stream_progress",
+    "ui.max_file_tokens",
+    "ui.strip_think_blocks",
+    "ui.exec_output_max_lines",
+    "ui.autosave_interval",
+    "ui.prompt_format",
+    "ui.submit_on_enter",
+    "ui.keep_partial_on_interrupt",
+];
+
+/// Alternative names for `ollama_host`, accepted in config files and by `slab config`
+const KEY_ALIASES: &[&str] = &["host", "api_base"];
+
+/// Keys that are valid in a config file but not settable with `slab config --set`
+const FILE_ONLY_KEYS: &[&str] = &[
+    "system_prompt",
+    "paths.templates",
+    "paths.rules",
+    "ui.custom_theme",
+    "hooks.pre_apply",
+    "hooks.post_apply",
+];
+
+/// Keys of a `[models.<name>]` table
+const MODEL_KEYS: &[&str] = &[
+    "name",
+    "temperature",
+    "top_p",
+    "system_prompt",
+    "system_prompt_file",
+    "prompt_cost_per_1k",
+    "completion_cost_per_1k",
+];
+
+/// Resolve key aliases to the name stored in the config file
+fn canonical_key(key: &str) -> &str {
+    match key {
+        "host" | "api_base" => "ollama_host",
+        other => other,
+    }
+}
+
 /// Which LLM inference backend to use.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -17,9 +84,10 @@ pub enum BackendType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    /// Base URL for the LLM server. Accepts "host" as an alias so new configs
-    /// can use the backend-neutral name while old configs with "ollama_host" keep working.
-    #[serde(default = "default_ollama_host", alias = "host")]
+    /// Base URL for the LLM server. Accepts "host" (and "api_base" for OpenAI-compatible
+    /// servers) as aliases so new configs can use a backend-neutral name while old
+    /// configs with "ollama_host" keep working.
+    #[serde(default = "default_ollama_host", alias = "host", alias = "api_base")]
     pub ollama_host: String,
 
     /// Which backend protocol to speak. Defaults to "ollama".
@@ -34,19 +102,47 @@ pub struct Config {
     #[serde(default)]
     pub default_model: Option<String>,
 
-    #[serde(default = "default_context_limit")]
-    pub context_limit: usize,
+    /// Template `slab chat` sends first when no `--template` is given
+    #[serde(default)]
+    pub default_template: Option<String>,
+
+    /// Context window sent to the model as `num_ctx`. When unset, requests use
+    /// 32768 and the REPL budgets against the model's native window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_limit: Option<usize>,
+
+    /// Drop the oldest messages instead of erroring when the context is over budget
+    #[serde(default)]
+    pub auto_prune: bool,
 
-    /// Default system prompt used when no model-specific prompt is set
+    /// Global system prompt applied to every model. A model's own prompt is appended after it.
     #[serde(default = "default_system_prompt")]
     pub system_prompt: String,
 
+    /// Read the global system prompt from this file instead of `system_prompt`
+    #[serde(default)]
+    pub system_prompt_file: Option<PathBuf>,
+
+    /// Ad-hoc system prompt from `--system`. Replaces every configured layer and is never saved.
+    #[serde(skip)]
+    pub system_prompt_override: Option<String>,
+
     #[serde(default)]
     pub models: HashMap<String, ModelConfig>,
 
+    /// Shortcut slash commands: name -> template command plus preset arguments
+    #[serde(default)]
+    pub commands: HashMap<String, String>,
+
     #[serde(default)]
     pub paths: PathsConfig,
 
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+
+    #[serde(default)]
+    pub hooks: HooksConfig,
+
     #[serde(default)]
     pub ui: UiConfig,
 }
@@ -63,6 +159,18 @@ pub struct ModelConfig {
 
     #[serde(default)]
     pub system_prompt: Option<String>,
+
+    /// Read the model system prompt from this file instead of `system_prompt`
+    #[serde(default)]
+    pub system_prompt_file: Option<PathBuf>,
+
+    /// Price per 1,000 prompt tokens, for the `/cost` estimate
+    #[serde(default)]
+    pub prompt_cost_per_1k: Option<f64>,
+
+    /// Price per 1,000 completion tokens, for the `/cost` estimate
+    #[serde(default)]
+    pub completion_cost_per_1k: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -74,11 +182,127 @@ pub struct PathsConfig {
     pub rules: Option<PathBuf>,
 }
 
+/// Retry behaviour for requests to an Ollama server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    /// Extra attempts after a connection failure, timeout or 5xx response
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay before the first retry; doubled for each one after it
+    #[serde(default = "default_retry_base_ms")]
+    pub retry_base_ms: u64,
+}
+
+/// Shell commands run around applying file operations. `{{files}}` expands to
+/// the affected paths, shell-quoted and relative to the project root.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HooksConfig {
+    /// Runs before operations are applied; a non-zero exit applies none of them
+    #[serde(default)]
+    pub pre_apply: Option<String>,
+
+    /// Runs after operations are applied, on the files that now exist
+    #[serde(default)]
+    pub post_apply: Option<String>,
+
+    /// Whether these hooks may run without asking. Hooks from a project's
+    /// `.slab/config.toml` start as `Ask`, since cloning a repository should not
+    /// be enough to run its commands.
+    #[serde(skip)]
+    pub trust: HookTrust,
+}
+
+/// Whether `[hooks]` commands may run this session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HookTrust {
+    /// From the global config or `--config`: run without asking
+    #[default]
+    Trusted,
+    /// From a project config: ask once before the first hook runs
+    Ask,
+    /// The user declined (or there was no terminal to ask on): never run
+    Refused,
+}
+
+/// Colors for `ui.theme = "custom"`, keyed by role: a color name, a 256-color
+/// index or `#rrggbb`. Unset roles use the default theme.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CustomThemeConfig {
+    #[serde(default)]
+    pub primary: Option<String>,
+    #[serde(default)]
+    pub secondary: Option<String>,
+    #[serde(default)]
+    pub success: Option<String>,
+    #[serde(default)]
+    pub warning: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub muted: Option<String>,
+    #[serde(default)]
+    pub accent: Option<String>,
+    #[serde(default)]
+    pub border: Option<String>,
+}
+
+impl CustomThemeConfig {
+    /// A message for every role whose color is not a name, index or `#rrggbb`,
+    /// since such a role silently keeps its default style
+    pub fn color_warnings(&self) -> Vec<String> {
+        let roles = [
+            ("primary", &self.primary),
+            ("secondary", &self.secondary),
+            ("success", &self.success),
+            ("warning", &self.warning),
+            ("error", &self.error),
+            ("muted", &self.muted),
+            ("accent", &self.accent),
+            ("border", &self.border),
+        ];
+        roles
+            .into_iter()
+            .filter_map(|(role, spec)| {
+                let spec = spec.as_deref()?;
+                crate::theme::parse_color(spec)
+                    .is_none()
+                    .then(|| format!("unrecognised color '{}' for ui.custom_theme.{}", spec, role))
+            })
+            .collect()
+    }
+}
+
+/// Keys of the `[ui.custom_theme]` table
+const CUSTOM_THEME_KEYS: &[&str] = &[
+    "primary",
+    "secondary",
+    "success",
+    "warning",
+    "error",
+    "muted",
+    "accent",
+    "border",
+];
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            retry_base_ms: default_retry_base_ms(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
     #[serde(default = "default_theme")]
     pub theme: String,
 
+    /// Role colors used when `theme` is "custom"
+    #[serde(default)]
+    pub custom_theme: CustomThemeConfig,
+
     #[serde(default = "default_streaming")]
     pub streaming: bool,
 
@@ -110,19 +334,64 @@ pub struct UiConfig {
     #[serde(default)]
     pub show_banner: bool,
 
-    /// Code block style: "bordered" or "plain"
+    /// Syntect theme used for code blocks (e.g. "base16-ocean.dark", "InspiredGitHub")
     #[serde(default = "default_code_block_style")]
     pub code_block_style: String,
 
+    /// Prefix highlighted code lines with line numbers
+    #[serde(default)]
+    pub code_line_numbers: bool,
+
     /// Diff style: "unified" or "side-by-side"
     #[serde(default = "default_diff_style")]
     pub diff_style: String,
+
+    /// File operation kinds the model may propose: "create", "edit", "delete", "rename"
+    #[serde(default = "default_allowed_file_ops")]
+    pub allowed_file_ops: Vec<String>,
+
+    /// Show a token/elapsed counter while a stream has produced no visible text yet
+    #[serde(default = "default_true")]
+    pub show_stream_progress: bool,
+
+    /// Remove `<think>...</think>` reasoning from responses before they reach
+    /// history and file-op parsing (shown dimmed while streaming)
+    #[serde(default)]
+    pub strip_think_blocks: bool,
+
+    /// Refuse to add files estimated above this many tokens (unlimited when unset)
+    #[serde(default)]
+    pub max_file_tokens: Option<usize>,
+
+    /// Lines of `/exec` stdout/stderr kept in context; longer output keeps the
+    /// head and tail around an elision marker (0 keeps everything)
+    #[serde(default = "default_exec_output_max_lines")]
+    pub exec_output_max_lines: usize,
+
+    /// Write the conversation to `.slab/sessions/.autosave.json` every this many
+    /// messages so `slab chat --continue` can recover it (off when unset)
+    #[serde(default)]
+    pub autosave_interval: Option<usize>,
+
+    /// Custom input prompt with `{model}`, `{bar}`, `{files}`, `{tokens}` and
+    /// `{cwd}` placeholders (the built-in prompt when unset)
+    #[serde(default)]
+    pub prompt_format: Option<String>,
+
+    /// Enter submits the prompt; when false Enter inserts a newline and Ctrl+J submits
+    #[serde(default = "default_true")]
+    pub submit_on_enter: bool,
+
+    /// On Ctrl+C while streaming, keep the text received so far as the assistant's reply
+    #[serde(default = "default_true")]
+    pub keep_partial_on_interrupt: bool,
 }
 
 impl Default for UiConfig {
     fn default() -> Self {
         Self {
             theme: default_theme(),
+            custom_theme: CustomThemeConfig::default(),
             streaming: default_streaming(),
             auto_apply_file_ops: false,
             inline_completion_preview: true,
@@ -132,7 +401,17 @@ impl Default for UiConfig {
             show_status_bar: true,
             show_banner: false,
             code_block_style: default_code_block_style(),
+            code_line_numbers: false,
             diff_style: default_diff_style(),
+            allowed_file_ops: default_allowed_file_ops(),
+            show_stream_progress: true,
+            strip_think_blocks: false,
+            max_file_tokens: None,
+            exec_output_max_lines: default_exec_output_max_lines(),
+            autosave_interval: None,
+            prompt_format: None,
+            submit_on_enter: true,
+            keep_partial_on_interrupt: true,
         }
     }
 }
@@ -141,6 +420,25 @@ fn default_true() -> bool {
     true
 }
 
+fn default_allowed_file_ops() -> Vec<String> {
+    ["create", "edit", "delete", "rename"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_retry_base_ms() -> u64 {
+    500
+}
+
+fn default_exec_output_max_lines() -> usize {
+    200
+}
+
 fn default_max_completions() -> usize {
     10
 }
@@ -149,10 +447,22 @@ fn default_ollama_host() -> String {
     "http://localhost:11434".to_string()
 }
 
-fn default_context_limit() -> usize {
-    32768
+/// Normalize an `OLLAMA_HOST` value. Ollama accepts bare `host:port`, so add a scheme
+/// when one is missing; empty values are ignored.
+fn host_from_env(value: Option<String>) -> Option<String> {
+    let value = value?;
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else if value.contains("://") {
+        Some(value.to_string())
+    } else {
+        Some(format!("http://{}", value))
+    }
 }
 
+const DEFAULT_CONTEXT_LIMIT: usize = 32768;
+
 fn default_temperature() -> f32 {
     0.7
 }
@@ -174,7 +484,7 @@ fn default_box_style() -> String {
 }
 
 fn default_code_block_style() -> String {
-    "bordered".to_string()
+    crate::highlight::DEFAULT_SYNTAX_THEME.to_string()
 }
 
 fn default_diff_style() -> String {
@@ -211,6 +521,12 @@ Examples:
 - DELETE:temp/cache.json
 - DELETE:unused.txt
 
+## Renaming Files
+
+To rename or move a file without changing its contents, output a rename marker on its own line:
+
+RENAME:old/path.ext -> new/path.ext
+
 The user will be prompted to confirm all file operations before they are applied.
 
 ## Running Commands
@@ -231,10 +547,17 @@ impl Default for Config {
             backend: BackendType::default(),
             api_key: None,
             default_model: None,
-            context_limit: default_context_limit(),
+            default_template: None,
+            context_limit: None,
+            auto_prune: false,
             system_prompt: default_system_prompt(),
+            system_prompt_file: None,
+            system_prompt_override: None,
             models: HashMap::new(),
+            commands: HashMap::new(),
             paths: PathsConfig::default(),
+            ollama: OllamaConfig::default(),
+            hooks: HooksConfig::default(),
             ui: UiConfig::default(),
         }
     }
@@ -252,7 +575,9 @@ impl Config {
         if let Some(project_root) = find_project_root() {
             let local_config = project_root.join(".slab/config.toml");
             if local_config.exists() {
-                return Self::load_from_path(&local_config);
+                let mut config = Self::load_from_path(&local_config)?;
+                config.hooks.trust = HookTrust::Ask;
+                return Ok(config);
             }
         }
 
@@ -267,9 +592,36 @@ impl Config {
         Ok(Self::default())
     }
 
+    /// Apply environment overrides on top of the loaded file values.
+    /// Kept out of `load` so `slab config set` never persists them.
+    pub fn apply_env_overrides(&mut self) {
+        self.apply_ollama_host_env(std::env::var("OLLAMA_HOST").ok());
+    }
+
+    /// Apply a value of `OLLAMA_HOST`. It only applies to the Ollama backend, so it
+    /// never replaces an OpenAI-compatible `api_base`.
+    fn apply_ollama_host_env(&mut self, ollama_host: Option<String>) {
+        if self.backend != BackendType::Ollama {
+            return;
+        }
+        if let Some(host) = host_from_env(ollama_host) {
+            self.ollama_host = host;
+        }
+    }
+
     fn load_from_path(path: &PathBuf) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
         let config: Config = toml::from_str(&content)?;
+        let table = content.parse::<toml::Table>().ok();
+
+        // Unknown keys are ignored by serde; say so instead of silently dropping them
+        let warnings = table.as_ref().map(unknown_key_warnings).unwrap_or_default();
+        for warning in warnings
+            .into_iter()
+            .chain(config.ui.custom_theme.color_warnings())
+        {
+            eprintln!("Warning: {} in {}", warning, path.display());
+        }
         Ok(config)
     }
 
@@ -285,6 +637,250 @@ impl Config {
         }
     }
 
+    /// Set a dotted config key (as used by `slab config --set`) from its string form
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<()> {
+        match canonical_key(key) {
+            "ollama_host" => self.ollama_host = value.to_string(),
+            "backend" => {
+                self.backend = match value {
+                    "ollama" => BackendType::Ollama,
+                    "openai" | "openai-compat" | "openai_compat" => BackendType::OpenAi,
+                    other => {
+                        return Err(SlabError::ConfigError(format!(
+                            "Unknown backend '{}'. Valid values: ollama, openai",
+                            other
+                        )))
+                    }
+                };
+            }
+            "api_key" => {
+                self.api_key = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.to_string())
+                };
+            }
+            "default_model" => {
+                self.default_model = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.to_string())
+                };
+            }
+            "default_template" => {
+                self.default_template = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.trim_start_matches('/').to_string())
+                };
+            }
+            "auto_prune" => {
+                self.auto_prune = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "system_prompt_file" => {
+                self.system_prompt_file = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.into())
+                };
+            }
+            "context_limit" => {
+                self.context_limit = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.parse().map_err(|_| {
+                        SlabError::ConfigError("Invalid context_limit value".to_string())
+                    })?)
+                };
+            }
+            "ui.streaming" => {
+                self.ui.streaming = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.auto_apply_file_ops" => {
+                self.ui.auto_apply_file_ops = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.inline_completion_preview" => {
+                self.ui.inline_completion_preview = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.fuzzy_completion" => {
+                self.ui.fuzzy_completion = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.max_completion_items" => {
+                self.ui.max_completion_items = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid number value".to_string()))?;
+            }
+            "ui.theme" => {
+                self.ui.theme = value.to_string();
+            }
+            "ui.box_style" => {
+                self.ui.box_style = value.to_string();
+            }
+            "ui.show_status_bar" => {
+                self.ui.show_status_bar = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.show_banner" => {
+                self.ui.show_banner = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.code_block_style" => {
+                self.ui.code_block_style = value.to_string();
+            }
+            "ui.code_line_numbers" => {
+                self.ui.code_line_numbers = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.diff_style" => {
+                self.ui.diff_style = value.to_string();
+            }
+            "ui.allowed_file_ops" => {
+                let ops: Vec<String> = value
+                    .split(',')
+                    .map(|op| op.trim().to_ascii_lowercase())
+                    .filter(|op| !op.is_empty())
+                    .collect();
+                if let Some(bad) = ops
+                    .iter()
+                    .find(|op| !["create", "edit", "delete", "rename"].contains(&op.as_str()))
+                {
+                    return Err(SlabError::ConfigError(format!(
+                        "Unknown file operation: {} (expected create, edit, delete, rename)",
+                        bad
+                    )));
+                }
+                self.ui.allowed_file_ops = ops;
+            }
+            "ui.show_stream_progress" => {
+                self.ui.show_stream_progress = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.submit_on_enter" => {
+                self.ui.submit_on_enter = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.keep_partial_on_interrupt" => {
+                self.ui.keep_partial_on_interrupt = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.strip_think_blocks" => {
+                self.ui.strip_think_blocks = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
+            }
+            "ui.max_file_tokens" => {
+                self.ui.max_file_tokens =
+                    if value.is_empty() {
+                        None
+                    } else {
+                        Some(value.parse().map_err(|_| {
+                            SlabError::ConfigError("Invalid number value".to_string())
+                        })?)
+                    };
+            }
+            "ui.autosave_interval" => {
+                self.ui.autosave_interval =
+                    if value.is_empty() {
+                        None
+                    } else {
+                        Some(value.parse().map_err(|_| {
+                            SlabError::ConfigError("Invalid number value".to_string())
+                        })?)
+                    };
+            }
+            "ui.prompt_format" => {
+                self.ui.prompt_format = (!value.is_empty()).then(|| value.to_string());
+            }
+            "ui.exec_output_max_lines" => {
+                self.ui.exec_output_max_lines = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid number value".to_string()))?;
+            }
+            "ollama.max_retries" => {
+                self.ollama.max_retries = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid number value".to_string()))?;
+            }
+            "ollama.retry_base_ms" => {
+                self.ollama.retry_base_ms = value
+                    .parse()
+                    .map_err(|_| SlabError::ConfigError("Invalid number value".to_string()))?;
+            }
+            _ => {
+                return Err(SlabError::ConfigError(format!(
+                    "Unknown config key: {}",
+                    key
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a dotted config key as the string `set_value` accepts; unset optional keys read as ""
+    pub fn get_value(&self, key: &str) -> Result<String> {
+        let key = canonical_key(key);
+        if !CONFIG_KEYS.contains(&key) {
+            return Err(SlabError::ConfigError(format!(
+                "Unknown config key: {}",
+                key
+            )));
+        }
+
+        let root =
+            toml::Value::try_from(self).map_err(|e| SlabError::ConfigError(e.to_string()))?;
+        let value = key.split('.').try_fold(&root, |v, part| v.get(part));
+        Ok(match value {
+            None => String::new(),
+            Some(toml::Value::String(s)) => s.clone(),
+            Some(toml::Value::Array(items)) => items
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(String::from)
+                        .unwrap_or_else(|| v.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(other) => other.to_string(),
+        })
+    }
+
+    /// Reset a dotted config key to its default value
+    pub fn unset_value(&mut self, key: &str) -> Result<()> {
+        let default = Config::default().get_value(key)?;
+        self.set_value(key, &default)
+    }
+
+    /// Resolve a `[commands]` shortcut to its template command and preset arguments
+    pub fn resolve_command(&self, name: &str) -> Option<(String, Vec<String>)> {
+        let expansion = self.commands.get(name.trim_start_matches('/'))?;
+        let mut words = expansion.split_whitespace();
+        let template = words.next()?.trim_start_matches('/').to_string();
+        Some((template, words.map(String::from).collect()))
+    }
+
+    /// The `num_ctx` to request: `context_limit` if set, otherwise 32768
+    pub fn effective_context_limit(&self) -> usize {
+        self.context_limit.unwrap_or(DEFAULT_CONTEXT_LIMIT)
+    }
+
     /// Get the model config for a given model name, or create a default one
     pub fn get_model_config(&self, model: &str) -> ModelConfig {
         let mut config = self
@@ -296,27 +892,190 @@ impl Config {
                 temperature: default_temperature(),
                 top_p: default_top_p(),
                 system_prompt: None,
+                system_prompt_file: None,
+                prompt_cost_per_1k: None,
+                completion_cost_per_1k: None,
             });
 
-        // Use the global system prompt if no model-specific one is set
-        if config.system_prompt.is_none() {
-            config.system_prompt = Some(self.system_prompt.clone());
+        if let Some(prompt) = &self.system_prompt_override {
+            config.system_prompt = Some(prompt.clone());
+            return config;
         }
 
+        // Layer the prompts: global first, then the model-specific one
+        let global = read_prompt_layer(self.system_prompt_file.as_ref())
+            .unwrap_or_else(|| self.system_prompt.clone());
+        let model_prompt = read_prompt_layer(config.system_prompt_file.as_ref())
+            .or_else(|| config.system_prompt.clone());
+
+        let layers: Vec<&str> = [Some(global.as_str()), model_prompt.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        config.system_prompt = if layers.is_empty() {
+            None
+        } else {
+            Some(layers.join("\n\n"))
+        };
+
         config
     }
 
     /// Save config to the project-local config file
     pub fn save(&self) -> Result<()> {
-        let path = Self::project_config_path();
+        self.save_to(&Self::project_config_path())
+    }
+
+    /// Write this config to `path`, creating its directory if needed
+    pub fn save_to(&self, path: &PathBuf) -> Result<()> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
         let content =
             toml::to_string_pretty(self).map_err(|e| SlabError::ConfigError(e.to_string()))?;
-        std::fs::write(&path, content)?;
+        std::fs::write(path, content)?;
         Ok(())
     }
+
+    /// Apply `update` to the config file at `path` alone (not merged with any other
+    /// config), starting from defaults if the file does not exist yet
+    pub fn update_file(path: &PathBuf, update: impl FnOnce(&mut Config)) -> Result<()> {
+        let mut config = if path.exists() {
+            Self::load_from_path(path)?
+        } else {
+            Self::default()
+        };
+        update(&mut config);
+        config.save_to(path)
+    }
+}
+
+/// A message for every key in a parsed config file that `Config` does not know,
+/// with the closest known key when one is near enough to be a typo
+fn unknown_key_warnings(table: &toml::Table) -> Vec<String> {
+    let known: Vec<&str> = CONFIG_KEYS
+        .iter()
+        .chain(KEY_ALIASES)
+        .chain(FILE_ONLY_KEYS)
+        .copied()
+        .collect();
+    let mut warnings = Vec::new();
+    let mut check = |key: String, known: &[&str]| {
+        if known.contains(&key.as_str()) {
+            return;
+        }
+        let mut warning = format!("unknown config key '{}'", key);
+        if let Some(nearest) = nearest_key(&key, known) {
+            warning.push_str(&format!(" (did you mean '{}'?)", nearest));
+        }
+        warnings.push(warning);
+    };
+
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("ui" | "paths" | "ollama" | "hooks", toml::Value::Table(section)) => {
+                for (sub, value) in section {
+                    check(format!("{}.{}", key, sub), &known);
+                    if let ("custom_theme", toml::Value::Table(roles)) = (sub.as_str(), value) {
+                        let role_keys: Vec<String> = CUSTOM_THEME_KEYS
+                            .iter()
+                            .map(|k| format!("ui.custom_theme.{}", k))
+                            .collect();
+                        let role_keys: Vec<&str> = role_keys.iter().map(String::as_str).collect();
+                        for role in roles.keys() {
+                            check(format!("ui.custom_theme.{}", role), &role_keys);
+                        }
+                    }
+                }
+            }
+            // Shortcut names are free-form
+            ("commands", toml::Value::Table(_)) => {}
+            ("models", toml::Value::Table(models)) => {
+                for (name, model) in models {
+                    let Some(model) = model.as_table() else {
+                        continue;
+                    };
+                    let model_keys: Vec<String> = MODEL_KEYS
+                        .iter()
+                        .map(|k| format!("models.{}.{}", name, k))
+                        .collect();
+                    let model_keys: Vec<&str> = model_keys.iter().map(String::as_str).collect();
+                    for field in model.keys() {
+                        check(format!("models.{}.{}", name, field), &model_keys);
+                    }
+                }
+            }
+            _ => check(key.clone(), &known),
+        }
+    }
+    warnings
+}
+
+/// The known key closest to `key` by edit distance, if it is close enough to be a typo
+fn nearest_key<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (key.len() / 3).max(2);
+    known
+        .iter()
+        .map(|k| (edit_distance(key, k), *k))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+/// Read a system prompt layer from a file. Relative paths resolve against the project root.
+/// Returns None (with a warning) if the file can't be read, so the inline prompt is used instead.
+fn read_prompt_layer(path: Option<&PathBuf>) -> Option<String> {
+    let path = path?;
+    let full_path = if path.is_absolute() {
+        path.clone()
+    } else {
+        find_project_root()
+            .map(|root| root.join(path))
+            .unwrap_or_else(|| path.clone())
+    };
+    match std::fs::read_to_string(&full_path) {
+        Ok(content) => Some(content),
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to read system prompt file {}: {}",
+                full_path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Walk up the directory tree from the current directory looking for a `.slab` directory.
+/// Returns the directory containing `.slab/` (the project root), or None.
+pub fn find_project_root() -> Option<PathBuf> {
+    let mut dir = std::env::current_dir().ok()?;
+    loop {
+        let slab_dir = dir.join(".slab");
+        if slab_dir.is_dir() {
+            return Some(dir);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
 }
 
 // ── Unit tests ────────────────────────────────────────────────────────────────
@@ -325,6 +1084,250 @@ impl Config {
 mod tests {
     use super::*;
 
+    #[test]
+    fn unknown_keys_are_reported_with_nearest_match() {
+        let table: toml::Table = r#"
+            default_model = "qwen2.5:7b"
+            defualt_model = "llama3"
+
+            [ui]
+            streeming = false
+            theme = "nord"
+
+            [models.qwen]
+            name = "qwen2.5:7b"
+            temprature = 0.2
+
+            [paths]
+            rules = ".slab/rules"
+        "#
+        .parse()
+        .unwrap();
+
+        let mut warnings = unknown_key_warnings(&table);
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            vec![
+                "unknown config key 'defualt_model' (did you mean 'default_model'?)",
+                "unknown config key 'models.qwen.temprature' (did you mean 'models.qwen.temperature'?)",
+                "unknown config key 'ui.streeming' (did you mean 'ui.streaming'?)",
+            ]
+        );
+
+        let table: toml::Table = "completely_unrelated = 1".parse().unwrap();
+        assert_eq!(
+            unknown_key_warnings(&table),
+            vec!["unknown config key 'completely_unrelated'"]
+        );
+    }
+
+    #[test]
+    fn custom_theme_table_is_read() {
+        let content = "[ui]\ntheme = \"custom\"\n\n[ui.custom_theme]\nprimary = \"#ff8800\"\nmuted = \"244\"\nacent = \"red\"\n";
+        let cfg: Config = toml::from_str(content).unwrap();
+        assert_eq!(cfg.ui.custom_theme.primary.as_deref(), Some("#ff8800"));
+        assert_eq!(cfg.ui.custom_theme.muted.as_deref(), Some("244"));
+        assert_eq!(cfg.ui.custom_theme.error, None);
+        assert_eq!(
+            unknown_key_warnings(&content.parse().unwrap()),
+            vec!["unknown config key 'ui.custom_theme.acent' (did you mean 'ui.custom_theme.accent'?)"]
+        );
+        assert!(cfg.ui.custom_theme.color_warnings().is_empty());
+
+        let cfg: Config =
+            toml::from_str("[ui.custom_theme]\nmuted = \"nope\"\nborder = \"#12345\"\n").unwrap();
+        assert_eq!(
+            cfg.ui.custom_theme.color_warnings(),
+            vec![
+                "unrecognised color 'nope' for ui.custom_theme.muted",
+                "unrecognised color '#12345' for ui.custom_theme.border",
+            ]
+        );
+    }
+
+    #[test]
+    fn default_template_and_command_aliases_are_read() {
+        let cfg: Config = toml::from_str(
+            r#"
+            default_template = "review"
+
+            [commands]
+            port = "/c-to-rust --package shim"
+            rv = "review"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.default_template.as_deref(), Some("review"));
+        assert_eq!(
+            cfg.resolve_command("port"),
+            Some((
+                "c-to-rust".to_string(),
+                vec!["--package".to_string(), "shim".to_string()]
+            ))
+        );
+        assert_eq!(
+            cfg.resolve_command("/rv"),
+            Some(("review".to_string(), vec![]))
+        );
+        assert_eq!(cfg.resolve_command("review"), None);
+        assert!(
+            unknown_key_warnings(&toml::from_str("[commands]\nrv = \"review\"").unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn get_value_reads_set_keys() {
+        let mut cfg = Config::default();
+        cfg.set_value("host", "http://gpu-box:11434").unwrap();
+        cfg.set_value("ui.theme", "dracula").unwrap();
+
+        assert_eq!(
+            cfg.get_value("ollama_host").unwrap(),
+            "http://gpu-box:11434"
+        );
+        assert_eq!(cfg.get_value("host").unwrap(), "http://gpu-box:11434");
+        cfg.set_value("api_base", "https://api.example.com")
+            .unwrap();
+        assert_eq!(
+            cfg.get_value("api_base").unwrap(),
+            "https://api.example.com"
+        );
+        assert_eq!(cfg.get_value("ui.theme").unwrap(), "dracula");
+        assert_eq!(cfg.get_value("context_limit").unwrap(), "");
+        assert_eq!(cfg.get_value("default_model").unwrap(), "");
+        assert!(cfg.get_value("no_such_key").is_err());
+    }
+
+    #[test]
+    fn unset_context_limit_is_not_saved() {
+        let saved = toml::to_string(&Config::default()).unwrap();
+        assert!(!saved.contains("context_limit"), "{}", saved);
+
+        let mut cfg = Config::default();
+        cfg.set_value("context_limit", "8192").unwrap();
+        let saved = toml::to_string(&cfg).unwrap();
+        let reloaded: Config = toml::from_str(&saved).unwrap();
+        assert_eq!(reloaded.context_limit, Some(8192));
+    }
+
+    #[test]
+    fn unset_value_reverts_to_default() {
+        let mut cfg = Config::default();
+        cfg.set_value("default_model", "qwen2.5:7b").unwrap();
+        cfg.set_value("context_limit", "8192").unwrap();
+        cfg.set_value("ui.allowed_file_ops", "create,edit").unwrap();
+
+        cfg.unset_value("default_model").unwrap();
+        cfg.unset_value("context_limit").unwrap();
+        cfg.unset_value("ui.allowed_file_ops").unwrap();
+
+        assert_eq!(cfg.default_model, None);
+        assert_eq!(cfg.context_limit, None);
+        assert_eq!(cfg.effective_context_limit(), 32768);
+        assert_eq!(cfg.ui.allowed_file_ops, default_allowed_file_ops());
+    }
+
+    #[test]
+    fn update_file_changes_only_that_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slab").join("config.toml");
+        Config::update_file(&path, |c| c.default_model = Some("first".into())).unwrap();
+
+        let mut cfg: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        cfg.context_limit = Some(4096);
+        cfg.save_to(&path).unwrap();
+        Config::update_file(&path, |c| c.default_model = Some("qwen2.5:7b".into())).unwrap();
+
+        let cfg: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(cfg.default_model.as_deref(), Some("qwen2.5:7b"));
+        assert_eq!(cfg.context_limit, Some(4096));
+    }
+
+    #[test]
+    fn every_config_key_round_trips() {
+        let mut cfg = Config::default();
+        for key in CONFIG_KEYS {
+            let value = cfg.get_value(key).unwrap();
+            cfg.set_value(key, &value)
+                .unwrap_or_else(|e| panic!("{} = {:?}: {}", key, value, e));
+        }
+    }
+
+    #[test]
+    fn ollama_host_env_overrides_config() {
+        let env_host = Some("http://gpu-box:11434".to_string());
+        let mut cfg: Config = toml::from_str("ollama_host = \"http://from-file:1\"").unwrap();
+        cfg.apply_ollama_host_env(env_host.clone());
+        let mut openai: Config =
+            toml::from_str("backend = \"openai\"\napi_base = \"https://api.example.com\"").unwrap();
+        openai.apply_ollama_host_env(env_host);
+        assert_eq!(cfg.ollama_host, "http://gpu-box:11434");
+        // An OpenAI-compatible api_base is not an Ollama host
+        assert_eq!(openai.ollama_host, "https://api.example.com");
+
+        // Without the variable the config value stands
+        let mut cfg: Config = toml::from_str("ollama_host = \"http://from-file:1\"").unwrap();
+        cfg.apply_ollama_host_env(None);
+        assert_eq!(cfg.ollama_host, "http://from-file:1");
+    }
+
+    /// Serializes tests that change the process environment
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Sets an environment variable for the life of the guard, restoring the previous
+    /// value (or its absence) on drop, while holding `ENV_LOCK`
+    struct EnvGuard {
+        key: &'static str,
+        previous: Option<std::ffi::OsString>,
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl EnvGuard {
+        fn set(key: &'static str, value: &str) -> Self {
+            let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let previous = std::env::var_os(key);
+            std::env::set_var(key, value);
+            Self {
+                key,
+                previous,
+                _lock: lock,
+            }
+        }
+    }
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            match &self.previous {
+                Some(value) => std::env::set_var(self.key, value),
+                None => std::env::remove_var(self.key),
+            }
+        }
+    }
+
+    #[test]
+    fn ollama_host_env_var_is_picked_up() {
+        let _env = EnvGuard::set("OLLAMA_HOST", "gpu-box:11434");
+        let mut cfg: Config = toml::from_str("ollama_host = \"http://from-file:1\"").unwrap();
+        cfg.apply_env_overrides();
+        assert_eq!(cfg.ollama_host, "http://gpu-box:11434");
+    }
+
+    #[test]
+    fn ollama_host_env_value_is_normalized() {
+        assert_eq!(
+            host_from_env(Some("0.0.0.0:11434".into())).as_deref(),
+            Some("http://0.0.0.0:11434")
+        );
+        assert_eq!(
+            host_from_env(Some("https://remote".into())).as_deref(),
+            Some("https://remote")
+        );
+        assert_eq!(host_from_env(Some("  ".into())), None);
+        assert_eq!(host_from_env(None), None);
+    }
+
     #[test]
     fn backend_type_default_is_ollama() {
         assert_eq!(BackendType::default(), BackendType::Ollama);
@@ -370,25 +1373,92 @@ mod tests {
         assert_eq!(cfg.ollama_host, "http://my-server:8080");
     }
 
+    #[test]
+    fn api_base_alias_accepted() {
+        let table: toml::Table =
+            toml::from_str("backend = \"openai\"\napi_base = \"http://vllm:8000\"").unwrap();
+        assert!(unknown_key_warnings(&table).is_empty());
+        let cfg: Config = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(cfg.backend, BackendType::OpenAi);
+        assert_eq!(cfg.ollama_host, "http://vllm:8000");
+        assert_eq!(cfg.get_value("api_base").unwrap(), "http://vllm:8000");
+    }
+
+    #[test]
+    fn key_aliases_are_settable() {
+        let mut cfg = Config::default();
+        for alias in KEY_ALIASES {
+            cfg.set_value(alias, "http://alias:1").unwrap();
+            assert_eq!(cfg.ollama_host, "http://alias:1");
+            assert!(!FILE_ONLY_KEYS.contains(alias));
+        }
+    }
+
     #[test]
     fn missing_backend_defaults_to_ollama() {
         // A config with no backend key must still work.
         let cfg: Config = toml::from_str("ollama_host = \"http://localhost:11434\"").unwrap();
         assert_eq!(cfg.backend, BackendType::Ollama);
     }
-}
 
-/// Walk up the directory tree from the current directory looking for a `.slab` directory.
-/// Returns the directory containing `.slab/` (the project root), or None.
-pub fn find_project_root() -> Option<PathBuf> {
-    let mut dir = std::env::current_dir().ok()?;
-    loop {
-        let slab_dir = dir.join(".slab");
-        if slab_dir.is_dir() {
-            return Some(dir);
-        }
-        if !dir.pop() {
-            return None;
-        }
+    #[test]
+    fn system_prompt_layers_global_then_model() {
+        let cfg: Config = toml::from_str(
+            r#"
+system_prompt = "GLOBAL"
+
+[models.coder]
+name = "coder"
+system_prompt = "MODEL"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.get_model_config("coder").system_prompt.as_deref(),
+            Some("GLOBAL\n\nMODEL")
+        );
+        assert_eq!(
+            cfg.get_model_config("other").system_prompt.as_deref(),
+            Some("GLOBAL")
+        );
+    }
+
+    #[test]
+    fn system_prompt_file_replaces_inline_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let global_file = dir.path().join("global.md");
+        let model_file = dir.path().join("model.md");
+        std::fs::write(&global_file, "FROM GLOBAL FILE\n").unwrap();
+        std::fs::write(&model_file, "FROM MODEL FILE\n").unwrap();
+
+        let mut cfg: Config = toml::from_str("system_prompt = \"INLINE\"").unwrap();
+        cfg.system_prompt_file = Some(global_file);
+        cfg.models.insert(
+            "coder".to_string(),
+            ModelConfig {
+                name: "coder".to_string(),
+                temperature: 0.7,
+                top_p: 0.9,
+                system_prompt: Some("MODEL INLINE".to_string()),
+                system_prompt_file: Some(model_file),
+                prompt_cost_per_1k: None,
+                completion_cost_per_1k: None,
+            },
+        );
+
+        assert_eq!(
+            cfg.get_model_config("coder").system_prompt.as_deref(),
+            Some("FROM GLOBAL FILE\n\nFROM MODEL FILE")
+        );
+    }
+
+    #[test]
+    fn system_prompt_file_missing_falls_back_to_inline() {
+        let mut cfg: Config = toml::from_str("system_prompt = \"INLINE\"").unwrap();
+        cfg.system_prompt_file = Some(PathBuf::from("/nonexistent/slab/prompt.md"));
+        assert_eq!(
+            cfg.get_model_config("any").system_prompt.as_deref(),
+            Some("INLINE")
+        );
     }
 }
@@ -4,8 +4,27 @@ use std::path::{Path, PathBuf};
 
 use regex::Regex;
 
+use crate::error::SlabError;
 use crate::ollama::Message;
 
+/// Whether a Content-Type header names something worth reading as text
+fn is_text_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/toml"
+                | "application/yaml"
+                | "application/x-yaml"
+                | "application/x-sh"
+        )
+}
+
 /// Simple offline tokenizer using chars/4 approximation
 pub fn estimate_tokens(text: &str) -> usize {
     // Simple approximation: ~4 characters per token on average
@@ -13,6 +32,9 @@ pub fn estimate_tokens(text: &str) -> usize {
     text.len() / 4
 }
 
+/// Files read from a directory (keyed by path) and descriptions of the entries skipped.
+type DirectoryContents = (Vec<(PathBuf, String)>, Vec<String>);
+
 /// Manages conversation context including files, messages, and token budget
 #[derive(Debug, Clone)]
 pub struct ContextManager {
@@ -40,6 +62,9 @@ pub struct ContextManager {
 
     /// When true, all context files are re-read from disk before each LLM call
     watch_mode: bool,
+
+    /// Files estimated above this many tokens are refused by `add_file`/`add_directory`
+    max_file_tokens: Option<usize>,
 }
 
 impl ContextManager {
@@ -54,9 +79,22 @@ impl ContextManager {
             project_root,
             initial_cwd,
             watch_mode: true,
+            max_file_tokens: None,
         }
     }
 
+    /// Cap the estimated token size of any single file added to context
+    pub fn set_max_file_tokens(&mut self, cap: Option<usize>) {
+        self.max_file_tokens = cap;
+    }
+
+    /// Estimated tokens of `content` when it exceeds the per-file cap
+    fn over_file_cap(&self, content: &str) -> Option<usize> {
+        let tokens = estimate_tokens(content);
+        self.max_file_tokens.filter(|cap| tokens > *cap)?;
+        Some(tokens)
+    }
+
     /// Return the directory from which the user started the chat session
     pub fn initial_cwd(&self) -> &PathBuf {
         &self.initial_cwd
@@ -97,6 +135,17 @@ impl ContextManager {
         let content =
             fs::read_to_string(&full_path).map_err(|e| format!("Failed to read file: {}", e))?;
 
+        if let Some(tokens) = self.over_file_cap(&content) {
+            return Err(format!(
+                "{} is ~{} tokens, over the {} token cap (ui.max_file_tokens). \
+                 Reference a slice instead, e.g. @{}:1-200",
+                path.display(),
+                tokens,
+                self.max_file_tokens.unwrap_or_default(),
+                path.display()
+            ));
+        }
+
         // Store with relative path for display
         let display_path = path.to_path_buf();
         self.files.insert(display_path, content);
@@ -104,13 +153,147 @@ impl ContextManager {
         Ok(())
     }
 
+    /// Fetch a URL and store its body under a synthetic `url:<host><path>` entry.
+    /// Non-text responses are refused; the body counts toward the budget like a file.
+    pub async fn add_url(&mut self, url: &str) -> Result<PathBuf, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported URL scheme: {}", parsed.scheme()));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let response = client
+            .get(parsed.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Failed to fetch {}: HTTP {}",
+                url,
+                response.status()
+            ));
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if !content_type.is_empty() && !is_text_content_type(&content_type) {
+            return Err(format!("Not a text response ({}): {}", content_type, url));
+        }
+
+        let content = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+        let display_path = PathBuf::from(format!(
+            "url:{}{}",
+            parsed.host_str().unwrap_or_default(),
+            parsed.path()
+        ));
+        if let Some(tokens) = self.over_file_cap(&content) {
+            return Err(format!(
+                "{} is ~{} tokens, over the {} token cap (ui.max_file_tokens)",
+                display_path.display(),
+                tokens,
+                self.max_file_tokens.unwrap_or_default()
+            ));
+        }
+
+        self.files.insert(display_path.clone(), content);
+        Ok(display_path)
+    }
+
+    /// Store piped input under a pseudo-file named `stdin`; it counts toward
+    /// the budget like any other file
+    pub fn add_stdin(&mut self, content: impl Into<String>) -> Result<(), String> {
+        let content = content.into();
+        if let Some(tokens) = self.over_file_cap(&content) {
+            return Err(format!(
+                "stdin is ~{} tokens, over the {} token cap (ui.max_file_tokens)",
+                tokens,
+                self.max_file_tokens.unwrap_or_default()
+            ));
+        }
+        self.files.insert(PathBuf::from("stdin"), content);
+        Ok(())
+    }
+
     /// Add all files in a directory to the context (recursively)
     /// Returns the number of files added and a list of skipped files
     pub fn add_directory(
         &mut self,
         path: impl AsRef<Path>,
     ) -> Result<(usize, Vec<String>), String> {
-        let path = path.as_ref();
+        self.add_directory_with_options(path, true)
+    }
+
+    /// Add all files from a directory, optionally honoring `.gitignore` files.
+    /// Files are added in path order until the next one would take the context
+    /// past the token budget; the rest are reported in the skipped list.
+    pub fn add_directory_with_options(
+        &mut self,
+        path: impl AsRef<Path>,
+        respect_gitignore: bool,
+    ) -> Result<(usize, Vec<String>), String> {
+        let (mut files, mut skipped) = self.read_directory(path.as_ref(), respect_gitignore)?;
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        // Files already in context are about to be replaced, so their old
+        // content must not count against the budget a second time
+        let replaced: usize = files
+            .iter()
+            .filter_map(|(path, _)| self.files.get(path))
+            .map(|content| estimate_tokens(content))
+            .sum();
+        let used = self.token_count().saturating_sub(replaced);
+        skipped.extend(self.drop_over_budget(&mut files, used));
+
+        let added = files.len();
+        self.files.extend(files);
+        Ok((added, skipped))
+    }
+
+    /// Keep the leading `files` that fit in the token budget on top of `used`
+    /// tokens and describe the rest as skipped
+    fn drop_over_budget(&self, files: &mut Vec<(PathBuf, String)>, mut used: usize) -> Vec<String> {
+        let mut fit = 0;
+        for (_, content) in files.iter() {
+            let tokens = estimate_tokens(content);
+            if used + tokens > self.token_budget {
+                break;
+            }
+            used += tokens;
+            fit += 1;
+        }
+        files
+            .split_off(fit)
+            .into_iter()
+            .map(|(path, content)| {
+                format!(
+                    "{} (over budget: ~{} tokens)",
+                    path.display(),
+                    estimate_tokens(&content)
+                )
+            })
+            .collect()
+    }
+
+    /// Read every text file under a directory with the same filtering as `/add <dir>`:
+    /// hidden entries, build/vendor directories, binaries and files over the token cap
+    /// are skipped. Keys are relative to `path` as given.
+    fn read_directory(
+        &self,
+        path: &Path,
+        respect_gitignore: bool,
+    ) -> Result<DirectoryContents, String> {
         let full_path = if path.is_absolute() {
             path.to_path_buf()
         } else {
@@ -125,22 +308,31 @@ impl ContextManager {
             return Err(format!("Not a directory: {}", path.display()));
         }
 
-        let mut added = 0;
+        let mut files = Vec::new();
         let mut skipped = Vec::new();
 
-        // Walk the directory recursively
+        // Walk the directory recursively, honoring .gitignore unless disabled
         // Skip hidden/ignored entries, but always allow the root directory the user explicitly requested
-        for entry in walkdir::WalkDir::new(&full_path)
+        let root = full_path.clone();
+        let walker = ignore::WalkBuilder::new(&full_path)
             .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| e.path() == full_path || (!is_hidden(e) && !is_ignored_dir(e)))
-        {
+            .hidden(false)
+            .ignore(false)
+            .parents(respect_gitignore)
+            .git_ignore(respect_gitignore)
+            .git_global(respect_gitignore)
+            .git_exclude(respect_gitignore)
+            .require_git(false)
+            .filter_entry(move |e| e.path() == root || (!is_hidden(e) && !is_ignored_dir(e)))
+            .build();
+
+        for entry in walker {
             let entry = match entry {
                 Ok(e) => e,
                 Err(_) => continue,
             };
 
-            if !entry.file_type().is_file() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
                 continue;
             }
 
@@ -155,13 +347,20 @@ impl ContextManager {
             // Try to read the file
             match fs::read_to_string(file_path) {
                 Ok(content) => {
+                    if let Some(tokens) = self.over_file_cap(&content) {
+                        skipped.push(format!(
+                            "{} (too large: {} tokens)",
+                            file_path.display(),
+                            tokens
+                        ));
+                        continue;
+                    }
                     // Use relative path from the original path argument
                     let relative = file_path
                         .strip_prefix(&full_path)
                         .map(|p| path.join(p))
                         .unwrap_or_else(|_| file_path.to_path_buf());
-                    self.files.insert(relative, content);
-                    added += 1;
+                    files.push((relative, content));
                 }
                 Err(_) => {
                     skipped.push(format!("{} (unreadable)", file_path.display()));
@@ -169,7 +368,7 @@ impl ContextManager {
             }
         }
 
-        Ok((added, skipped))
+        Ok((files, skipped))
     }
 
     /// Check if the path is a directory
@@ -189,6 +388,68 @@ impl ContextManager {
         self.files.remove(&path).is_some()
     }
 
+    /// Follow a rename on disk: if `old` is tracked, drop it and track `new` in its place,
+    /// re-read from disk. Returns the new context key, or `None` if `old` was not tracked.
+    pub fn rename_file(&mut self, old: impl AsRef<Path>, new: impl AsRef<Path>) -> Option<PathBuf> {
+        let old_full = self.resolve(old.as_ref());
+        let key = self
+            .files
+            .keys()
+            .find(|key| self.resolve(key) == old_full)?
+            .clone();
+        let content = self.files.remove(&key)?;
+
+        let new_full = self.resolve(new.as_ref());
+        let new_key = if key.is_absolute() {
+            new_full.clone()
+        } else {
+            new_full
+                .strip_prefix(&self.initial_cwd)
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| new_full.clone())
+        };
+        let content = fs::read_to_string(&new_full).unwrap_or(content);
+        self.files.insert(new_key.clone(), content);
+        Some(new_key)
+    }
+
+    /// Absolute path for a context key or user-supplied path
+    fn resolve(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.initial_cwd.join(path)
+        }
+    }
+
+    /// Every file in context with its content, sorted by path
+    pub fn file_entries(&self) -> Vec<(PathBuf, String)> {
+        let mut entries: Vec<_> = self
+            .files
+            .iter()
+            .map(|(path, content)| (path.clone(), content.clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// Put back a file saved with a session. Real files are re-read from disk so the
+    /// model sees current code; pseudo-files (`stdin`, `url:...`) keep the saved
+    /// content, as do files that can no longer be read, for which `false` is returned.
+    pub fn restore_file(&mut self, path: PathBuf, saved: String) -> bool {
+        let pseudo = path == Path::new("stdin") || path.to_string_lossy().starts_with("url:");
+        let (content, current) = if pseudo {
+            (saved, true)
+        } else {
+            match fs::read_to_string(self.resolve(&path)) {
+                Ok(content) => (content, true),
+                Err(_) => (saved, false),
+            }
+        };
+        self.files.insert(path, content);
+        current
+    }
+
     /// Get list of files in context
     pub fn list_files(&self) -> Vec<&PathBuf> {
         self.files.keys().collect()
@@ -215,12 +476,13 @@ impl ContextManager {
         &self.messages
     }
 
-    /// Get a mutable reference to the content of the last user message
+    /// Get a mutable reference to the content of the last message the user typed,
+    /// skipping command and hook output added after it
     pub fn last_user_message_mut(&mut self) -> Option<&mut String> {
         self.messages
             .iter_mut()
             .rev()
-            .find(|m| m.role == "user")
+            .find(|m| m.typed.is_some())
             .map(|m| &mut m.content)
     }
 
@@ -233,11 +495,55 @@ impl ContextManager {
             .map(|m| m.content.as_str())
     }
 
+    /// Remove and return the most recent message
+    pub fn pop_message(&mut self) -> Option<Message> {
+        self.messages.pop()
+    }
+
+    /// Remove the last message the user typed and every message after it, returning
+    /// it. Command and hook output sent as user messages is not a typed turn.
+    pub fn truncate_from_last_user(&mut self) -> Option<Message> {
+        let idx = self.messages.iter().rposition(|m| m.typed.is_some())?;
+        self.messages.drain(idx..).next()
+    }
+
+    /// Drop every file from the context (messages are kept)
+    pub fn clear_files(&mut self) {
+        self.files.clear();
+    }
+
     /// Clear conversation messages (but keep files)
     pub fn clear_messages(&mut self) {
         self.messages.clear();
     }
 
+    /// Drop all but the most recent `keep` messages, returning how many were removed.
+    /// Like `prune_to_fit`, whole exchanges go: an assistant reply whose question
+    /// was dropped is dropped too, so fewer than `keep` may remain.
+    pub fn keep_last_messages(&mut self, keep: usize) -> usize {
+        let mut dropped = self.messages.len().saturating_sub(keep);
+        if dropped > 0 {
+            while self
+                .messages
+                .get(dropped)
+                .is_some_and(|m| m.role == "assistant")
+            {
+                dropped += 1;
+            }
+        }
+        self.messages.drain(..dropped);
+        dropped
+    }
+
+    /// Replace the oldest `count` messages with a single summary message.
+    /// Returns the number of messages that were removed.
+    pub fn compact_messages(&mut self, count: usize, summary: Message) -> usize {
+        let count = count.min(self.messages.len());
+        self.messages.drain(..count);
+        self.messages.insert(0, summary);
+        count
+    }
+
     /// Clear everything except system prompt
     #[allow(dead_code)]
     pub fn clear_all(&mut self) {
@@ -262,8 +568,8 @@ impl ContextManager {
         result
     }
 
-    /// Build the system content including prompt, rules, and files
-    fn build_system_content(&self) -> String {
+    /// Assemble the full system content: system prompt, rules, and file blocks
+    pub fn build_system_content(&self) -> String {
         let mut parts = Vec::new();
 
         // System prompt
@@ -306,6 +612,11 @@ impl ContextManager {
         self.token_budget
     }
 
+    /// Change the token budget, e.g. to a model's native context window
+    pub fn set_token_budget(&mut self, budget: usize) {
+        self.token_budget = budget;
+    }
+
     /// Get remaining token budget
     #[allow(dead_code)]
     pub fn tokens_remaining(&self) -> usize {
@@ -319,9 +630,43 @@ impl ContextManager {
         self.token_count() > self.token_budget
     }
 
+    /// Make sure the assembled context fits the token budget before sending.
+    /// With `auto_prune`, old messages are dropped first; returns how many were removed.
+    /// Nothing is pruned when even a fully pruned context would not fit.
+    pub fn ensure_within_budget(&mut self, auto_prune: bool) -> Result<usize, SlabError> {
+        let floor = if auto_prune {
+            self.unprunable_tokens()
+        } else {
+            self.token_count()
+        };
+        if floor > self.token_budget {
+            return Err(SlabError::ContextOverflow {
+                used: self.token_count(),
+                limit: self.token_budget,
+            });
+        }
+        let before = self.messages.len();
+        if auto_prune {
+            self.prune_to_fit();
+        }
+        Ok(before - self.messages.len())
+    }
+
+    /// Tokens `prune_to_fit` can never free: the system content, a leading
+    /// system message and the last one or two messages
+    fn unprunable_tokens(&self) -> usize {
+        let skip = usize::from(self.messages.first().map(|m| m.role.as_str()) == Some("system"));
+        let total = self.messages.len();
+        let kept = if total > 2 { 2 - total % 2 } else { total };
+        let freed: usize = self.messages[skip..skip + (total - kept)]
+            .iter()
+            .map(|m| estimate_tokens(&m.content))
+            .sum();
+        self.token_count() - freed
+    }
+
     /// Prune old messages to fit within token budget
     /// Keeps system prompt and recent messages, removes oldest user/assistant pairs
-    #[allow(dead_code)]
     pub fn prune_to_fit(&mut self) {
         while self.is_over_budget() && self.messages.len() > 2 {
             // Remove the oldest user message and its response
@@ -377,11 +722,22 @@ impl ContextManager {
     ///
     /// Patterns like `@src/main.rs` or `@main.rs` are replaced with a formatted
     /// block containing the file content. Unresolved references are left unchanged.
+    /// `@path:START-END` inlines just those lines (1-based, inclusive) and may name
+    /// a file on disk that is not in context, e.g. one too large to add whole.
+    /// `@dir` or `@dir/` inlines every file in a directory on disk, filtered like `/add <dir>`.
     pub fn expand_file_references(&self, input: &str) -> String {
-        let re = Regex::new(r"@([a-zA-Z0-9_\-./]+)").unwrap();
+        let re = Regex::new(r"@([a-zA-Z0-9_\-./]+)(?::(\d+)-(\d+))?").unwrap();
 
         re.replace_all(input, |caps: &regex::Captures| {
             let reference = &caps[1];
+            if let (Some(start), Some(end)) = (caps.get(2), caps.get(3)) {
+                let start: usize = start.as_str().parse().unwrap_or(0);
+                let end: usize = end.as_str().parse().unwrap_or(0);
+                if let Some(block) = self.expand_line_range(reference, start, end) {
+                    return block;
+                }
+                return caps[0].to_string();
+            }
             if let Some(resolved_path) = self.resolve_file_reference(reference) {
                 if let Some(content) = self.files.get(&resolved_path) {
                     let ext = resolved_path
@@ -396,12 +752,78 @@ impl ContextManager {
                     );
                 }
             }
+            if let Some(blocks) = self.expand_directory(reference) {
+                return blocks;
+            }
             // No match — leave the @reference unchanged
             caps[0].to_string()
         })
         .into_owned()
     }
 
+    /// Render every file in a directory on disk as file blocks, sorted by path,
+    /// stopping once the files would not fit in the token budget.
+    /// Returns `None` if `reference` is not a directory or it holds no text files.
+    fn expand_directory(&self, reference: &str) -> Option<String> {
+        let path = Path::new(reference.trim_end_matches('/'));
+        if path.as_os_str().is_empty() || !self.is_directory(path) {
+            return None;
+        }
+        let (mut files, mut skipped) = self.read_directory(path, true).ok()?;
+        if files.is_empty() {
+            return None;
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        skipped.extend(self.drop_over_budget(&mut files, self.token_count()));
+
+        let mut blocks: Vec<String> = files
+            .iter()
+            .map(|(path, content)| {
+                let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("txt");
+                format!("[File: {}]\n```{}\n{}\n```", path.display(), ext, content)
+            })
+            .collect();
+        blocks.extend(skipped.iter().map(|s| format!("[Skipped: {}]", s)));
+        Some(blocks.join("\n\n"))
+    }
+
+    /// Render lines `start..=end` of a context file, or of a file on disk, as a file block
+    fn expand_line_range(&self, reference: &str, start: usize, end: usize) -> Option<String> {
+        if start == 0 || end < start {
+            return None;
+        }
+        let (path, content) = match self.resolve_file_reference(reference) {
+            Some(path) => {
+                let content = self.files.get(&path)?.clone();
+                (path, content)
+            }
+            None => {
+                let path = PathBuf::from(reference);
+                let full_path = if path.is_absolute() {
+                    path.clone()
+                } else {
+                    self.initial_cwd.join(&path)
+                };
+                (path, fs::read_to_string(full_path).ok()?)
+            }
+        };
+
+        let excerpt: Vec<&str> = content
+            .lines()
+            .skip(start - 1)
+            .take(end - start + 1)
+            .collect();
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("txt");
+        Some(format!(
+            "[File: {} (lines {}-{})]\n```{}\n{}\n```",
+            path.display(),
+            start,
+            end,
+            ext,
+            excerpt.join("\n")
+        ))
+    }
+
     /// Enable or disable auto-refresh of context files before LLM calls
     pub fn set_watch_mode(&mut self, enabled: bool) {
         self.watch_mode = enabled;
@@ -453,6 +875,64 @@ pub struct ContextSummary {
     pub has_rules: bool,
 }
 
+/// Check if a directory entry is hidden (starts with .)
+fn is_hidden(entry: &ignore::DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
+}
+
+/// Check if a directory should be ignored (node_modules, target, etc.)
+fn is_ignored_dir(entry: &ignore::DirEntry) -> bool {
+    if !entry.file_type().is_some_and(|t| t.is_dir()) {
+        return false;
+    }
+
+    let ignored = [
+        "node_modules",
+        "target",
+        "dist",
+        "build",
+        "__pycache__",
+        ".git",
+        ".svn",
+        ".hg",
+        "vendor",
+        "venv",
+        ".venv",
+        "env",
+        ".env",
+    ];
+
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| ignored.contains(&s))
+        .unwrap_or(false)
+}
+
+/// Check if a file is likely binary based on extension
+fn is_likely_binary(path: &Path) -> bool {
+    let binary_extensions = [
+        // Images
+        "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "svg", // Audio/Video
+        "mp3", "mp4", "wav", "avi", "mov", "flv", "wmv", "webm", // Archives
+        "zip", "tar", "gz", "bz2", "7z", "rar", "xz", // Executables
+        "exe", "dll", "so", "dylib", "bin", "o", "a", // Documents
+        "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", // Fonts
+        "ttf", "otf", "woff", "woff2", "eot", // Other binary
+        "pyc", "pyo", "class", "jar", "war", "sqlite", "db", "sqlite3",
+        "lock", // Often large and not useful
+    ];
+
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|ext| binary_extensions.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -478,6 +958,101 @@ mod tests {
         assert_eq!(messages[2].role, "assistant");
     }
 
+    #[test]
+    fn test_truncate_from_last_user() {
+        let mut ctx = ContextManager::new(4096, PathBuf::from("."));
+        ctx.add_message(Message::user("first").with_typed("first"));
+        ctx.add_message(Message::assistant("reply one"));
+        ctx.add_message(Message::user("secnod").with_typed("secnod"));
+        ctx.add_message(Message::assistant("reply two"));
+        // Command output comes back as a user message but was never typed
+        ctx.add_message(Message::user("[Ran shell command]\n$ ls\n"));
+
+        let removed = ctx.truncate_from_last_user().unwrap();
+        assert_eq!(removed.content, "secnod");
+        let contents: Vec<&str> = ctx.messages().iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "reply one"]);
+
+        ctx.clear_messages();
+        ctx.add_message(Message::assistant("no user yet"));
+        assert!(ctx.truncate_from_last_user().is_none());
+        assert_eq!(ctx.messages().len(), 1);
+    }
+
+    #[test]
+    fn test_rename_file_updates_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = ContextManager::new(4096, dir.path().to_path_buf());
+        ctx.initial_cwd = dir.path().to_path_buf();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/old.rs"), "fn old() {}").unwrap();
+        ctx.add_file("src/old.rs").unwrap();
+
+        std::fs::rename(dir.path().join("src/old.rs"), dir.path().join("src/new.rs")).unwrap();
+        let new_key = ctx.rename_file(dir.path().join("src/old.rs"), dir.path().join("src/new.rs"));
+
+        assert_eq!(new_key, Some(PathBuf::from("src/new.rs")));
+        assert!(!ctx.has_file("src/old.rs"));
+        assert_eq!(
+            ctx.get_file_content("src/new.rs").map(String::as_str),
+            Some("fn old() {}")
+        );
+
+        // Untracked files are left alone
+        assert!(ctx.rename_file("other.rs", "moved.rs").is_none());
+        assert_eq!(ctx.list_files().len(), 1);
+    }
+
+    #[test]
+    fn test_expand_directory_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = ContextManager::new(4096, dir.path().to_path_buf());
+        ctx.initial_cwd = dir.path().to_path_buf();
+        std::fs::create_dir(dir.path().join("somedir")).unwrap();
+        std::fs::write(dir.path().join("somedir/b.rs"), "fn b() {}").unwrap();
+        std::fs::write(dir.path().join("somedir/a.py"), "def a(): pass").unwrap();
+        std::fs::write(dir.path().join("somedir/logo.png"), "not text").unwrap();
+
+        let expanded = ctx.expand_file_references("Review @somedir please");
+        assert_eq!(
+            expanded,
+            format!(
+                "Review [File: somedir/a.py]\n```py\ndef a(): pass\n```\n\n\
+                 [File: somedir/b.rs]\n```rs\nfn b() {{}}\n```\n\n\
+                 [Skipped: {} (binary)] please",
+                dir.path().join("somedir/logo.png").display()
+            )
+        );
+        // A trailing slash works too, and nothing is added to context
+        assert!(ctx
+            .expand_file_references("@somedir/")
+            .contains("fn b() {}"));
+        assert!(ctx.list_files().is_empty());
+
+        // Unknown directories are left as written
+        assert_eq!(ctx.expand_file_references("@nowhere/"), "@nowhere/");
+
+        // Files past the token budget are listed as skipped instead of inlined
+        let mut ctx = ContextManager::new(100, dir.path().to_path_buf());
+        ctx.initial_cwd = dir.path().to_path_buf();
+        std::fs::write(dir.path().join("somedir/c.txt"), "x".repeat(400)).unwrap();
+        let expanded = ctx.expand_file_references("@somedir");
+        assert!(expanded.contains("fn b() {}"));
+        assert!(expanded.contains("[Skipped: somedir/c.txt (over budget: ~100 tokens)]"));
+    }
+
+    #[test]
+    fn test_add_stdin_appears_in_system_content() {
+        let mut ctx = ContextManager::new(4096, PathBuf::from("."));
+        let before = ctx.token_count();
+        ctx.add_stdin("int main(void) { return 0; }").unwrap();
+
+        let system = ctx.build_system_content();
+        assert!(system.contains("### stdin\n```txt\nint main(void) { return 0; }\n```"));
+        assert!(ctx.has_file("stdin"));
+        assert!(ctx.token_count() > before);
+    }
+
     #[test]
     fn test_expand_file_references_exact_path() {
         let mut ctx = ContextManager::new(4096, PathBuf::from("."));
@@ -545,62 +1120,201 @@ mod tests {
             Some(PathBuf::from("src/main.rs"))
         );
     }
-}
 
-/// Check if a directory entry is hidden (starts with .)
-fn is_hidden(entry: &walkdir::DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
-}
+    #[test]
+    fn test_add_directory_respects_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "generated.rs\n").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("generated.rs"), "// generated").unwrap();
+
+        let mut ctx = ContextManager::new(4096, dir.path().to_path_buf());
+        let (added, _) = ctx.add_directory(dir.path()).unwrap();
+        assert_eq!(added, 1);
+        assert!(ctx.get_file_content(dir.path().join("main.rs")).is_some());
+        assert!(ctx
+            .get_file_content(dir.path().join("generated.rs"))
+            .is_none());
+
+        let mut ctx = ContextManager::new(4096, dir.path().to_path_buf());
+        let (added, _) = ctx.add_directory_with_options(dir.path(), false).unwrap();
+        assert_eq!(added, 2);
+    }
 
-/// Check if a directory should be ignored (node_modules, target, etc.)
-fn is_ignored_dir(entry: &walkdir::DirEntry) -> bool {
-    if !entry.file_type().is_dir() {
-        return false;
+    #[test]
+    fn test_add_file_over_cap_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let big = dir.path().join("big.txt");
+        std::fs::write(&big, "line of text\n".repeat(100)).unwrap();
+
+        let mut ctx = ContextManager::new(4096, dir.path().to_path_buf());
+        ctx.set_max_file_tokens(Some(50));
+        let err = ctx.add_file(&big).unwrap_err();
+        assert!(err.contains("over the 50 token cap"), "{}", err);
+        assert!(ctx.list_files().is_empty());
+
+        // The suggested slice reference still works for the refused file
+        let expanded = ctx.expand_file_references(&format!("see @{}:2-3", big.display()));
+        assert!(expanded.contains("(lines 2-3)"));
+        assert_eq!(expanded.matches("line of text").count(), 2);
+
+        let (added, skipped) = ctx.add_directory(dir.path()).unwrap();
+        assert_eq!(added, 0);
+        assert!(
+            skipped[0].ends_with("(too large: 325 tokens)"),
+            "{}",
+            skipped[0]
+        );
     }
 
-    let ignored = [
-        "node_modules",
-        "target",
-        "dist",
-        "build",
-        "__pycache__",
-        ".git",
-        ".svn",
-        ".hg",
-        "vendor",
-        "venv",
-        ".venv",
-        "env",
-        ".env",
-    ];
+    #[test]
+    fn test_add_directory_stops_at_token_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(dir.path().join(name), "x".repeat(400)).unwrap();
+        }
 
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| ignored.contains(&s))
-        .unwrap_or(false)
-}
+        // Room for two 100-token files, not three
+        let mut ctx = ContextManager::new(250, dir.path().to_path_buf());
+        let (added, skipped) = ctx.add_directory(dir.path()).unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(skipped.len(), 1);
+        assert!(
+            skipped[0].contains("c.txt (over budget: ~100 tokens)"),
+            "{}",
+            skipped[0]
+        );
+        assert!(ctx.token_count() <= 250);
+    }
 
-/// Check if a file is likely binary based on extension
-fn is_likely_binary(path: &Path) -> bool {
-    let binary_extensions = [
-        // Images
-        "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "svg", // Audio/Video
-        "mp3", "mp4", "wav", "avi", "mov", "flv", "wmv", "webm", // Archives
-        "zip", "tar", "gz", "bz2", "7z", "rar", "xz", // Executables
-        "exe", "dll", "so", "dylib", "bin", "o", "a", // Documents
-        "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", // Fonts
-        "ttf", "otf", "woff", "woff2", "eot", // Other binary
-        "pyc", "pyo", "class", "jar", "war", "sqlite", "db", "sqlite3",
-        "lock", // Often large and not useful
-    ];
+    #[test]
+    fn test_re_add_directory_near_budget_replaces_content() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(dir.path().join(name), "x".repeat(400)).unwrap();
+        }
 
-    path.extension()
-        .and_then(|e| e.to_str())
-        .map(|ext| binary_extensions.contains(&ext.to_lowercase().as_str()))
-        .unwrap_or(false)
+        let mut ctx = ContextManager::new(250, dir.path().to_path_buf());
+        ctx.add_directory(dir.path()).unwrap();
+
+        // The tracked files are replaced, not counted twice against the budget
+        std::fs::write(dir.path().join("a.txt"), "y".repeat(400)).unwrap();
+        let (added, skipped) = ctx.add_directory(dir.path()).unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("c.txt"), "{}", skipped[0]);
+        let a = ctx.get_file_content(dir.path().join("a.txt")).unwrap();
+        assert_eq!(a, &"y".repeat(400));
+    }
+
+    #[test]
+    fn test_add_file_under_cap_is_accepted() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.txt");
+        std::fs::write(&small, "short").unwrap();
+
+        let mut ctx = ContextManager::new(4096, dir.path().to_path_buf());
+        ctx.set_max_file_tokens(Some(50));
+        ctx.add_file(&small).unwrap();
+        assert_eq!(ctx.list_files().len(), 1);
+    }
+
+    #[test]
+    fn test_ensure_within_budget_overflow() {
+        let mut ctx = ContextManager::new(10, PathBuf::from("."));
+        ctx.files.insert(PathBuf::from("big.txt"), "x".repeat(400));
+        ctx.add_message(Message::user("hello"));
+
+        let err = ctx.ensure_within_budget(false).unwrap_err();
+        assert!(matches!(
+            err,
+            SlabError::ContextOverflow { limit: 10, used } if used > 10
+        ));
+    }
+
+    #[test]
+    fn test_ensure_within_budget_keeps_history_when_pruning_cannot_help() {
+        let mut ctx = ContextManager::new(30, PathBuf::from("."));
+        ctx.files.insert(PathBuf::from("big.txt"), "x".repeat(400));
+        for i in 0..4 {
+            ctx.add_message(Message::user(format!("question {}", i)));
+            ctx.add_message(Message::assistant(format!("answer {}", i)));
+        }
+
+        assert!(ctx.ensure_within_budget(true).is_err());
+        assert_eq!(ctx.messages.len(), 8);
+    }
+
+    #[test]
+    fn test_ensure_within_budget_auto_prune() {
+        let mut ctx = ContextManager::new(30, PathBuf::from("."));
+        for i in 0..6 {
+            ctx.add_message(Message::user(format!("question {} {}", i, "x".repeat(40))));
+            ctx.add_message(Message::assistant(format!("answer {}", i)));
+        }
+        assert!(ctx.ensure_within_budget(false).is_err());
+
+        let pruned = ctx.ensure_within_budget(true).unwrap();
+        assert!(pruned > 0);
+        assert!(ctx.token_count() <= 30);
+    }
+
+    #[test]
+    fn test_build_system_content_includes_rules_and_files() {
+        let mut ctx = ContextManager::new(4096, PathBuf::from("."));
+        ctx.set_system_prompt("You are helpful.");
+        ctx.set_rules("- No unwrap()");
+        ctx.files
+            .insert(PathBuf::from("src/main.rs"), "fn main() {}".to_string());
+
+        let content = ctx.build_system_content();
+        assert!(content.starts_with("You are helpful."));
+        assert!(content.contains("## Rules\n\n- No unwrap()"));
+        assert!(content.contains("### src/main.rs\n```rs\nfn main() {}\n```"));
+    }
+
+    #[tokio::test]
+    async fn test_add_url_stores_text_body() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/docs/readme.md"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/markdown; charset=utf-8")
+                    .set_body_string("# Docs\nUse it well."),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/logo.png"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "image/png")
+                    .set_body_bytes(vec![0x89, b'P', b'N', b'G']),
+            )
+            .mount(&server)
+            .await;
+
+        let mut ctx = ContextManager::new(4096, PathBuf::from("."));
+        let stored = ctx
+            .add_url(&format!("{}/docs/readme.md", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(stored, PathBuf::from("url:127.0.0.1/docs/readme.md"));
+        assert_eq!(
+            ctx.get_file_content(&stored).map(String::as_str),
+            Some("# Docs\nUse it well.")
+        );
+        assert!(ctx.token_count() > 0);
+
+        let err = ctx
+            .add_url(&format!("{}/logo.png", server.uri()))
+            .await
+            .unwrap_err();
+        assert!(err.contains("Not a text response"));
+        assert_eq!(ctx.list_files().len(), 1);
+    }
 }
@@ -1,3 +1,6 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,6 +14,9 @@ pub enum SlabError {
     #[error("Model '{0}' not found. Run 'slab models' to see available models.")]
     ModelNotFound(String),
 
+    #[error("Model '{0}' is still loading")]
+    ModelLoading(String),
+
     #[error("No models available")]
     NoModelsAvailable,
 
@@ -29,12 +35,138 @@ pub enum SlabError {
     #[error("File operation error: {0}")]
     FileOperation(String),
 
+    #[error("{kind} for {}: {message}", path.display())]
+    FileIo {
+        kind: FileIoKind,
+        path: PathBuf,
+        message: String,
+    },
+
     #[error("Template error: {0}")]
     TemplateError(String),
 
-    #[allow(dead_code)]
+    #[error("Context is over budget: ~{used} tokens used, limit is {limit}")]
+    ContextOverflow { used: usize, limit: usize },
+
     #[error("{0}")]
     Other(String),
 }
 
 pub type Result<T> = std::result::Result<T, SlabError>;
+
+/// Why a file operation could not touch the disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileIoKind {
+    PermissionDenied,
+    DiskFull,
+    NotFound,
+    Other,
+}
+
+impl FileIoKind {
+    pub fn from_io(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                FileIoKind::PermissionDenied
+            }
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => FileIoKind::DiskFull,
+            io::ErrorKind::NotFound => FileIoKind::NotFound,
+            _ => FileIoKind::Other,
+        }
+    }
+
+    /// What to try next, if there is anything specific
+    pub fn suggestion(&self) -> Option<&'static str> {
+        match self {
+            FileIoKind::PermissionDenied => Some(
+                "Check the file and directory permissions, and that the filesystem is not mounted read-only",
+            ),
+            FileIoKind::DiskFull => Some("Free up disk space (or quota) and apply the changes again"),
+            FileIoKind::NotFound => {
+                Some("The file may have been moved or deleted since the response was generated")
+            }
+            FileIoKind::Other => None,
+        }
+    }
+}
+
+impl fmt::Display for FileIoKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileIoKind::PermissionDenied => "Permission denied",
+            FileIoKind::DiskFull => "Disk full",
+            FileIoKind::NotFound => "Not found",
+            FileIoKind::Other => "I/O error",
+        })
+    }
+}
+
+/// Map an io error from reading or writing `path` to a `SlabError::FileIo`
+pub fn file_io_error(path: &Path, err: io::Error) -> SlabError {
+    SlabError::FileIo {
+        kind: FileIoKind::from_io(err.kind()),
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind_of(err: io::Error) -> FileIoKind {
+        match file_io_error(Path::new("src/main.rs"), err) {
+            SlabError::FileIo { kind, .. } => kind,
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_file_io_error_kinds() {
+        assert_eq!(
+            kind_of(io::Error::from(io::ErrorKind::PermissionDenied)),
+            FileIoKind::PermissionDenied
+        );
+        assert_eq!(
+            kind_of(io::Error::from(io::ErrorKind::ReadOnlyFilesystem)),
+            FileIoKind::PermissionDenied
+        );
+        assert_eq!(
+            kind_of(io::Error::from(io::ErrorKind::StorageFull)),
+            FileIoKind::DiskFull
+        );
+        assert_eq!(
+            kind_of(io::Error::from(io::ErrorKind::NotFound)),
+            FileIoKind::NotFound
+        );
+        assert_eq!(
+            kind_of(io::Error::other("something else")),
+            FileIoKind::Other
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_io_error_from_enospc() {
+        // ENOSPC is 28 on Linux and macOS
+        let err = file_io_error(Path::new("out.txt"), io::Error::from_raw_os_error(28));
+        assert!(err.to_string().starts_with("Disk full for out.txt:"));
+        assert!(matches!(
+            err,
+            SlabError::FileIo {
+                kind: FileIoKind::DiskFull,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_file_io_suggestions() {
+        assert!(FileIoKind::PermissionDenied
+            .suggestion()
+            .unwrap()
+            .contains("permissions"));
+        assert!(FileIoKind::DiskFull.suggestion().is_some());
+        assert!(FileIoKind::Other.suggestion().is_none());
+    }
+}
@@ -1,13 +1,52 @@
 use console::style;
+use std::path::Path;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
 use syntect::util::as_24_bit_terminal_escaped;
 
+use crate::config::UiConfig;
+
+/// Syntect theme used when none is configured or the configured name is unknown
+pub const DEFAULT_SYNTAX_THEME: &str = "base16-ocean.dark";
+
+/// Language name for a file path based on its extension, used when a code
+/// fence is generic (`txt`, missing) or disagrees with the file it targets
+pub fn infer_language(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "go" => "go",
+        "java" => "java",
+        "rb" => "ruby",
+        "sh" | "bash" | "zsh" => "bash",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "md" | "markdown" => "markdown",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "sql" => "sql",
+        "lua" => "lua",
+        "php" => "php",
+        "cs" => "csharp",
+        _ => return None,
+    };
+    Some(lang)
+}
+
 /// Syntax highlighter for code blocks
 pub struct Highlighter {
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
+    theme_name: String,
+    line_numbers: bool,
+    color: bool,
 }
 
 impl Highlighter {
@@ -15,12 +54,66 @@ impl Highlighter {
         Self {
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set: ThemeSet::load_defaults(),
+            theme_name: DEFAULT_SYNTAX_THEME.to_string(),
+            line_numbers: false,
+            color: true,
+        }
+    }
+
+    /// Create a highlighter using the syntect theme and line-number setting from config.
+    /// The `bordered`/`plain` values saved by older versions select the default theme;
+    /// any other unknown `ui.code_block_style` falls back to it with a warning.
+    pub fn from_config(ui: &UiConfig) -> Self {
+        let mut highlighter = Self::new();
+        let legacy = matches!(ui.code_block_style.as_str(), "bordered" | "plain");
+        if !highlighter.set_theme(&ui.code_block_style) && !legacy {
+            eprintln!(
+                "Warning: unknown ui.code_block_style '{}', using '{}' (available: {})",
+                ui.code_block_style,
+                DEFAULT_SYNTAX_THEME,
+                highlighter.available_themes().join(", ")
+            );
         }
+        highlighter.set_line_numbers(ui.code_line_numbers);
+        highlighter
+    }
+
+    /// Select a syntect theme by name. Unknown names fall back to the default theme.
+    /// Returns true if the requested theme was found.
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        if self.theme_set.themes.contains_key(name) {
+            self.theme_name = name.to_string();
+            true
+        } else {
+            self.theme_name = DEFAULT_SYNTAX_THEME.to_string();
+            false
+        }
+    }
+
+    /// Name of the active syntect theme
+    #[allow(dead_code)]
+    pub fn theme_name(&self) -> &str {
+        &self.theme_name
+    }
+
+    /// Names of all available syntect themes
+    pub fn available_themes(&self) -> Vec<&str> {
+        self.theme_set.themes.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// Enable or disable line numbers in highlighted code blocks
+    pub fn set_line_numbers(&mut self, enabled: bool) {
+        self.line_numbers = enabled;
+    }
+
+    /// Enable or disable syntax coloring; when disabled code is emitted verbatim
+    pub fn set_color(&mut self, enabled: bool) {
+        self.color = enabled;
     }
 
     /// Highlight a code block with the given language
     pub fn highlight(&self, code: &str, language: &str) -> String {
-        let theme = &self.theme_set.themes["base16-ocean.dark"];
+        let theme = &self.theme_set.themes[&self.theme_name];
 
         // Try to find syntax for the language
         let syntax = self
@@ -31,8 +124,20 @@ impl Highlighter {
 
         let mut highlighter = HighlightLines::new(syntax, theme);
         let mut result = String::new();
+        let gutter_width = code.lines().count().to_string().len();
 
-        for line in code.lines() {
+        for (idx, line) in code.lines().enumerate() {
+            if self.line_numbers {
+                result.push_str(&format!(
+                    "{} ",
+                    style(format!("{:>width$} │", idx + 1, width = gutter_width)).dim()
+                ));
+            }
+            if !self.color {
+                result.push_str(line);
+                result.push('\n');
+                continue;
+            }
             match highlighter.highlight_line(line, &self.syntax_set) {
                 Ok(ranges) => {
                     let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
@@ -54,6 +159,15 @@ impl Highlighter {
         result
     }
 
+    /// Read and highlight a whole file, picking the syntax from its extension
+    pub fn highlight_file(&self, path: &Path) -> std::io::Result<String> {
+        let code = std::fs::read_to_string(path)?;
+        let language = infer_language(path)
+            .or_else(|| path.extension().and_then(|e| e.to_str()))
+            .unwrap_or("txt");
+        Ok(self.highlight(&code, language))
+    }
+
     /// Format a response with syntax-highlighted code blocks
     pub fn format_response(&self, response: &str) -> String {
         let mut result = String::new();
@@ -74,15 +188,18 @@ impl Highlighter {
                 } else {
                     // Start of code block
                     current_lang = after_backticks.trim().to_string();
-                    // Strip filename if present (e.g., "rust:src/main.rs" -> "rust")
+                    // Strip filename if present (e.g., "rust:src/main.rs" -> "rust"),
+                    // preferring the language implied by the file's extension
                     if let Some(colon_pos) = current_lang.find(':') {
-                        let (lang, _path) = current_lang.split_at(colon_pos);
+                        let (lang, path) = current_lang.split_at(colon_pos);
+                        let path = &path[1..];
+                        let lang = infer_language(Path::new(path)).unwrap_or(lang).to_string();
                         result.push_str(&format!(
                             "{} {}\n",
-                            style(&current_lang[..colon_pos]).cyan(),
-                            style(&current_lang[colon_pos + 1..]).dim()
+                            style(&lang).cyan(),
+                            style(path).dim()
                         ));
-                        current_lang = lang.to_string();
+                        current_lang = lang;
                     } else if !current_lang.is_empty() {
                         result.push_str(&format!("{}\n", style(&current_lang).cyan()));
                     }
@@ -140,4 +257,93 @@ mod tests {
         // Should contain the formatted output
         assert!(result.contains("That's it"));
     }
+
+    #[test]
+    fn test_highlight_line_numbers() {
+        let mut highlighter = Highlighter::new();
+        highlighter.set_line_numbers(true);
+        let code = "a\nb\nc\nd\ne";
+        let result = highlighter.highlight(code, "txt");
+        let plain = console::strip_ansi_codes(&result).to_string();
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines.len(), 5);
+        for (i, line) in lines.iter().enumerate() {
+            assert!(
+                line.starts_with(&format!("{} │ ", i + 1)),
+                "line: {:?}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_highlight_without_line_numbers() {
+        let highlighter = Highlighter::new();
+        let result = highlighter.highlight("a\nb", "txt");
+        let plain = console::strip_ansi_codes(&result).to_string();
+        assert_eq!(plain, "a\nb");
+    }
+
+    #[test]
+    fn test_highlight_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "pub fn one() -> u8 {\n    1\n}\n").unwrap();
+
+        let mut highlighter = Highlighter::new();
+        highlighter.set_line_numbers(true);
+        let result = highlighter.highlight_file(&path).unwrap();
+        assert!(result.contains("\x1b["));
+        let plain = console::strip_ansi_codes(&result).to_string();
+        assert_eq!(plain.lines().count(), 3);
+        assert!(plain.starts_with("1 │ pub fn one()"), "{plain}");
+
+        assert!(highlighter
+            .highlight_file(&dir.path().join("missing.rs"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_infer_language() {
+        assert_eq!(infer_language(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(
+            infer_language(Path::new("scripts/build.PY")),
+            Some("python")
+        );
+        assert_eq!(infer_language(Path::new("data.unknownext")), None);
+        assert_eq!(infer_language(Path::new("Makefile")), None);
+    }
+
+    #[test]
+    fn test_format_response_prefers_path_language() {
+        let mut highlighter = Highlighter::new();
+        highlighter.set_color(false);
+        let result = highlighter.format_response("```txt:src/main.rs\nfn main() {}\n```");
+        let plain = console::strip_ansi_codes(&result).to_string();
+        assert!(plain.starts_with("rust src/main.rs\n"), "{plain}");
+    }
+
+    #[test]
+    fn test_set_theme_falls_back_on_unknown() {
+        let mut highlighter = Highlighter::new();
+        assert!(highlighter.set_theme("InspiredGitHub"));
+        assert_eq!(highlighter.theme_name(), "InspiredGitHub");
+        assert!(!highlighter.set_theme("bordered"));
+        assert_eq!(highlighter.theme_name(), DEFAULT_SYNTAX_THEME);
+        assert!(highlighter
+            .available_themes()
+            .contains(&DEFAULT_SYNTAX_THEME));
+    }
+
+    #[test]
+    fn test_from_config_maps_legacy_styles_to_default_theme() {
+        for style in ["bordered", "plain"] {
+            let ui = UiConfig {
+                code_block_style: style.to_string(),
+                ..UiConfig::default()
+            };
+            let highlighter = Highlighter::from_config(&ui);
+            assert_eq!(highlighter.theme_name(), DEFAULT_SYNTAX_THEME);
+        }
+    }
 }
@@ -13,18 +13,19 @@ mod session;
 mod templates;
 mod testing;
 mod theme;
+mod think;
 mod ui;
 
 use clap::Parser;
 use console::style;
 use std::process;
 
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ConfigAction, ModelSort, OutputFormat};
 use config::Config;
-use error::{Result, SlabError};
+use error::{file_io_error, Result, SlabError};
 use ollama::{AnyBackend, LlmBackend};
-use repl::Repl;
-use theme::{BoxStyle, ThemeName};
+use repl::{Repl, StdinInput};
+use theme::{BoxStyle, Theme};
 use ui::BoxRenderer;
 
 #[tokio::main]
@@ -38,8 +39,16 @@ async fn main() {
 async fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    if ui::color_disabled(cli.no_color) {
+        ui::disable_color();
+    }
+
     // Load config
-    let config = Config::load(cli.config.as_ref())?;
+    let mut config = Config::load(cli.config.as_ref())?;
+    config.apply_env_overrides();
+    if let Some(host) = &cli.host {
+        config.ollama_host = host.clone();
+    }
 
     // Create backend client
     let client = AnyBackend::from_config(&config);
@@ -54,10 +63,16 @@ async fn run() -> Result<()> {
             session: session_name,
             files,
             template,
+            no_template,
+            system,
+            script,
+            readonly,
         } => {
             // Health check first
             client.health_check().await?;
 
+            config.system_prompt_override = system;
+
             // Get model (CLI override > config default > first available)
             let model = get_model(&cli, &config, &client).await?;
 
@@ -82,8 +97,17 @@ async fn run() -> Result<()> {
                 None
             };
 
+            // A project's default_template opens new conversations, not resumed ones
+            let resumed = session.as_ref().is_some_and(|s| !s.messages.is_empty());
+            let default_template = if template.is_none() && !no_template && !resumed {
+                config.default_template.clone()
+            } else {
+                None
+            };
+
             // Run REPL
             let mut repl = Repl::new(client, config, model.clone(), streaming);
+            repl.set_readonly(readonly);
             if let Some(s) = session {
                 repl.load_session(s);
             }
@@ -94,13 +118,31 @@ async fn run() -> Result<()> {
             // If a template is provided, send it as the first message
             if let Some(tpl_name) = &template {
                 repl.send_template(tpl_name).await?;
+            } else if let Some(tpl_name) = &default_template {
+                // A broken project default should not keep the REPL from starting
+                if let Err(e) = repl.send_template(tpl_name).await {
+                    eprintln!(
+                        "{} default_template {}: {}",
+                        style("Warning:").yellow(),
+                        tpl_name,
+                        e
+                    );
+                }
             }
 
-            repl.run().await?;
+            if let Some(script) = script {
+                let input = std::fs::read_to_string(&script).map_err(|e| {
+                    SlabError::Other(format!("Failed to read script {}: {}", script.display(), e))
+                })?;
+                repl.run_script(&input).await?;
+            } else {
+                repl.run().await?;
+            }
 
-            // Auto-save session
+            // Auto-save session, under the one switched to with /load if any
             if let Some(name) = session_name {
-                if let Err(e) = repl.save_session(&name) {
+                let name = repl.session_name().unwrap_or(&name);
+                if let Err(e) = repl.save_session(name) {
                     eprintln!("{} {}", style("Warning:").yellow(), e);
                 }
             }
@@ -110,10 +152,43 @@ async fn run() -> Result<()> {
             prompt,
             files,
             template,
+            system,
+            stdin,
+            json,
+            max_tokens,
         } => {
             // Health check
             client.health_check().await?;
 
+            config.system_prompt_override = system;
+
+            // Without a prompt (or with `-`) stdin is the prompt; otherwise it is context,
+            // unless a template takes it as `content`
+            let prompt_from_stdin = prompt.as_deref().is_none_or(|p| p == "-");
+            let stdin_content = if stdin || prompt_from_stdin {
+                use std::io::{IsTerminal, Read};
+
+                if std::io::stdin().is_terminal() {
+                    return Err(SlabError::Other(
+                        "--stdin expects piped input, e.g. `cat file.c | slab run --stdin ...`"
+                            .to_string(),
+                    ));
+                }
+                let mut content = String::new();
+                std::io::stdin().read_to_string(&mut content)?;
+                Some(content)
+            } else {
+                None
+            };
+            let prompt = if prompt_from_stdin {
+                String::new()
+            } else {
+                prompt.unwrap_or_default()
+            };
+            let stdin_input = stdin_content
+                .as_deref()
+                .map(|content| StdinInput::piped(content, !prompt_from_stdin, template.is_some()));
+
             let model = get_model(&cli, &config, &client).await?;
             repl::run_single_prompt(
                 &client,
@@ -122,13 +197,28 @@ async fn run() -> Result<()> {
                 &prompt,
                 streaming,
                 &files,
+                stdin_input,
                 template.as_deref(),
+                json,
+                max_tokens,
             )
             .await?;
         }
 
-        Commands::Config { show: _, init, set } => {
-            if init {
+        Commands::Config {
+            show: _,
+            init,
+            set,
+            action,
+        } => {
+            if let Some(action) = action {
+                match action {
+                    ConfigAction::Get { key } => {
+                        println!("{}", Config::load(cli.config.as_ref())?.get_value(&key)?);
+                    }
+                    ConfigAction::Unset { key } => unset_config_value(&key)?,
+                }
+            } else if init {
                 init_config()?;
             } else if let Some(key_value) = set {
                 set_config_value(&key_value)?;
@@ -137,22 +227,77 @@ async fn run() -> Result<()> {
             }
         }
 
-        Commands::Models { names_only } => {
+        Commands::Models {
+            names_only,
+            sort,
+            filter,
+            format,
+        } => {
             // Health check
             client.health_check().await?;
 
-            list_models(&client, names_only).await?;
+            list_models(&client, names_only, sort, filter.as_deref(), format).await?;
         }
 
-        Commands::Sessions { names_only } => {
-            list_sessions(names_only)?;
+        Commands::Pull { model, set_default } => {
+            client.health_check().await?;
+
+            pull_model(&client, &model, set_default).await?;
+        }
+
+        Commands::Sessions { names_only, format } => {
+            list_sessions(names_only, format)?;
         }
 
-        Commands::Test { filter, model } => {
+        Commands::Test {
+            filter,
+            tags,
+            model,
+            watch,
+            fail_fast,
+            bail,
+            jobs,
+        } => {
             // Health check
             client.health_check().await?;
 
-            run_tests(&client, &config, &cli, filter.as_deref(), model.as_deref()).await?;
+            let filter = testing::TestFilter::new(filter.as_deref(), &tags);
+            let bail = if fail_fast { Some(1) } else { bail };
+            if watch {
+                watch_tests(
+                    &client,
+                    &config,
+                    &cli,
+                    &filter,
+                    model.as_deref(),
+                    bail,
+                    jobs,
+                )
+                .await?;
+            } else {
+                let failed = run_tests(
+                    &client,
+                    &config,
+                    &cli,
+                    &filter,
+                    model.as_deref(),
+                    bail,
+                    jobs,
+                )
+                .await?;
+                // Exit with error code if any tests failed
+                if failed > 0 {
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Diff { response, dry_run } => {
+            diff_response_file(&response, dry_run, &config)?;
+        }
+
+        Commands::Highlight { file, line_numbers } => {
+            highlight_file(&file, line_numbers, &config)?;
         }
 
         Commands::Init => {
@@ -221,8 +366,19 @@ fn show_config(config: &Config) -> Result<()> {
     println!(
         "  {} {}",
         style("Context limit:").dim(),
-        config.context_limit
+        config.context_limit.map_or_else(
+            || format!("{} (default)", config.effective_context_limit()),
+            |n| n.to_string()
+        )
     );
+    println!("  {} {}", style("Auto prune:").dim(), config.auto_prune);
+    if let Some(path) = &config.system_prompt_file {
+        println!(
+            "  {} {}",
+            style("System prompt file:").dim(),
+            path.display()
+        );
+    }
     println!("  {} {}", style("Streaming:").dim(), config.ui.streaming);
     println!(
         "  {} {}",
@@ -261,7 +417,30 @@ fn show_config(config: &Config) -> Result<()> {
         style("Code block style:").dim(),
         config.ui.code_block_style
     );
+    println!(
+        "  {} {}",
+        style("Code line numbers:").dim(),
+        config.ui.code_line_numbers
+    );
     println!("  {} {}", style("Diff style:").dim(), config.ui.diff_style);
+    println!(
+        "  {} {}",
+        style("Allowed file ops:").dim(),
+        config.ui.allowed_file_ops.join(", ")
+    );
+    println!(
+        "  {} {}",
+        style("Show stream progress:").dim(),
+        config.ui.show_stream_progress
+    );
+    println!(
+        "  {} {}",
+        style("Strip think blocks:").dim(),
+        config.ui.strip_think_blocks
+    );
+    if let Some(cap) = config.ui.max_file_tokens {
+        println!("  {} {}", style("Max file tokens:").dim(), cap);
+    }
 
     if !config.models.is_empty() {
         println!();
@@ -275,6 +454,9 @@ fn show_config(config: &Config) -> Result<()> {
                 let preview: String = sp.chars().take(40).collect();
                 println!("    system_prompt: {}...", preview);
             }
+            if let Some(path) = &model.system_prompt_file {
+                println!("    system_prompt_file: {}", path.display());
+            }
         }
     }
 
@@ -307,95 +489,49 @@ fn set_config_value(key_value: &str) -> Result<()> {
     let key = parts[0];
     let value = parts[1];
 
-    match key {
-        "ollama_host" | "host" => config.ollama_host = value.to_string(),
-        "backend" => {
-            config.backend = match value {
-                "ollama" => config::BackendType::Ollama,
-                "openai" | "openai-compat" | "openai_compat" => config::BackendType::OpenAi,
-                other => {
-                    return Err(SlabError::ConfigError(format!(
-                        "Unknown backend '{}'. Valid values: ollama, openai",
-                        other
-                    )))
-                }
-            };
-        }
-        "api_key" => {
-            config.api_key = if value.is_empty() {
-                None
-            } else {
-                Some(value.to_string())
-            };
-        }
-        "default_model" => config.default_model = Some(value.to_string()),
-        "context_limit" => {
-            config.context_limit = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid context_limit value".to_string()))?;
-        }
-        "ui.streaming" => {
-            config.ui.streaming = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
-        }
-        "ui.auto_apply_file_ops" => {
-            config.ui.auto_apply_file_ops = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
-        }
-        "ui.inline_completion_preview" => {
-            config.ui.inline_completion_preview = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
-        }
-        "ui.fuzzy_completion" => {
-            config.ui.fuzzy_completion = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
-        }
-        "ui.max_completion_items" => {
-            config.ui.max_completion_items = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid number value".to_string()))?;
-        }
-        "ui.theme" => {
-            config.ui.theme = value.to_string();
-        }
-        "ui.box_style" => {
-            config.ui.box_style = value.to_string();
-        }
-        "ui.show_status_bar" => {
-            config.ui.show_status_bar = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
-        }
-        "ui.show_banner" => {
-            config.ui.show_banner = value
-                .parse()
-                .map_err(|_| SlabError::ConfigError("Invalid boolean value".to_string()))?;
-        }
-        "ui.code_block_style" => {
-            config.ui.code_block_style = value.to_string();
-        }
-        "ui.diff_style" => {
-            config.ui.diff_style = value.to_string();
-        }
-        _ => {
-            return Err(SlabError::ConfigError(format!(
-                "Unknown config key: {}",
-                key
-            )));
-        }
-    }
+    config.set_value(key, value)?;
 
     config.save()?;
     println!("{} Set {} = {}", style("✓").green(), key, value);
     Ok(())
 }
 
-async fn list_models(client: &AnyBackend, names_only: bool) -> Result<()> {
-    let models = client.llm_list_models().await?;
+fn unset_config_value(key: &str) -> Result<()> {
+    let mut config = Config::load(None)?;
+    config.unset_value(key)?;
+    config.save()?;
+    println!(
+        "{} Reset {} to default ({})",
+        style("✓").green(),
+        key,
+        config.get_value(key)?
+    );
+    Ok(())
+}
+
+async fn list_models(
+    client: &AnyBackend,
+    names_only: bool,
+    sort: Option<ModelSort>,
+    filter: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut models = client.llm_list_models().await?;
+
+    if let Some(filter) = filter {
+        models.retain(|m| m.matches_filter(filter));
+    }
+    match sort {
+        Some(ModelSort::Name) => models.sort_by(ollama::compare_models_by_name),
+        Some(ModelSort::Size) => models.sort_by(ollama::compare_models_by_size),
+        None => {}
+    }
+
+    if format == OutputFormat::Json {
+        let json: Vec<serde_json::Value> = models.iter().map(|m| m.to_json()).collect();
+        println!("{}", serde_json::Value::Array(json));
+        return Ok(());
+    }
 
     if models.is_empty() {
         if !names_only {
@@ -424,16 +560,14 @@ async fn list_models(client: &AnyBackend, names_only: bool) -> Result<()> {
             }
         }
 
-        if let Some(size) = model.size {
-            let size_mb = size / 1_000_000;
-            let size_str = if size_mb > 1000 {
-                format!("{:.1} GB", size_mb as f64 / 1000.0)
-            } else {
-                format!("{} MB", size_mb)
-            };
+        if let Some(size_str) = model.size_label() {
             print!(" {}", style(size_str).dim());
         }
 
+        if let Some(quant) = model.quantization() {
+            print!(" {}", style(quant).dim());
+        }
+
         println!();
     }
 
@@ -441,13 +575,102 @@ async fn list_models(client: &AnyBackend, names_only: bool) -> Result<()> {
     Ok(())
 }
 
-fn list_sessions(names_only: bool) -> Result<()> {
+async fn pull_model(client: &AnyBackend, model: &str, set_default: bool) -> Result<()> {
+    let rx = client.llm_pull_model(model).await?;
+    ui::show_pull_progress(rx).await?;
+    println!("{} Pulled {}", style("✓").green(), style(model).cyan());
+
+    if set_default {
+        let path = Config::global_config_path().ok_or_else(|| {
+            SlabError::ConfigError("Could not determine the global config directory".into())
+        })?;
+        Config::update_file(&path, |config| {
+            config.default_model = Some(model.to_string())
+        })?;
+        println!(
+            "{} Set default_model = {} in {}",
+            style("✓").green(),
+            model,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn highlight_file(path: &std::path::Path, line_numbers: bool, config: &Config) -> Result<()> {
+    let mut highlighter = highlight::Highlighter::from_config(&config.ui);
+    highlighter.set_line_numbers(line_numbers || config.ui.code_line_numbers);
+    highlighter.set_color(console::colors_enabled());
+
+    let output = highlighter
+        .highlight_file(path)
+        .map_err(|e| file_io_error(path, e))?;
+    print!("{}", output);
+    if !output.ends_with('\n') {
+        println!();
+    }
+    Ok(())
+}
+
+fn diff_response_file(path: &std::path::Path, dry_run: bool, config: &Config) -> Result<()> {
+    let project_root = config::find_project_root().unwrap_or_else(|| {
+        std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
+    });
+    let operations = file_ops::parse_response_file(path, &project_root)?;
+
+    if operations.is_empty() {
+        println!(
+            "{}",
+            style(format!("No file operations found in {}", path.display())).dim()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} Found {} file operation(s) in {}",
+        style("→").cyan(),
+        operations.len(),
+        style(path.display()).cyan()
+    );
+    let mut operations =
+        file_ops::filter_allowed_operations(operations, &config.ui.allowed_file_ops);
+
+    if dry_run {
+        print!(
+            "{}",
+            file_ops::dry_run_report(&mut operations, &project_root)
+        );
+        println!();
+        println!("{}", style("Dry run: nothing was applied.").dim());
+        return Ok(());
+    }
+
+    file_ops::review_and_apply(
+        operations,
+        &project_root,
+        config.ui.auto_apply_file_ops,
+        &mut config.hooks.clone(),
+    )?;
+    Ok(())
+}
+
+fn list_sessions(names_only: bool, format: OutputFormat) -> Result<()> {
     let sessions_dir = config::find_project_root()
         .unwrap_or_else(|| {
             std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
         })
         .join(".slab/sessions");
 
+    if format == OutputFormat::Json {
+        let json: Vec<serde_json::Value> = session::Session::summaries_in(&sessions_dir)
+            .iter()
+            .map(|s| s.to_json())
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+        return Ok(());
+    }
+
     if !sessions_dir.exists() {
         if !names_only {
             println!("{}", style("No sessions directory found.").yellow());
@@ -455,20 +678,7 @@ fn list_sessions(names_only: bool) -> Result<()> {
         return Ok(());
     }
 
-    let mut sessions: Vec<String> = Vec::new();
-
-    if let Ok(entries) = std::fs::read_dir(&sessions_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if let Some(stem) = path.file_stem() {
-                    sessions.push(stem.to_string_lossy().to_string());
-                }
-            }
-        }
-    }
-
-    sessions.sort();
+    let sessions = session::Session::summaries_in(&sessions_dir);
 
     if sessions.is_empty() {
         if !names_only {
@@ -484,7 +694,7 @@ fn list_sessions(names_only: bool) -> Result<()> {
     // For shell completion scripts - just output names
     if names_only {
         for session in &sessions {
-            println!("{}", session);
+            println!("{}", session.name);
         }
         return Ok(());
     }
@@ -492,14 +702,35 @@ fn list_sessions(names_only: bool) -> Result<()> {
     println!("{}", style("Saved sessions:").cyan().bold());
     println!();
 
-    for session in &sessions {
-        println!("  {}", style(session).green());
+    let name_width = sessions.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for s in &sessions {
+        let model = if s.model.is_empty() { "?" } else { &s.model };
+        let count = s.message_count.map_or("?".to_string(), |n| n.to_string());
+        let mut details = format!("{} · {} message(s)", model, count);
+        if let Some(updated) = session::parse_timestamp(&s.updated_at) {
+            details.push_str(&format!(" · updated {}", format_session_time(updated)));
+        }
+        if let Some(created) = session::parse_timestamp(&s.created_at) {
+            details.push_str(&format!(" · created {}", format_session_time(created)));
+        }
+        println!(
+            "  {}  {}",
+            style(format!("{:<width$}", s.name, width = name_width)).green(),
+            style(details).dim()
+        );
     }
 
     println!();
     Ok(())
 }
 
+/// Local "YYYY-MM-DD HH:MM" for session listings
+fn format_session_time(time: chrono::DateTime<chrono::FixedOffset>) -> String {
+    time.with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
 async fn init_project(client: &AnyBackend) -> Result<()> {
     println!("{}", style("Initializing The Slab...").cyan().bold());
     println!();
@@ -512,16 +743,8 @@ async fn init_project(client: &AnyBackend) -> Result<()> {
                 Ok(models) if !models.is_empty() => {
                     println!("{} Found {} model(s):", style("✓").green(), models.len());
                     for model in &models {
-                        let size_str = model.size.map(|s| {
-                            let mb = s / 1_000_000;
-                            if mb > 1000 {
-                                format!("{:.1} GB", mb as f64 / 1000.0)
-                            } else {
-                                format!("{} MB", mb)
-                            }
-                        });
                         print!("    {}", style(&model.name).yellow());
-                        if let Some(s) = size_str {
+                        if let Some(s) = model.size_label() {
                             print!(" {}", style(s).dim());
                         }
                         println!();
@@ -1127,14 +1350,26 @@ tags:
     Ok(())
 }
 
+fn test_directories() -> [std::path::PathBuf; 2] {
+    let project_root = config::find_project_root().unwrap_or_else(|| {
+        std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
+    });
+    [
+        project_root.join("tests/prompt_tests"),
+        project_root.join(".slab/tests"),
+    ]
+}
+
+/// Run the prompt test suite and return the number of failed tests
 async fn run_tests(
     client: &AnyBackend,
     config: &Config,
     cli: &Cli,
-    filter: Option<&str>,
+    filter: &testing::TestFilter,
     model_override: Option<&str>,
-) -> Result<()> {
-    use std::path::PathBuf;
+    bail: Option<usize>,
+    jobs: usize,
+) -> Result<usize> {
     use testing::{load_tests_from_directory, TestRunner};
 
     // Get model
@@ -1153,12 +1388,7 @@ async fn run_tests(
     };
 
     // Load tests from tests/prompt_tests/ or .slab/tests/
-    let project_root = config::find_project_root()
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
-    let test_dirs = [
-        project_root.join("tests/prompt_tests"),
-        project_root.join(".slab/tests"),
-    ];
+    let test_dirs = test_directories();
 
     let mut all_tests = Vec::new();
     for dir in &test_dirs {
@@ -1172,7 +1402,7 @@ async fn run_tests(
             "{}",
             style("Create tests in tests/prompt_tests/*.yaml or .slab/tests/*.yaml").dim()
         );
-        return Ok(());
+        return Ok(0);
     }
 
     println!(
@@ -1183,26 +1413,100 @@ async fn run_tests(
     );
 
     // Run tests
-    let runner = TestRunner::new(client.clone(), config.clone(), model, cli.verbose);
+    let runner = TestRunner::new(client.clone(), config.clone(), model, cli.verbose)
+        .with_bail(bail)
+        .with_jobs(jobs);
 
     let results = runner.run_tests(&all_tests, filter, model_override).await;
 
     // Print results
     runner.print_results(&results);
 
-    // Exit with error code if any tests failed
-    let failed = results.iter().filter(|r| !r.passed).count();
-    if failed > 0 {
-        std::process::exit(1);
+    Ok(results.iter().filter(|r| !r.passed).count())
+}
+
+/// Run the suite, then re-run it whenever a test file changes until interrupted
+async fn watch_tests(
+    client: &AnyBackend,
+    config: &Config,
+    cli: &Cli,
+    filter: &testing::TestFilter,
+    model_override: Option<&str>,
+    bail: Option<usize>,
+    jobs: usize,
+) -> Result<()> {
+    use notify::{RecursiveMode, Watcher};
+    use std::time::{Duration, Instant};
+    use testing::{is_test_file, Debouncer};
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            for path in event.paths {
+                let _ = tx.send(path);
+            }
+        }
+    })
+    .map_err(|e| SlabError::Other(format!("Failed to start file watcher: {}", e)))?;
+
+    let watched: Vec<_> = test_directories()
+        .into_iter()
+        .filter(|dir| dir.is_dir())
+        .collect();
+    for dir in &watched {
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| SlabError::Other(format!("Failed to watch {}: {}", dir.display(), e)))?;
     }
 
-    Ok(())
+    let term = console::Term::stdout();
+    let mut debouncer = Debouncer::new(Duration::from_millis(300));
+    let mut changed: Vec<std::path::PathBuf> = Vec::new();
+    loop {
+        term.clear_screen().ok();
+        for path in &changed {
+            println!("{} {}", style("Changed:").dim(), path.display());
+        }
+        // Keep watching through errors such as a briefly unreachable server
+        if let Err(e) = run_tests(client, config, cli, filter, model_override, bail, jobs).await {
+            print_error(&e);
+        }
+
+        println!();
+        if watched.is_empty() {
+            println!(
+                "{}",
+                style("No test directories to watch. Create tests/prompt_tests/ or .slab/tests/ first.")
+                    .yellow()
+            );
+            return Ok(());
+        }
+        println!(
+            "{}",
+            style("Watching for test file changes (Ctrl+C to stop)...").dim()
+        );
+
+        changed = loop {
+            if let Ok(Some(path)) =
+                tokio::time::timeout(Duration::from_millis(100), rx.recv()).await
+            {
+                if is_test_file(&path) {
+                    debouncer.push(path, Instant::now());
+                }
+            }
+            if let Some(paths) = debouncer.ready(Instant::now()) {
+                break paths;
+            }
+        };
+    }
 }
 
 fn print_error(error: &SlabError) {
     // Use default theme for error display
-    let theme = ThemeName::Default.to_theme();
-    let renderer = BoxRenderer::new(BoxStyle::Rounded, theme.clone()).with_width(60);
+    let theme = Theme::default();
+    let renderer = BoxRenderer::new(BoxStyle::Rounded, theme.clone())
+        .with_width(60)
+        .with_plain(!console::colors_enabled_stderr());
 
     // Build error content with suggestions
     let mut content = format!("{}", error);
@@ -1223,12 +1527,30 @@ fn print_error(error: &SlabError) {
         ]),
         SlabError::NoModelsAvailable => Some(vec![
             format!("List models: {}", style("slab models").cyan()),
-            format!("Ollama: {}", style("ollama pull qwen2.5:7b").cyan()),
+            format!("Pull one: {}", style("slab pull qwen2.5:7b").cyan()),
+        ]),
+        SlabError::ContextOverflow { .. } => Some(vec![
+            format!(
+                "Remove files from context: {}",
+                style("/remove <file>").cyan()
+            ),
+            format!(
+                "Raise the limit: {}",
+                style("slab config --set context_limit=65536").cyan()
+            ),
+            format!(
+                "Drop old messages automatically: {}",
+                style("slab config --set auto_prune=true").cyan()
+            ),
         ]),
         SlabError::ModelNotFound(model) => Some(vec![
-            format!("Ollama: {}", style(format!("ollama pull {}", model)).cyan()),
+            format!("Pull it: {}", style(format!("slab pull {}", model)).cyan()),
             format!("List available: {}", style("slab models").cyan()),
         ]),
+        SlabError::ModelLoading(_) => Some(vec![
+            "Ollama is loading the model into memory; try again in a few seconds".to_string(),
+        ]),
+        SlabError::FileIo { kind, .. } => kind.suggestion().map(|s| vec![s.to_string()]),
         _ => None,
     };
 
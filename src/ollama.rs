@@ -1,6 +1,7 @@
 use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tokio::sync::mpsc;
 
 use crate::error::{Result, SlabError};
@@ -8,40 +9,116 @@ use crate::error::{Result, SlabError};
 /// Trait abstracting LLM communication so `Repl` can be generic over the backend.
 /// The real implementation is `OllamaClient`; tests use `MockLlmBackend`.
 pub trait LlmBackend: Send + Sync {
-    /// Send a chat request and return the complete response text (non-streaming).
+    /// Send a chat request and return the complete response (non-streaming).
     fn llm_chat(
         &self,
         request: ChatRequest,
-    ) -> impl std::future::Future<Output = Result<String>> + Send;
+    ) -> impl std::future::Future<Output = Result<ChatReply>> + Send;
 
     /// Send a chat request and return a streaming receiver.
     fn llm_stream(
         &self,
         request: ChatRequest,
-    ) -> impl std::future::Future<Output = Result<mpsc::Receiver<Result<String>>>> + Send;
+    ) -> impl std::future::Future<Output = Result<ChatStream>> + Send;
 
     /// List available models.
     fn llm_list_models(&self) -> impl std::future::Future<Output = Result<Vec<ModelInfo>>> + Send;
+
+    /// The model's native context window in tokens, if the backend reports it.
+    fn llm_context_length(
+        &self,
+        model: &str,
+    ) -> impl std::future::Future<Output = Result<Option<usize>>> + Send;
+
+    /// Download a model, streaming progress. Only Ollama exposes a pull API.
+    fn llm_pull_model(
+        &self,
+        name: &str,
+    ) -> impl std::future::Future<Output = Result<mpsc::Receiver<Result<PullStatus>>>> + Send;
 }
 
 impl LlmBackend for OllamaClient {
-    async fn llm_chat(&self, request: ChatRequest) -> Result<String> {
+    async fn llm_chat(&self, request: ChatRequest) -> Result<ChatReply> {
         self.chat(request).await
     }
 
-    async fn llm_stream(&self, request: ChatRequest) -> Result<mpsc::Receiver<Result<String>>> {
+    async fn llm_stream(&self, request: ChatRequest) -> Result<ChatStream> {
         self.chat_stream(request).await
     }
 
     async fn llm_list_models(&self) -> Result<Vec<ModelInfo>> {
         self.list_models().await
     }
+
+    async fn llm_context_length(&self, model: &str) -> Result<Option<usize>> {
+        Ok(self.show_model(model).await?.context_length())
+    }
+
+    async fn llm_pull_model(&self, name: &str) -> Result<mpsc::Receiver<Result<PullStatus>>> {
+        self.pull_model(name).await
+    }
+}
+
+/// A complete (non-streamed) chat response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatReply {
+    pub content: String,
+    /// Why generation ended, e.g. "stop" or "length" (hit `num_predict`)
+    pub done_reason: Option<String>,
+}
+
+/// What a backend's stream task sends: a piece of content, or the end of the reply
+#[derive(Debug)]
+pub enum StreamEvent {
+    Chunk(String),
+    Done(Option<String>),
+}
+
+/// A streamed chat response. `recv` yields content chunks; once it returns `None`,
+/// `done_reason` says why the reply ended, if the backend reported it.
+#[derive(Debug)]
+pub struct ChatStream {
+    rx: mpsc::Receiver<Result<StreamEvent>>,
+    done_reason: Option<String>,
+}
+
+impl ChatStream {
+    pub fn channel(buffer: usize) -> (mpsc::Sender<Result<StreamEvent>>, Self) {
+        let (tx, rx) = mpsc::channel(buffer);
+        (
+            tx,
+            Self {
+                rx,
+                done_reason: None,
+            },
+        )
+    }
+
+    /// The next content chunk, or `None` once the reply has ended
+    pub async fn recv(&mut self) -> Option<Result<String>> {
+        match self.rx.recv().await? {
+            Ok(StreamEvent::Chunk(chunk)) => Some(Ok(chunk)),
+            Ok(StreamEvent::Done(reason)) => {
+                self.done_reason = reason;
+                None
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    pub fn done_reason(&self) -> Option<&str> {
+        self.done_reason.as_deref()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct OllamaClient {
     client: Client,
     pub(crate) base_url: String,
+    /// Extra attempts for chat requests that fail to connect, time out or get a 5xx
+    max_retries: u32,
+    /// Delay before the first retry, doubled for each one after it
+    retry_base: std::time::Duration,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -52,22 +129,36 @@ pub struct ChatRequest {
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<ModelOptions>,
+    /// Output format constraint; "json" makes Ollama return valid JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
     pub content: String,
+    /// The user's input as typed, before `@file` expansion. Never sent or saved.
+    #[serde(skip)]
+    pub typed: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ModelOptions {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub num_ctx: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Maximum number of tokens to generate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<usize>,
+    /// Sequences that end generation as soon as the model emits one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -87,6 +178,9 @@ pub struct GenerateRequest {
 pub struct ChatResponse {
     pub message: Option<Message>,
     pub done: bool,
+    /// Why generation ended, e.g. "stop" (end of turn or stop sequence) or "length"
+    #[serde(default)]
+    pub done_reason: Option<String>,
     #[serde(default)]
     pub total_duration: Option<u64>,
     #[serde(default)]
@@ -131,6 +225,94 @@ pub struct ModelDetails {
     pub quantization_level: Option<String>,
 }
 
+/// One line of the NDJSON stream returned by `/api/pull`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullStatus {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub completed: Option<u64>,
+    /// Set instead of `status` when the pull fails mid-stream
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Response from `/api/show`; only the fields slab uses are deserialized
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShowResponse {
+    /// GGUF metadata, including "<arch>.context_length"
+    #[serde(default)]
+    pub model_info: HashMap<String, serde_json::Value>,
+}
+
+impl ShowResponse {
+    /// Native context window from the model metadata, e.g. `llama.context_length`
+    pub fn context_length(&self) -> Option<usize> {
+        self.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|n| n as usize)
+    }
+}
+
+impl ModelInfo {
+    /// Quantization level reported by the backend (e.g. "Q4_K_M"), if any
+    pub fn quantization(&self) -> Option<&str> {
+        self.details.as_ref()?.quantization_level.as_deref()
+    }
+
+    /// Download size formatted for display (e.g. "4.7 GB"), if reported
+    pub fn size_label(&self) -> Option<String> {
+        let mb = self.size? / 1_000_000;
+        Some(if mb > 1000 {
+            format!("{:.1} GB", mb as f64 / 1000.0)
+        } else {
+            format!("{} MB", mb)
+        })
+    }
+
+    /// `{name, size, parameter_size}` for `slab models --format json`
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "size": self.size,
+            "parameter_size": self.details.as_ref().and_then(|d| d.parameter_size.clone()),
+        })
+    }
+
+    /// Case-insensitive substring match on the model name
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        self.name.to_lowercase().contains(&filter.to_lowercase())
+    }
+}
+
+/// Order models alphabetically by name
+pub fn compare_models_by_name(a: &ModelInfo, b: &ModelInfo) -> std::cmp::Ordering {
+    a.name.to_lowercase().cmp(&b.name.to_lowercase())
+}
+
+/// Order models largest first; models without a reported size sort last
+pub fn compare_models_by_size(a: &ModelInfo, b: &ModelInfo) -> std::cmp::Ordering {
+    match (a.size, b.size) {
+        (Some(x), Some(y)) => y.cmp(&x).then_with(|| compare_models_by_name(a, b)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => compare_models_by_name(a, b),
+    }
+}
+
+/// Longest wait between retries, however many attempts have been made
+const MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Exponential backoff for retry `attempt` (0-based), capped at `MAX_RETRY_DELAY`
+fn retry_delay(base: std::time::Duration, attempt: u32) -> std::time::Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY)
+}
+
 impl OllamaClient {
     pub fn new(base_url: &str) -> Self {
         let client = Client::builder()
@@ -141,6 +323,39 @@ impl OllamaClient {
         Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
+            max_retries: 2,
+            retry_base: std::time::Duration::from_millis(500),
+        }
+    }
+
+    /// Set how often and how patiently transient chat failures are retried
+    pub fn with_retry(mut self, max_retries: u32, base_ms: u64) -> Self {
+        self.max_retries = max_retries;
+        self.retry_base = std::time::Duration::from_millis(base_ms);
+        self
+    }
+
+    /// POST a chat request, retrying with exponential backoff on connection
+    /// failures, timeouts and 5xx responses. 4xx responses fail straight away.
+    async fn post_chat(&self, req: &ChatRequest) -> Result<reqwest::Response> {
+        let url = format!("{}/api/chat", self.base_url);
+        let mut attempt = 0;
+        loop {
+            let retryable = attempt < self.max_retries;
+            match self.client.post(&url).json(req).send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) if !(retryable && resp.status().is_server_error()) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(api_error(&req.model, status, &body));
+                }
+                Err(e) if !(retryable && (e.is_connect() || e.is_timeout())) => {
+                    return Err(e.into())
+                }
+                _ => {}
+            }
+            tokio::time::sleep(retry_delay(self.retry_base, attempt)).await;
+            attempt += 1;
         }
     }
 
@@ -169,22 +384,93 @@ impl OllamaClient {
     }
 
     /// Send a chat request with streaming response
-    pub async fn chat_stream(
-        &self,
-        request: ChatRequest,
-    ) -> Result<mpsc::Receiver<Result<String>>> {
-        let url = format!("{}/api/chat", self.base_url);
+    pub async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream> {
         let mut req = request;
         req.stream = Some(true);
 
-        let resp = self.client.post(&url).json(&req).send().await?;
+        // Only the initial request is retried; once tokens flow, errors go to the caller
+        let resp = self.post_chat(&req).await?;
+
+        let (tx, rx) = ChatStream::channel(100);
+        let mut stream = resp.bytes_stream();
+
+        tokio::spawn(async move {
+            let mut buffer = LineBuffer::default();
+            loop {
+                let (lines, eof) = match stream.next().await {
+                    Some(Ok(bytes)) => (buffer.push(&bytes), false),
+                    Some(Err(e)) => {
+                        let _ = tx.send(Err(SlabError::ConnectionError(e))).await;
+                        return;
+                    }
+                    None => (buffer.finish().into_iter().collect(), true),
+                };
+                for line in lines {
+                    match serde_json::from_str::<ChatResponse>(&line) {
+                        Ok(resp) => {
+                            if let Some(msg) = resp.message {
+                                if !msg.content.is_empty()
+                                    && tx.send(Ok(StreamEvent::Chunk(msg.content))).await.is_err()
+                                {
+                                    return;
+                                }
+                            }
+                            // Natural ends and stop sequences both arrive as done
+                            if resp.done {
+                                let _ = tx.send(Ok(StreamEvent::Done(resp.done_reason))).await;
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            let _ = tx
+                                .send(Err(SlabError::StreamError(format!("Parse error: {}", e))))
+                                .await;
+                            return;
+                        }
+                    }
+                }
+                if eof {
+                    return;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Show model metadata (parameters, architecture info)
+    pub async fn show_model(&self, name: &str) -> Result<ShowResponse> {
+        let url = format!("{}/api/show", self.base_url);
+        let body = serde_json::json!({ "model": name });
+        let resp = self.client.post(&url).json(&body).send().await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(SlabError::ModelNotFound(name.to_string()));
+        }
+        if !resp.status().is_success() {
+            return Err(SlabError::BackendNotReachable(self.base_url.clone()));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Pull a model from the Ollama registry, streaming download progress
+    pub async fn pull_model(&self, name: &str) -> Result<mpsc::Receiver<Result<PullStatus>>> {
+        let url = format!("{}/api/pull", self.base_url);
+        let body = serde_json::json!({ "model": name, "stream": true });
+
+        // Large models take far longer than the default request timeout to download
+        let resp = self
+            .client
+            .post(&url)
+            .json(&body)
+            .timeout(std::time::Duration::from_secs(6 * 60 * 60))
+            .send()
+            .await?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
-            if body.contains("model") && body.contains("not found") {
-                return Err(SlabError::ModelNotFound(req.model));
-            }
             return Err(SlabError::StreamError(format!("HTTP {}: {}", status, body)));
         }
 
@@ -192,43 +478,28 @@ impl OllamaClient {
         let mut stream = resp.bytes_stream();
 
         tokio::spawn(async move {
-            while let Some(chunk) = stream.next().await {
-                match chunk {
-                    Ok(bytes) => {
-                        let text = String::from_utf8_lossy(&bytes);
-                        for line in text.lines() {
-                            if line.is_empty() {
-                                continue;
-                            }
-                            match serde_json::from_str::<ChatResponse>(line) {
-                                Ok(resp) => {
-                                    if let Some(msg) = resp.message {
-                                        if !msg.content.is_empty()
-                                            && tx.send(Ok(msg.content)).await.is_err()
-                                        {
-                                            return;
-                                        }
-                                    }
-                                    if resp.done {
-                                        return;
-                                    }
-                                }
-                                Err(e) => {
-                                    let _ = tx
-                                        .send(Err(SlabError::StreamError(format!(
-                                            "Parse error: {}",
-                                            e
-                                        ))))
-                                        .await;
-                                    return;
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
+            // Progress lines can be split across chunks, so buffer until a newline arrives
+            let mut buffer = LineBuffer::default();
+            loop {
+                let (lines, eof) = match stream.next().await {
+                    Some(Ok(bytes)) => (buffer.push(&bytes), false),
+                    Some(Err(e)) => {
                         let _ = tx.send(Err(SlabError::ConnectionError(e))).await;
                         return;
                     }
+                    // The final status may arrive without a trailing newline
+                    None => (buffer.finish().into_iter().collect(), true),
+                };
+                for line in lines {
+                    let item = serde_json::from_str::<PullStatus>(&line)
+                        .map_err(|e| SlabError::StreamError(format!("Parse error: {}", e)));
+                    let failed = item.is_err();
+                    if tx.send(item).await.is_err() || failed {
+                        return;
+                    }
+                }
+                if eof {
+                    return;
                 }
             }
         });
@@ -237,24 +508,17 @@ impl OllamaClient {
     }
 
     /// Send a chat request without streaming (returns complete response)
-    pub async fn chat(&self, request: ChatRequest) -> Result<String> {
-        let url = format!("{}/api/chat", self.base_url);
+    pub async fn chat(&self, request: ChatRequest) -> Result<ChatReply> {
         let mut req = request;
         req.stream = Some(false);
 
-        let resp = self.client.post(&url).json(&req).send().await?;
-
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            if body.contains("model") && body.contains("not found") {
-                return Err(SlabError::ModelNotFound(req.model));
-            }
-            return Err(SlabError::StreamError(format!("HTTP {}: {}", status, body)));
-        }
+        let resp = self.post_chat(&req).await?;
 
         let chat_resp: ChatResponse = resp.json().await?;
-        Ok(chat_resp.message.map(|m| m.content).unwrap_or_default())
+        Ok(ChatReply {
+            content: chat_resp.message.map(|m| m.content).unwrap_or_default(),
+            done_reason: chat_resp.done_reason,
+        })
     }
 
     /// Send a generate request (single prompt, not chat)
@@ -269,10 +533,7 @@ impl OllamaClient {
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
-            if body.contains("model") && body.contains("not found") {
-                return Err(SlabError::ModelNotFound(req.model));
-            }
-            return Err(SlabError::StreamError(format!("HTTP {}: {}", status, body)));
+            return Err(api_error(&req.model, status, &body));
         }
 
         let gen_resp: GenerateResponse = resp.json().await?;
@@ -280,11 +541,70 @@ impl OllamaClient {
     }
 }
 
+/// Splits an NDJSON byte stream into complete lines. Bytes are only decoded
+/// once their line is complete, so a chunk boundary inside a line (or inside a
+/// multi-byte UTF-8 character) never corrupts the text.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Add a chunk and return the non-empty lines it completed
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// The trailing line once the stream ends without a final newline
+    fn finish(&mut self) -> Option<String> {
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending))
+            .trim()
+            .to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// Error body returned by Ollama on a failed request
+#[derive(Debug, Deserialize)]
+struct ApiErrorBody {
+    error: String,
+}
+
+/// Map a failed Ollama response to a `SlabError`, telling a missing model
+/// apart from one that is still being loaded into memory
+fn api_error(model: &str, status: reqwest::StatusCode, body: &str) -> SlabError {
+    let message = serde_json::from_str::<ApiErrorBody>(body)
+        .map(|b| b.error)
+        .unwrap_or_else(|_| body.to_string());
+    let lower = message.to_lowercase();
+
+    if lower.contains("model") && lower.contains("not found") {
+        return SlabError::ModelNotFound(model.to_string());
+    }
+    if lower.contains("loading model")
+        || lower.contains("model is loading")
+        || lower.contains("still loading")
+    {
+        return SlabError::ModelLoading(model.to_string());
+    }
+    SlabError::StreamError(format!("HTTP {}: {}", status, message))
+}
+
 impl Message {
     pub fn system(content: impl Into<String>) -> Self {
         Self {
             role: "system".to_string(),
             content: content.into(),
+            typed: None,
         }
     }
 
@@ -292,6 +612,7 @@ impl Message {
         Self {
             role: "user".to_string(),
             content: content.into(),
+            typed: None,
         }
     }
 
@@ -299,8 +620,15 @@ impl Message {
         Self {
             role: "assistant".to_string(),
             content: content.into(),
+            typed: None,
         }
     }
+
+    /// Remember the text the user typed, when it differs from `content`
+    pub fn with_typed(mut self, typed: impl Into<String>) -> Self {
+        self.typed = Some(typed.into());
+        self
+    }
 }
 
 // ── Backend-agnostic dispatch ─────────────────────────────────────────────────
@@ -317,9 +645,10 @@ impl AnyBackend {
     /// Construct the right backend from config.
     pub fn from_config(config: &crate::config::Config) -> Self {
         match config.backend {
-            crate::config::BackendType::Ollama => {
-                AnyBackend::Ollama(OllamaClient::new(&config.ollama_host))
-            }
+            crate::config::BackendType::Ollama => AnyBackend::Ollama(
+                OllamaClient::new(&config.ollama_host)
+                    .with_retry(config.ollama.max_retries, config.ollama.retry_base_ms),
+            ),
             crate::config::BackendType::OpenAi => AnyBackend::OpenAi(
                 crate::openai::OpenAiClient::new(&config.ollama_host, config.api_key.clone()),
             ),
@@ -343,6 +672,46 @@ impl AnyBackend {
     }
 }
 
+impl LlmBackend for AnyBackend {
+    async fn llm_chat(&self, request: ChatRequest) -> Result<ChatReply> {
+        match self {
+            AnyBackend::Ollama(c) => c.llm_chat(request).await,
+            AnyBackend::OpenAi(c) => c.chat(request).await,
+        }
+    }
+
+    async fn llm_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        match self {
+            AnyBackend::Ollama(c) => c.llm_stream(request).await,
+            AnyBackend::OpenAi(c) => c.chat_stream(request).await,
+        }
+    }
+
+    async fn llm_list_models(&self) -> Result<Vec<ModelInfo>> {
+        match self {
+            AnyBackend::Ollama(c) => c.llm_list_models().await,
+            AnyBackend::OpenAi(c) => c.list_models().await,
+        }
+    }
+
+    async fn llm_context_length(&self, model: &str) -> Result<Option<usize>> {
+        match self {
+            AnyBackend::Ollama(c) => c.llm_context_length(model).await,
+            // OpenAI-compatible servers don't report a context window
+            AnyBackend::OpenAi(_) => Ok(None),
+        }
+    }
+
+    async fn llm_pull_model(&self, name: &str) -> Result<mpsc::Receiver<Result<PullStatus>>> {
+        match self {
+            AnyBackend::Ollama(c) => c.pull_model(name).await,
+            AnyBackend::OpenAi(_) => Err(SlabError::Other(
+                "Pulling models is only supported with the Ollama backend".to_string(),
+            )),
+        }
+    }
+}
+
 // ── Unit tests ────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -350,6 +719,234 @@ mod tests {
     use super::*;
     use crate::config::{BackendType, Config};
 
+    fn model(name: &str, size: Option<u64>, quant: Option<&str>) -> ModelInfo {
+        ModelInfo {
+            name: name.to_string(),
+            modified_at: None,
+            size,
+            details: Some(ModelDetails {
+                family: None,
+                parameter_size: None,
+                quantization_level: quant.map(|q| q.to_string()),
+            }),
+        }
+    }
+
+    #[test]
+    fn models_sort_by_name_and_size() {
+        let mut models = [
+            model("qwen2.5:7b", Some(4_700_000_000), Some("Q4_K_M")),
+            model("Llama3:8b", None, None),
+            model("codellama:13b", Some(7_400_000_000), Some("Q4_0")),
+        ];
+
+        models.sort_by(compare_models_by_name);
+        let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["codellama:13b", "Llama3:8b", "qwen2.5:7b"]);
+
+        models.sort_by(compare_models_by_size);
+        let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["codellama:13b", "qwen2.5:7b", "Llama3:8b"]);
+    }
+
+    #[test]
+    fn models_filter_and_quantization() {
+        let models = [
+            model("qwen2.5:7b", None, Some("Q4_K_M")),
+            model("Qwen2.5-coder:14b", None, None),
+            model("llama3:8b", None, None),
+        ];
+        let matched: Vec<&str> = models
+            .iter()
+            .filter(|m| m.matches_filter("qwen"))
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(matched, vec!["qwen2.5:7b", "Qwen2.5-coder:14b"]);
+        assert_eq!(models[0].quantization(), Some("Q4_K_M"));
+        assert_eq!(models[2].quantization(), None);
+    }
+
+    #[test]
+    fn models_to_json() {
+        let mut m = model("qwen2.5:7b", Some(4_700_000_000), None);
+        m.details.as_mut().unwrap().parameter_size = Some("7.6B".to_string());
+        assert_eq!(
+            m.to_json(),
+            serde_json::json!({"name": "qwen2.5:7b", "size": 4_700_000_000u64, "parameter_size": "7.6B"})
+        );
+        assert_eq!(
+            model("gpt-4o", None, None).to_json(),
+            serde_json::json!({"name": "gpt-4o", "size": null, "parameter_size": null})
+        );
+    }
+
+    #[test]
+    fn show_response_reports_context_length() {
+        let body = r#"{
+            "modelfile": "FROM llama3",
+            "parameters": "num_ctx 8192\nstop \"<|eot_id|>\"",
+            "details": {"family": "llama"},
+            "model_info": {
+                "general.architecture": "llama",
+                "general.parameter_count": 8030261248,
+                "llama.context_length": 131072,
+                "llama.embedding_length": 4096
+            }
+        }"#;
+        let show: ShowResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(show.context_length(), Some(131072));
+
+        // Older servers omit model_info entirely
+        let show: ShowResponse = serde_json::from_str(r#"{"modelfile": ""}"#).unwrap();
+        assert_eq!(show.context_length(), None);
+    }
+
+    #[test]
+    fn chat_request_serializes_json_format() {
+        let mut request = ChatRequest {
+            model: "qwen2.5:7b".to_string(),
+            messages: vec![Message::user("list three colors as JSON")],
+            stream: Some(false),
+            options: None,
+            format: Some("json".to_string()),
+        };
+        let body = serde_json::to_string(&request).unwrap();
+        assert!(body.contains(r#""format":"json""#), "{body}");
+
+        request.format = None;
+        let body = serde_json::to_string(&request).unwrap();
+        assert!(!body.contains("format"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn chat_reports_length_done_reason() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"message":{"role":"assistant","content":"The answer is"},"done":true,"done_reason":"length"}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri());
+        let reply = client.chat(hello_request()).await.unwrap();
+        assert_eq!(reply.content, "The answer is");
+        assert_eq!(reply.done_reason.as_deref(), Some("length"));
+    }
+
+    #[test]
+    fn line_buffer_holds_partial_lines_and_utf8() {
+        let mut buffer = LineBuffer::default();
+        let line = "{\"message\":{\"role\":\"assistant\",\"content\":\"café\"},\"done\":false}\n";
+        let bytes = line.as_bytes();
+        // Split inside the two-byte "é"
+        let split = line.find('é').unwrap() + 1;
+
+        assert!(buffer.push(&bytes[..split]).is_empty());
+        assert_eq!(buffer.push(&bytes[split..]), vec![line.trim().to_string()]);
+
+        assert!(buffer.push(b"{\"done\":true}").is_empty());
+        assert_eq!(buffer.finish().as_deref(), Some("{\"done\":true}"));
+        assert_eq!(buffer.finish(), None);
+    }
+
+    #[tokio::test]
+    async fn chat_stream_ends_cleanly_on_stop_sequence() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let body = [
+            r#"{"message":{"role":"assistant","content":"```rust\nfn "},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"main() {} // ✓\n"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop"}"#,
+        ]
+        .join("\n");
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri());
+        let request = ChatRequest {
+            model: "qwen2.5:7b".to_string(),
+            messages: vec![Message::user("write main")],
+            stream: None,
+            options: Some(ModelOptions {
+                stop: vec!["\n```".to_string()],
+                ..Default::default()
+            }),
+            format: None,
+        };
+        let mut rx = client.chat_stream(request).await.unwrap();
+        let mut text = String::new();
+        while let Some(chunk) = rx.recv().await {
+            text.push_str(&chunk.unwrap());
+        }
+        assert_eq!(text, "```rust\nfn main() {} // ✓\n");
+        assert_eq!(rx.done_reason(), Some("stop"));
+
+        let sent: serde_json::Value =
+            serde_json::from_slice(&server.received_requests().await.unwrap()[0].body).unwrap();
+        assert_eq!(sent["options"]["stop"], serde_json::json!(["\n```"]));
+    }
+
+    #[test]
+    fn pull_status_stream_lines_deserialize() {
+        let lines = [
+            r#"{"status":"pulling manifest"}"#,
+            r#"{"status":"pulling 6a0746a1ec1a","digest":"sha256:6a0746a1ec1a","total":4661211424,"completed":1048576}"#,
+            r#"{"status":"success"}"#,
+            r#"{"error":"pull model manifest: file does not exist"}"#,
+        ];
+        let parsed: Vec<PullStatus> = lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(parsed[0].status, "pulling manifest");
+        assert!(parsed[0].total.is_none());
+        assert_eq!(parsed[1].total, Some(4_661_211_424));
+        assert_eq!(parsed[1].completed, Some(1_048_576));
+        assert_eq!(parsed[2].status, "success");
+
+        // Error lines carry no status field
+        assert!(parsed[3].status.is_empty());
+        assert_eq!(
+            parsed[3].error.as_deref(),
+            Some("pull model manifest: file does not exist")
+        );
+    }
+
+    #[tokio::test]
+    async fn pull_model_keeps_final_status_without_newline() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("{\"status\":\"pulling manifest\"}\n{\"status\":\"success\"}"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri());
+        let mut rx = client.pull_model("qwen3:8b").await.unwrap();
+        let mut statuses = Vec::new();
+        while let Some(status) = rx.recv().await {
+            statuses.push(status.unwrap().status);
+        }
+        assert_eq!(statuses, vec!["pulling manifest", "success"]);
+    }
+
     #[test]
     fn from_config_builds_ollama_backend() {
         let config = Config {
@@ -388,27 +985,128 @@ mod tests {
         // OllamaClient trims the slash, so host() should not end with '/'.
         assert!(!backend.host().ends_with('/'));
     }
-}
 
-impl LlmBackend for AnyBackend {
-    async fn llm_chat(&self, request: ChatRequest) -> Result<String> {
-        match self {
-            AnyBackend::Ollama(c) => c.llm_chat(request).await,
-            AnyBackend::OpenAi(c) => c.chat(request).await,
+    fn hello_request() -> ChatRequest {
+        ChatRequest {
+            model: "qwen2.5:7b".to_string(),
+            messages: vec![Message::user("hi")],
+            stream: None,
+            options: None,
+            format: None,
         }
     }
 
-    async fn llm_stream(&self, request: ChatRequest) -> Result<mpsc::Receiver<Result<String>>> {
-        match self {
-            AnyBackend::Ollama(c) => c.llm_stream(request).await,
-            AnyBackend::OpenAi(c) => c.chat_stream(request).await,
-        }
+    #[tokio::test]
+    async fn chat_retries_server_errors_then_succeeds() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(
+                ResponseTemplate::new(503).set_body_string(r#"{"error":"loading model"}"#),
+            )
+            .up_to_n_times(2)
+            .expect(2)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"message":{"role":"assistant","content":"hello"},"done":true}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri()).with_retry(2, 1);
+        assert_eq!(client.chat(hello_request()).await.unwrap().content, "hello");
     }
 
-    async fn llm_list_models(&self) -> Result<Vec<ModelInfo>> {
-        match self {
-            AnyBackend::Ollama(c) => c.llm_list_models().await,
-            AnyBackend::OpenAi(c) => c.list_models().await,
-        }
+    #[tokio::test]
+    async fn chat_gives_up_after_max_retries_and_never_retries_4xx() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(
+                ResponseTemplate::new(503).set_body_string(r#"{"error":"loading model"}"#),
+            )
+            .expect(3)
+            .mount(&server)
+            .await;
+        let client = OllamaClient::new(&server.uri()).with_retry(2, 1);
+        let err = client.chat_stream(hello_request()).await.unwrap_err();
+        assert!(matches!(err, SlabError::ModelLoading(_)));
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(
+                ResponseTemplate::new(404).set_body_string(r#"{"error":"model not found"}"#),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = OllamaClient::new(&server.uri()).with_retry(2, 1);
+        let err = client.chat(hello_request()).await.unwrap_err();
+        assert!(matches!(err, SlabError::ModelNotFound(_)));
+    }
+
+    #[test]
+    fn retry_delay_doubles_and_is_capped() {
+        use std::time::Duration;
+
+        let base = Duration::from_millis(500);
+        assert_eq!(retry_delay(base, 0), Duration::from_millis(500));
+        assert_eq!(retry_delay(base, 3), Duration::from_secs(4));
+        assert_eq!(retry_delay(base, 10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(base, 40), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(Duration::MAX, u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn api_error_maps_model_not_found() {
+        let err = api_error(
+            "ghost:7b",
+            reqwest::StatusCode::NOT_FOUND,
+            r#"{"error":"model \"ghost:7b\" not found, try pulling it first"}"#,
+        );
+        assert!(matches!(err, SlabError::ModelNotFound(ref m) if m == "ghost:7b"));
+    }
+
+    #[test]
+    fn api_error_maps_model_loading() {
+        let err = api_error(
+            "qwen2.5:7b",
+            reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            r#"{"error":"loading model: timed out waiting for llama runner to start"}"#,
+        );
+        assert!(matches!(err, SlabError::ModelLoading(ref m) if m == "qwen2.5:7b"));
+    }
+
+    #[test]
+    fn api_error_keeps_other_messages() {
+        let err = api_error(
+            "qwen2.5:7b",
+            reqwest::StatusCode::BAD_REQUEST,
+            r#"{"error":"invalid options"}"#,
+        );
+        assert_eq!(
+            err.to_string(),
+            "Streaming error: HTTP 400 Bad Request: invalid options"
+        );
+
+        // Non-JSON bodies are passed through as-is
+        let err = api_error(
+            "qwen2.5:7b",
+            reqwest::StatusCode::BAD_GATEWAY,
+            "upstream down",
+        );
+        assert!(err.to_string().ends_with("upstream down"));
     }
 }
@@ -1,10 +1,9 @@
 use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
 
 use crate::error::{Result, SlabError};
-use crate::ollama::{ChatRequest, Message, ModelInfo};
+use crate::ollama::{ChatReply, ChatRequest, ChatStream, Message, ModelInfo, StreamEvent};
 
 /// OpenAI-compatible client. Works with vllm, llama.cpp --server, LM Studio,
 /// and any other server that implements the `/v1/chat/completions` API.
@@ -20,8 +19,8 @@ pub struct OpenAiClient {
 /// The POST body for /v1/chat/completions.
 /// Note: we do NOT map Ollama's `num_ctx` here — that controls the context
 /// *window* size, while OpenAI's `max_tokens` controls the *output* limit.
-/// They are semantically different; omitting max_tokens lets the server use
-/// its own default output limit.
+/// `max_tokens` comes from `num_predict` instead; when that is unset it is
+/// omitted so the server uses its own default output limit.
 #[derive(Debug, Serialize)]
 struct OpenAiChatRequest<'a> {
     model: &'a str,
@@ -30,21 +29,42 @@ struct OpenAiChatRequest<'a> {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<usize>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
     stream: bool,
 }
 
+/// `response_format` body; `json_object` is the OpenAI equivalent of Ollama's `format: "json"`
+#[derive(Debug, Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
 impl<'a> OpenAiChatRequest<'a> {
     fn from_chat_request(req: &'a ChatRequest, stream: bool) -> Self {
-        let (temperature, top_p) = req
+        let (temperature, top_p, seed) = req
             .options
             .as_ref()
-            .map(|o| (o.temperature, o.top_p))
-            .unwrap_or((None, None));
+            .map(|o| (o.temperature, o.top_p, o.seed))
+            .unwrap_or((None, None, None));
         Self {
             model: &req.model,
             messages: &req.messages,
             temperature,
             top_p,
+            seed,
+            max_tokens: req.options.as_ref().and_then(|o| o.num_predict),
+            stop: req.options.as_ref().map_or(&[], |o| o.stop.as_slice()),
+            response_format: (req.format.as_deref() == Some("json")).then_some(ResponseFormat {
+                kind: "json_object",
+            }),
             stream,
         }
     }
@@ -60,6 +80,8 @@ struct OpenAiChatResponse {
 #[derive(Debug, Deserialize)]
 struct OpenAiChoice {
     message: OpenAiMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -101,6 +123,44 @@ struct OpenAiModelEntry {
     id: String,
 }
 
+// ── Error mapping ─────────────────────────────────────────────────────────────
+
+/// The message from an OpenAI-style error body. Servers disagree on the shape:
+/// OpenAI sends `{"error":{"message":..}}`, vllm `{"object":"error","message":..}`
+/// and some proxies `{"error":"..."}`.
+fn error_message(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let message = match value.get("error") {
+        Some(serde_json::Value::String(s)) => Some(s.as_str()),
+        Some(error) => error.get("message").and_then(|m| m.as_str()),
+        None => value.get("message").and_then(|m| m.as_str()),
+    };
+    message.map(String::from)
+}
+
+/// Map a failed OpenAI-compatible response to a `SlabError`
+fn api_error(model: &str, base_url: &str, status: reqwest::StatusCode, body: &str) -> SlabError {
+    let message = error_message(body).unwrap_or_else(|| body.to_string());
+    let lower = message.to_lowercase();
+
+    if status == reqwest::StatusCode::NOT_FOUND
+        || lower.contains("model")
+            && (lower.contains("not found") || lower.contains("does not exist"))
+    {
+        return SlabError::ModelNotFound(model.to_string());
+    }
+    if lower.contains("loading model") || lower.contains("model is loading") {
+        return SlabError::ModelLoading(model.to_string());
+    }
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return SlabError::ConfigError(format!(
+            "{} rejected the request ({}); check api_key",
+            base_url, message
+        ));
+    }
+    SlabError::StreamError(format!("HTTP {}: {}", status, message))
+}
+
 // ── Client implementation ─────────────────────────────────────────────────────
 
 impl OpenAiClient {
@@ -110,9 +170,13 @@ impl OpenAiClient {
             .build()
             .expect("Failed to create HTTP client");
 
+        // Endpoint paths already include `/v1`, so accept bases written either way
+        let base_url = base_url.trim_end_matches('/');
+        let base_url = base_url.strip_suffix("/v1").unwrap_or(base_url);
+
         Self {
             client,
-            base_url: base_url.trim_end_matches('/').to_string(),
+            base_url: base_url.to_string(),
             api_key,
         }
     }
@@ -132,6 +196,14 @@ impl OpenAiClient {
         let req = self.auth(self.client.get(&url));
         match req.send().await {
             Ok(resp) if resp.status().is_success() => Ok(()),
+            Ok(resp)
+                if resp.status() == reqwest::StatusCode::UNAUTHORIZED
+                    || resp.status() == reqwest::StatusCode::FORBIDDEN =>
+            {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                Err(api_error("", &self.base_url, status, &text))
+            }
             Ok(_) => Err(SlabError::BackendNotReachable(self.base_url.clone())),
             Err(e) if e.is_connect() => Err(SlabError::BackendNotReachable(self.base_url.clone())),
             Err(e) => Err(SlabError::ConnectionError(e)),
@@ -162,7 +234,7 @@ impl OpenAiClient {
     }
 
     /// Send a non-streaming chat request to `POST /v1/chat/completions`.
-    pub async fn chat(&self, request: ChatRequest) -> Result<String> {
+    pub async fn chat(&self, request: ChatRequest) -> Result<ChatReply> {
         let url = format!("{}/v1/chat/completions", self.base_url);
         let body = OpenAiChatRequest::from_chat_request(&request, false);
         let req = self.auth(self.client.post(&url).json(&body));
@@ -171,22 +243,18 @@ impl OpenAiClient {
         if !resp.status().is_success() {
             let status = resp.status();
             let text = resp.text().await.unwrap_or_default();
-            if status.as_u16() == 404
-                || text.to_lowercase().contains("model")
-                    && text.to_lowercase().contains("not found")
-            {
-                return Err(SlabError::ModelNotFound(request.model));
-            }
-            return Err(SlabError::StreamError(format!("HTTP {}: {}", status, text)));
+            return Err(api_error(&request.model, &self.base_url, status, &text));
         }
 
         let chat_resp: OpenAiChatResponse = resp.json().await?;
-        Ok(chat_resp
-            .choices
-            .into_iter()
-            .next()
-            .and_then(|c| c.message.content)
-            .unwrap_or_default())
+        let Some(choice) = chat_resp.choices.into_iter().next() else {
+            return Ok(ChatReply::default());
+        };
+        // "length" means the reply hit `max_tokens`
+        Ok(ChatReply {
+            content: choice.message.content.unwrap_or_default(),
+            done_reason: choice.finish_reason,
+        })
     }
 
     /// Send a streaming chat request. Returns a channel that yields content
@@ -198,10 +266,7 @@ impl OpenAiClient {
     ///   - Blank lines separate events and must be ignored
     ///   - The first delta often has `role` but empty `content`
     ///   - The final delta has no `content` key, only `finish_reason`
-    pub async fn chat_stream(
-        &self,
-        request: ChatRequest,
-    ) -> Result<mpsc::Receiver<Result<String>>> {
+    pub async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream> {
         let url = format!("{}/v1/chat/completions", self.base_url);
         let body = OpenAiChatRequest::from_chat_request(&request, true);
         let req = self.auth(self.client.post(&url).json(&body));
@@ -210,21 +275,16 @@ impl OpenAiClient {
         if !resp.status().is_success() {
             let status = resp.status();
             let text = resp.text().await.unwrap_or_default();
-            if status.as_u16() == 404
-                || text.to_lowercase().contains("model")
-                    && text.to_lowercase().contains("not found")
-            {
-                return Err(SlabError::ModelNotFound(request.model));
-            }
-            return Err(SlabError::StreamError(format!("HTTP {}: {}", status, text)));
+            return Err(api_error(&request.model, &self.base_url, status, &text));
         }
 
-        let (tx, rx) = mpsc::channel(100);
+        let (tx, rx) = ChatStream::channel(100);
         let mut stream = resp.bytes_stream();
 
         tokio::spawn(async move {
             // Accumulate a partial line buffer to handle chunks that split mid-line.
             let mut buf = String::new();
+            let mut finish_reason = None;
 
             while let Some(chunk) = stream.next().await {
                 match chunk {
@@ -254,6 +314,17 @@ impl OpenAiClient {
 
                             // The stream terminator — not JSON, do not parse.
                             if json_str == "[DONE]" {
+                                let _ = tx.send(Ok(StreamEvent::Done(finish_reason))).await;
+                                return;
+                            }
+
+                            // Servers report mid-stream failures as an error event
+                            if let Some(message) = json_str
+                                .contains("\"error\"")
+                                .then(|| error_message(json_str))
+                                .flatten()
+                            {
+                                let _ = tx.send(Err(SlabError::StreamError(message))).await;
                                 return;
                             }
 
@@ -263,12 +334,16 @@ impl OpenAiClient {
                                         // `finish_reason` being set means this is
                                         // the last choice; content will be absent.
                                         if choice.finish_reason.is_some() {
+                                            finish_reason = choice.finish_reason;
                                             continue;
                                         }
                                         if let Some(content) = choice.delta.content {
                                             // First chunk has empty content — skip.
                                             if !content.is_empty()
-                                                && tx.send(Ok(content)).await.is_err()
+                                                && tx
+                                                    .send(Ok(StreamEvent::Chunk(content)))
+                                                    .await
+                                                    .is_err()
                                             {
                                                 return;
                                             }
@@ -293,6 +368,8 @@ impl OpenAiClient {
                     }
                 }
             }
+            // Some servers close the stream without sending `[DONE]`
+            let _ = tx.send(Ok(StreamEvent::Done(finish_reason))).await;
         });
 
         Ok(rx)
@@ -332,6 +409,24 @@ mod tests {
         assert!(client.health_check().await.is_ok());
     }
 
+    #[tokio::test]
+    async fn health_check_accepts_base_url_ending_in_v1() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data":[]})))
+            .mount(&server)
+            .await;
+
+        for base in [
+            format!("{}/v1", server.uri()),
+            format!("{}/v1/", server.uri()),
+        ] {
+            let client = make_client(&base);
+            assert!(client.health_check().await.is_ok(), "{}", base);
+        }
+    }
+
     #[tokio::test]
     async fn health_check_server_error_returns_err() {
         let server = MockServer::start().await;
@@ -407,9 +502,10 @@ mod tests {
             messages: vec![Message::user("hi")],
             stream: None,
             options: None,
+            format: None,
         };
         let response = client.chat(request).await.unwrap();
-        assert_eq!(response, "Hello!");
+        assert_eq!(response.content, "Hello!");
     }
 
     #[tokio::test]
@@ -429,6 +525,7 @@ mod tests {
             messages: vec![Message::user("hi")],
             stream: None,
             options: None,
+            format: None,
         };
         let err = client.chat(request).await.unwrap_err();
         assert!(
@@ -460,7 +557,11 @@ mod tests {
                 temperature: Some(0.3),
                 top_p: Some(0.8),
                 num_ctx: None,
+                seed: None,
+                num_predict: None,
+                stop: vec!["```".to_string()],
             }),
+            format: None,
         };
         client.chat(request).await.unwrap();
 
@@ -475,6 +576,23 @@ mod tests {
         assert!((top_p - 0.8).abs() < 1e-5, "top_p mismatch: {top_p}");
         // num_ctx must NOT be forwarded to the OpenAI endpoint.
         assert!(body.get("max_tokens").is_none());
+        assert!(body.get("response_format").is_none());
+        assert_eq!(body["stop"], json!(["```"]));
+    }
+
+    #[test]
+    fn json_format_maps_to_response_format() {
+        let request = ChatRequest {
+            model: "llama3".to_string(),
+            messages: vec![Message::user("hi")],
+            stream: None,
+            options: None,
+            format: Some("json".to_string()),
+        };
+        let body =
+            serde_json::to_value(OpenAiChatRequest::from_chat_request(&request, false)).unwrap();
+        assert_eq!(body["response_format"], json!({"type": "json_object"}));
+        assert!(body.get("format").is_none());
     }
 
     // ── API key ───────────────────────────────────────────────────────────────
@@ -550,6 +668,7 @@ mod tests {
             messages: vec![Message::user("hi")],
             stream: None,
             options: None,
+            format: None,
         };
 
         let mut rx = client.chat_stream(request).await.unwrap();
@@ -559,6 +678,7 @@ mod tests {
         }
 
         assert_eq!(tokens, vec!["Hello", " world"]);
+        assert_eq!(rx.done_reason(), Some("stop"));
     }
 
     #[tokio::test]
@@ -589,6 +709,7 @@ mod tests {
             messages: vec![Message::user("hi")],
             stream: None,
             options: None,
+            format: None,
         };
 
         let mut rx = client.chat_stream(request).await.unwrap();
@@ -600,4 +721,76 @@ mod tests {
         // Empty-string content must not be forwarded.
         assert_eq!(tokens, vec!["Hi"]);
     }
+
+    #[test]
+    fn api_error_reads_each_body_shape() {
+        let openai = r#"{"error":{"message":"The model `ghost` does not exist","type":"invalid_request_error","code":"model_not_found"}}"#;
+        let err = api_error(
+            "ghost",
+            "http://x",
+            reqwest::StatusCode::BAD_REQUEST,
+            openai,
+        );
+        assert!(matches!(err, SlabError::ModelNotFound(ref m) if m == "ghost"));
+
+        let vllm = r#"{"object":"error","message":"max_tokens must be at least 1","type":"BadRequestError","code":400}"#;
+        let err = api_error("m", "http://x", reqwest::StatusCode::BAD_REQUEST, vllm);
+        assert_eq!(
+            err.to_string(),
+            "Streaming error: HTTP 400 Bad Request: max_tokens must be at least 1"
+        );
+
+        let llama_cpp =
+            r#"{"error":{"code":503,"message":"Loading model","type":"unavailable_error"}}"#;
+        let err = api_error(
+            "m",
+            "http://x",
+            reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            llama_cpp,
+        );
+        assert!(matches!(err, SlabError::ModelLoading(_)));
+
+        let err = api_error(
+            "m",
+            "http://x",
+            reqwest::StatusCode::UNAUTHORIZED,
+            "bad key",
+        );
+        assert!(matches!(err, SlabError::ConfigError(ref m) if m.contains("check api_key")));
+    }
+
+    #[tokio::test]
+    async fn chat_stream_reports_error_event() {
+        let sse_body = [
+            r#"data: {"choices":[{"delta":{"content":"Hi"},"finish_reason":null}]}"#,
+            r#"data: {"error":{"message":"engine crashed","type":"internal_error"}}"#,
+        ]
+        .join("\n\n")
+            + "\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(sse_body),
+            )
+            .mount(&server)
+            .await;
+
+        let client = make_client(&server.uri());
+        let request = ChatRequest {
+            model: "llama3".to_string(),
+            messages: vec![Message::user("hi")],
+            stream: None,
+            options: None,
+            format: None,
+        };
+
+        let mut rx = client.chat_stream(request).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().unwrap(), "Hi");
+        let err = rx.recv().await.unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "Streaming error: engine crashed");
+    }
 }
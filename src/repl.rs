@@ -4,25 +4,34 @@ use crossterm::event::{
 };
 use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::completion::{CompletionContext, CompletionEngine, CompletionKind};
+use crate::completion::{
+    at_token_at, search_history, suggest_similar, CompletionContext, CompletionEngine,
+    CompletionKind,
+};
 use crate::config::{find_project_root, Config};
 use crate::context::ContextManager;
-use crate::error::Result;
+use crate::error::{Result, SlabError};
 use crate::file_ops::{
-    execute_operations, parse_exec_operations, parse_file_operations, FileOperationUI,
+    execute_operations, filter_allowed_operations, is_risky_command, parse_exec_operations,
+    parse_file_operations, print_command_output, review_and_apply, run_post_apply_hook,
+    run_pre_apply_hook, shell_command, FileOperation, FileOperationUI, HookRun,
 };
 use crate::highlight::Highlighter;
 use crate::ollama::{ChatRequest, LlmBackend, Message, ModelOptions, OllamaClient};
 use crate::rules::RuleEngine;
-use crate::session::Session;
+use crate::session::{messages_after_mark, shift_marks, Mark, Session, AUTOSAVE_NAME};
 use crate::templates::TemplateManager;
 use crate::theme::{BoxStyle, Theme, ThemeName};
-use crate::ui::{terminal_width, BoxRenderer};
+use crate::think::{strip_think_blocks, ThinkFilter, ThinkSegment};
+use crate::ui::{
+    display_width, has_markdown_table, render_markdown_tables, show_pull_progress, terminal_width,
+    truncate_str, BoxRenderer,
+};
 
 pub struct Repl<B: LlmBackend = OllamaClient> {
     client: B,
@@ -36,12 +45,36 @@ pub struct Repl<B: LlmBackend = OllamaClient> {
     streaming: bool,
     project_root: PathBuf,
     file_ops_enabled: bool,
+    /// `slab chat --readonly`: never apply file operations or run commands
+    readonly: bool,
     history: Vec<String>,
     cached_models: Option<Vec<String>>,
     #[allow(dead_code)]
     history_index: usize,
     theme: Theme,
     box_style: BoxStyle,
+    /// Session-only sampling overrides set with /temp and /top_p
+    temperature_override: Option<f32>,
+    top_p_override: Option<f32>,
+    /// Ask the backend for JSON output (/json on)
+    json_mode: bool,
+    /// Text to pre-fill the next prompt with (set by /edit)
+    prefill: Option<String>,
+    /// The next message replaces the last user message and its replies (/edit)
+    replace_last_user: bool,
+    /// Message count at the last autosave (ui.autosave_interval)
+    autosaved_count: usize,
+    /// Where the autosave goes and /load looks; the project's session directory when unset
+    sessions_dir: Option<PathBuf>,
+    /// Name of the session last loaded (`--session`, `--continue` or /load)
+    session_name: Option<String>,
+    /// Bookmarks set with /mark, saved with the session
+    marks: Vec<Mark>,
+    /// Text streamed before Ctrl+C, added to the conversation by `respond` unless
+    /// ui.keep_partial_on_interrupt is off
+    partial_response: Option<String>,
+    /// The last batch of applied file operations, reverted by /undo
+    last_applied: Vec<FileOperation>,
 }
 
 impl<B: LlmBackend> Repl<B> {
@@ -51,7 +84,9 @@ impl<B: LlmBackend> Repl<B> {
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
         // Create context manager
-        let mut context = ContextManager::new(config.context_limit, project_root.clone());
+        let mut context =
+            ContextManager::new(config.effective_context_limit(), project_root.clone());
+        context.set_max_file_tokens(config.ui.max_file_tokens);
 
         // Set system prompt if configured
         let model_config = config.get_model_config(&model);
@@ -69,8 +104,7 @@ impl<B: LlmBackend> Repl<B> {
 
         // Load rules
         let mut rules = RuleEngine::new();
-        let rules_dir = project_root.join(".slab/rules");
-        rules.load_from_directory(&rules_dir);
+        rules.load_from_directories(&get_rules_directories(&project_root));
 
         // Inject rules into context if any were loaded
         if rules.rule_count() > 0 {
@@ -81,20 +115,22 @@ impl<B: LlmBackend> Repl<B> {
         }
 
         // Create highlighter for syntax highlighting
-        let highlighter = Highlighter::new();
+        let mut highlighter = Highlighter::from_config(&config.ui);
+        highlighter.set_color(console::colors_enabled());
 
         // Create completion engine with template commands
-        let mut completion_engine = CompletionEngine::new();
-        let template_cmds: Vec<(String, String)> = templates
-            .list()
-            .iter()
-            .map(|t| (t.command.clone(), t.description.clone()))
-            .collect();
-        completion_engine.add_template_commands(template_cmds);
+        let mut completion_engine = CompletionEngine::with_fuzzy(config.ui.fuzzy_completion)
+            .with_max_file_items(config.ui.max_completion_items);
+        completion_engine.add_template_commands(template_completions(&templates, &config));
 
         // Load theme from config
-        let theme = ThemeName::from_str(&config.ui.theme).to_theme();
-        let box_style = BoxStyle::from_str(&config.ui.box_style);
+        let theme = ThemeName::from_str(&config.ui.theme).to_theme(&config.ui.custom_theme);
+        // Without color (NO_COLOR, --no-color, or piped output) fall back to ASCII borders
+        let box_style = if console::colors_enabled() {
+            BoxStyle::from_str(&config.ui.box_style)
+        } else {
+            BoxStyle::Ascii
+        };
 
         Self {
             client,
@@ -108,11 +144,23 @@ impl<B: LlmBackend> Repl<B> {
             streaming,
             project_root,
             file_ops_enabled: true,
+            readonly: false,
             history: Vec::new(),
             cached_models: None,
             history_index: 0,
             theme,
             box_style,
+            temperature_override: None,
+            top_p_override: None,
+            json_mode: false,
+            prefill: None,
+            replace_last_user: false,
+            autosaved_count: 0,
+            sessions_dir: None,
+            session_name: None,
+            partial_response: None,
+            marks: Vec::new(),
+            last_applied: Vec::new(),
         }
     }
 
@@ -124,29 +172,16 @@ impl<B: LlmBackend> Repl<B> {
         if let Ok(models) = self.client.llm_list_models().await {
             self.cached_models = Some(models.into_iter().map(|m| m.name).collect());
         }
+        self.adopt_model_context_length().await;
 
         self.print_welcome();
 
         loop {
             match self.read_input()? {
                 Some(input) => {
-                    let trimmed = input.trim();
-
-                    // Handle commands
-                    if trimmed.starts_with('/') {
-                        if self.handle_command(trimmed).await? {
-                            continue;
-                        } else {
-                            break;
-                        }
-                    }
-
-                    if trimmed.is_empty() {
-                        continue;
+                    if !self.process_input(&input).await? {
+                        break;
                     }
-
-                    // Send message to Ollama
-                    self.send_message(trimmed).await?;
                 }
                 None => {
                     // Ctrl+D pressed
@@ -159,6 +194,115 @@ impl<B: LlmBackend> Repl<B> {
         Ok(())
     }
 
+    /// Run a script of REPL input without a TTY: each line is echoed after the
+    /// prompt and handled as if typed. Blank lines are skipped; a line starting
+    /// with `#` is a prompt like any other. `/exit` or the end of the script ends the run.
+    pub async fn run_script(&mut self, script: &str) -> Result<()> {
+        if let Ok(models) = self.client.llm_list_models().await {
+            self.cached_models = Some(models.into_iter().map(|m| m.name).collect());
+        }
+        self.adopt_model_context_length().await;
+
+        for line in script.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            self.print_prompt();
+            println!("{}", trimmed);
+            if !self.process_input(trimmed).await? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle one line of input, then autosave if enough messages were added.
+    /// Returns `false` when the REPL should exit.
+    async fn process_input(&mut self, input: &str) -> Result<bool> {
+        let keep_going = self.handle_input(input).await?;
+        self.maybe_autosave();
+        Ok(keep_going)
+    }
+
+    /// Handle one line of input: a `/command` or a message for the model.
+    /// Returns `false` when the REPL should exit.
+    async fn handle_input(&mut self, input: &str) -> Result<bool> {
+        let trimmed = input.trim();
+        // A pending /edit only applies when the very next input is a message
+        let replace_last_user = std::mem::take(&mut self.replace_last_user);
+
+        // Handle commands
+        if trimmed.starts_with('/') {
+            return self.handle_command(trimmed).await;
+        }
+
+        if trimmed.is_empty() {
+            return Ok(true);
+        }
+
+        if replace_last_user {
+            self.context.truncate_from_last_user();
+        }
+
+        // Send message to Ollama
+        match self.send_message(trimmed).await {
+            Err(e @ SlabError::ContextOverflow { .. }) => {
+                println!("{} {}", style("Error:").red(), e);
+                println!(
+                    "  {}",
+                    style(
+                        "Try /remove <file>, raising context_limit, or setting auto_prune = true."
+                    )
+                    .dim()
+                );
+            }
+            Err(SlabError::ModelNotFound(model)) => {
+                // Drop the unanswered message so it isn't sent twice after switching
+                if self
+                    .context
+                    .messages()
+                    .last()
+                    .is_some_and(|m| m.role == "user")
+                {
+                    self.context.pop_message();
+                }
+                println!(
+                    "{} {}",
+                    style("Error:").red(),
+                    SlabError::ModelNotFound(model.clone())
+                );
+                println!(
+                    "  {}",
+                    style(format!(
+                        "Pull it with `slab pull {}`, or switch with /model <name>.",
+                        model
+                    ))
+                    .dim()
+                );
+                self.print_model_suggestions(&model);
+            }
+            Err(e @ SlabError::ModelLoading(_)) => {
+                if self
+                    .context
+                    .messages()
+                    .last()
+                    .is_some_and(|m| m.role == "user")
+                {
+                    self.context.pop_message();
+                }
+                println!("{} {}", style("Error:").red(), e);
+                println!(
+                    "  {}",
+                    style("The model is still loading; try again in a few seconds.").dim()
+                );
+            }
+            other => other?,
+        }
+        Ok(true)
+    }
+
     fn print_welcome(&self) {
         // Show ASCII banner if enabled
         if self.config.ui.show_banner {
@@ -197,6 +341,20 @@ impl<B: LlmBackend> Repl<B> {
         println!();
     }
 
+    fn set_theme(&mut self, theme: ThemeName) {
+        self.config.ui.theme = theme.name().to_string();
+        self.theme = theme.to_theme(&self.config.ui.custom_theme);
+    }
+
+    /// Read-only mode: file operations stay off and no commands are run,
+    /// regardless of `ui.auto_apply_file_ops`
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+        if readonly {
+            self.file_ops_enabled = false;
+        }
+    }
+
     fn print_status_bar(&self) {
         let width = terminal_width().min(80);
         let chars = self.box_style.chars();
@@ -204,12 +362,23 @@ impl<B: LlmBackend> Repl<B> {
 
         // Build status content
         let model_str = format!(" Model: {} ", self.model);
+        let readonly_str = if self.readonly { " READ-ONLY " } else { "" };
         let context_str = format!(
-            " Context: {}f | {}t ",
-            summary.files_count, summary.tokens_used
+            " Context: {}f | {} ",
+            summary.files_count,
+            self.format_context_bar(summary.tokens_used, summary.token_budget)
         );
+        let context_style = match BudgetLevel::from_usage(summary.tokens_used, summary.token_budget)
+        {
+            BudgetLevel::Normal => &self.theme.secondary,
+            BudgetLevel::Warning => &self.theme.warning,
+            BudgetLevel::Critical => &self.theme.error,
+        };
 
-        let content_len = model_str.len() + context_str.len() + 3; // +3 for separators
+        let content_len = display_width(&model_str)
+            + display_width(readonly_str)
+            + display_width(&context_str)
+            + 3; // +3 for separators
         let padding = width.saturating_sub(content_len + 2);
 
         // Top border
@@ -226,11 +395,12 @@ impl<B: LlmBackend> Repl<B> {
 
         // Content line
         println!(
-            "{} {}{}{}{}{}",
+            "{} {}{}{}{}{}{}",
             self.theme.border.apply_to(chars.vertical),
             self.theme.warning.apply_to(&model_str),
+            self.theme.error.apply_to(readonly_str),
             self.theme.border.apply_to(chars.vertical),
-            self.theme.secondary.apply_to(&context_str),
+            context_style.apply_to(&context_str),
             " ".repeat(padding),
             self.theme.border.apply_to(chars.vertical)
         );
@@ -248,14 +418,17 @@ impl<B: LlmBackend> Repl<B> {
 
     fn format_context_bar(&self, used: usize, budget: usize) -> String {
         const BAR_WIDTH: usize = 8;
-        let filled = if budget > 0 {
-            (used * BAR_WIDTH / budget).min(BAR_WIDTH)
+        let filled = (used * BAR_WIDTH)
+            .checked_div(budget)
+            .map_or(0, |f| f.min(BAR_WIDTH));
+        let empty = BAR_WIDTH - filled;
+        let pct = (used * 100).checked_div(budget).unwrap_or(0);
+        let (fill, blank) = if console::colors_enabled() {
+            ("█", "░")
         } else {
-            0
+            ("#", "-")
         };
-        let empty = BAR_WIDTH - filled;
-        let pct = if budget > 0 { used * 100 / budget } else { 0 };
-        let bar: String = "█".repeat(filled) + &"░".repeat(empty);
+        let bar: String = fill.repeat(filled) + &blank.repeat(empty);
         format!("{} {}% ({}/{}t)", bar, pct, used, budget)
     }
 
@@ -263,6 +436,33 @@ impl<B: LlmBackend> Repl<B> {
         let summary = self.context.summary();
         let bar = self.format_context_bar(summary.tokens_used, summary.token_budget);
 
+        if let Some(format) = &self.config.ui.prompt_format {
+            let cwd = std::env::current_dir().unwrap_or_else(|_| self.project_root.clone());
+            let cwd = match dirs_next::home_dir().and_then(|home| {
+                cwd.strip_prefix(&home)
+                    .ok()
+                    .map(|rest| Path::new("~").join(rest))
+            }) {
+                Some(short) => short.display().to_string(),
+                None => cwd.display().to_string(),
+            };
+            print!(
+                "{}",
+                render_prompt_format(
+                    format,
+                    &[
+                        ("model", self.model.clone()),
+                        ("bar", bar),
+                        ("files", summary.files_count.to_string()),
+                        ("tokens", summary.tokens_used.to_string()),
+                        ("cwd", cwd),
+                    ],
+                )
+            );
+            io::stdout().flush().ok();
+            return;
+        }
+
         // [model]
         print!(
             "{}{}{} ",
@@ -299,9 +499,13 @@ impl<B: LlmBackend> Repl<B> {
     fn read_input(&mut self) -> Result<Option<String>> {
         self.print_prompt();
 
-        let mut input = String::new();
-        let mut cursor_pos: usize = 0;
+        let mut input = self.prefill.take().unwrap_or_default();
+        let mut cursor_pos: usize = input.len();
         let mut stdout = io::stdout();
+        if !input.is_empty() {
+            print!("{}", input.replace('\n', "↵"));
+            stdout.flush().ok();
+        }
         let mut history_index = self.history.len();
         let mut saved_input = String::new();
         let mut current_preview: Option<String> = None;
@@ -362,8 +566,41 @@ impl<B: LlmBackend> Repl<B> {
                                 self.print_welcome();
                                 return Ok(Some(String::new()));
                             }
-                            // Enter - submit
-                            (KeyCode::Enter, _) => {
+                            // Ctrl+R - incremental reverse history search
+                            (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                                if let Some(found) = self.reverse_search(&mut stdout) {
+                                    input = found;
+                                    history_index = self.history.len();
+                                }
+                                print!("\r\x1b[2K");
+                                self.print_prompt();
+                                cursor_pos = input.len();
+                                print!("{}", input.replace('\n', "↵"));
+                                stdout.flush().ok();
+                            }
+                            // Alt+Enter, or Enter after a trailing `\`, inserts a newline
+                            // (as does plain Enter when `ui.submit_on_enter` is off)
+                            (KeyCode::Enter, modifiers)
+                                if modifiers.contains(KeyModifiers::ALT)
+                                    || !self.config.ui.submit_on_enter
+                                    || continues_line(&input, cursor_pos) =>
+                            {
+                                let screen_cursor_pos = cursor_pos;
+                                if continues_line(&input, cursor_pos) {
+                                    cursor_pos -= 1;
+                                    input.remove(cursor_pos);
+                                }
+                                input.insert(cursor_pos, '\n');
+                                cursor_pos += 1;
+                                self.redraw_full_input(
+                                    &input,
+                                    cursor_pos,
+                                    screen_cursor_pos,
+                                    &mut stdout,
+                                );
+                            }
+                            // Enter or Ctrl+J - submit
+                            (KeyCode::Enter, _) | (KeyCode::Char('j'), KeyModifiers::CONTROL) => {
                                 crossterm::execute!(stdout, DisableBracketedPaste).ok();
                                 crossterm::terminal::disable_raw_mode().ok();
                                 println!();
@@ -391,70 +628,73 @@ impl<B: LlmBackend> Repl<B> {
                                 }
                             }
                             // Left arrow - move cursor left
-                            (KeyCode::Left, _) => {
-                                if cursor_pos > 0 {
-                                    cursor_pos -= 1;
-                                    print!("\x1b[D");
-                                    stdout.flush().ok();
-                                }
+                            (KeyCode::Left, _) if cursor_pos > 0 => {
+                                cursor_pos -= 1;
+                                print!("\x1b[D");
+                                stdout.flush().ok();
                             }
                             // Home - move cursor to start
-                            (KeyCode::Home, _) => {
-                                if cursor_pos > 0 {
-                                    print!("\x1b[{}D", cursor_pos);
-                                    cursor_pos = 0;
-                                    stdout.flush().ok();
-                                }
+                            (KeyCode::Home, _) if cursor_pos > 0 => {
+                                print!("\x1b[{}D", cursor_pos);
+                                cursor_pos = 0;
+                                stdout.flush().ok();
                             }
                             // End - move cursor to end
-                            (KeyCode::End, _) => {
-                                if cursor_pos < input.len() {
-                                    print!("\x1b[{}C", input.len() - cursor_pos);
-                                    cursor_pos = input.len();
-                                    stdout.flush().ok();
-                                }
+                            (KeyCode::End, _) if cursor_pos < input.len() => {
+                                print!("\x1b[{}C", input.len() - cursor_pos);
+                                cursor_pos = input.len();
+                                stdout.flush().ok();
                             }
                             // Up arrow - history back
-                            (KeyCode::Up, _) => {
-                                if !self.history.is_empty() && history_index > 0 {
-                                    // Save current input on first up
-                                    if history_index == self.history.len() {
-                                        saved_input = input.clone();
-                                    }
-                                    history_index -= 1;
-                                    // Clear current line
-                                    self.clear_input(&input, cursor_pos);
-                                    input = self.history[history_index].clone();
-                                    cursor_pos = input.len();
-                                    print!("{}", input);
-                                    stdout.flush().ok();
+                            (KeyCode::Up, _) if !self.history.is_empty() && history_index > 0 => {
+                                // Save current input on first up
+                                if history_index == self.history.len() {
+                                    saved_input = input.clone();
                                 }
+                                history_index -= 1;
+                                // Clear current line
+                                self.clear_input(&input, cursor_pos);
+                                input = self.history[history_index].clone();
+                                cursor_pos = input.len();
+                                print!("{}", input.replace('\n', "↵"));
+                                stdout.flush().ok();
                             }
                             // Down arrow - history forward
-                            (KeyCode::Down, _) => {
-                                if history_index < self.history.len() {
-                                    // Clear current line
-                                    self.clear_input(&input, cursor_pos);
-                                    history_index += 1;
-                                    if history_index == self.history.len() {
-                                        input = saved_input.clone();
-                                    } else {
-                                        input = self.history[history_index].clone();
-                                    }
-                                    cursor_pos = input.len();
-                                    print!("{}", input);
-                                    stdout.flush().ok();
+                            (KeyCode::Down, _) if history_index < self.history.len() => {
+                                // Clear current line
+                                self.clear_input(&input, cursor_pos);
+                                history_index += 1;
+                                if history_index == self.history.len() {
+                                    input = saved_input.clone();
+                                } else {
+                                    input = self.history[history_index].clone();
                                 }
+                                cursor_pos = input.len();
+                                print!("{}", input.replace('\n', "↵"));
+                                stdout.flush().ok();
                             }
                             // Tab - command completion
                             (KeyCode::Tab, _) => {
-                                let completions = self.get_completions(&input);
+                                // Mid-line, complete only the @reference under the cursor
+                                // and keep whatever follows it
+                                let (completions, tail_len) = match at_token_at(&input, cursor_pos)
+                                {
+                                    Some(token) if cursor_pos < input.len() => (
+                                        self.get_at_completions(&input, cursor_pos),
+                                        input.len() - token.end,
+                                    ),
+                                    _ => (self.get_completions(&input), 0),
+                                };
                                 if completions.len() == 1 {
                                     // Single match - auto-complete
                                     self.clear_input(&input, cursor_pos);
                                     input = completions[0].0.clone();
-                                    cursor_pos = input.len();
+                                    cursor_pos = input.len() - tail_len;
                                     print!("{}", input);
+                                    // Step back over the kept tail by its on-screen width
+                                    for _ in 0..display_width(&input[cursor_pos..]) {
+                                        print!("\x08");
+                                    }
                                     stdout.flush().ok();
                                 } else if !completions.is_empty() {
                                     // Multiple matches - show completion menu
@@ -464,41 +704,40 @@ impl<B: LlmBackend> Repl<B> {
                                     self.show_completion_menu(&completions);
                                     self.print_prompt();
                                     print!("{}", input);
+                                    for _ in 0..display_width(&input[cursor_pos..]) {
+                                        print!("\x08");
+                                    }
                                     stdout.flush().ok();
                                     // Re-enable raw mode
                                     crossterm::terminal::enable_raw_mode().ok();
                                 }
                             }
                             // Backspace
-                            (KeyCode::Backspace, _) => {
-                                if cursor_pos > 0 {
-                                    input.remove(cursor_pos - 1);
-                                    cursor_pos -= 1;
-                                    // Move cursor back, reprint rest of line, clear trailing char
+                            (KeyCode::Backspace, _) if cursor_pos > 0 => {
+                                input.remove(cursor_pos - 1);
+                                cursor_pos -= 1;
+                                // Move cursor back, reprint rest of line, clear trailing char
+                                print!("\x08");
+                                let tail = &input[cursor_pos..];
+                                print!("{} ", tail);
+                                // Move cursor back to position
+                                let move_back = tail.len() + 1;
+                                for _ in 0..move_back {
                                     print!("\x08");
-                                    let tail = &input[cursor_pos..];
-                                    print!("{} ", tail);
-                                    // Move cursor back to position
-                                    let move_back = tail.len() + 1;
-                                    for _ in 0..move_back {
-                                        print!("\x08");
-                                    }
-                                    stdout.flush().ok();
                                 }
+                                stdout.flush().ok();
                             }
                             // Delete key
-                            (KeyCode::Delete, _) => {
-                                if cursor_pos < input.len() {
-                                    input.remove(cursor_pos);
-                                    // Reprint rest of line, clear trailing char
-                                    let tail = &input[cursor_pos..];
-                                    print!("{} ", tail);
-                                    let move_back = tail.len() + 1;
-                                    for _ in 0..move_back {
-                                        print!("\x08");
-                                    }
-                                    stdout.flush().ok();
+                            (KeyCode::Delete, _) if cursor_pos < input.len() => {
+                                input.remove(cursor_pos);
+                                // Reprint rest of line, clear trailing char
+                                let tail = &input[cursor_pos..];
+                                print!("{} ", tail);
+                                let move_back = tail.len() + 1;
+                                for _ in 0..move_back {
+                                    print!("\x08");
                                 }
+                                stdout.flush().ok();
                             }
                             // Regular character
                             (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
@@ -542,6 +781,49 @@ impl<B: LlmBackend> Repl<B> {
         }
     }
 
+    /// Run an incremental reverse-search over history on the current line.
+    /// Returns the selected entry, or None if cancelled or nothing matched.
+    fn reverse_search(&self, stdout: &mut impl io::Write) -> Option<String> {
+        let mut query = String::new();
+        let mut nth = 0;
+
+        loop {
+            let matches = search_history(&self.history, &query);
+            let current = matches.get(nth).map(|(_, h)| h.as_str());
+            print!(
+                "\r\x1b[2K{}`{}': {}",
+                style("(reverse-i-search)").dim(),
+                query,
+                current.unwrap_or("").replace('\n', "↵")
+            );
+            stdout.flush().ok();
+
+            let Ok(Event::Key(key_event)) = event::read() else {
+                continue;
+            };
+            match (key_event.code, key_event.modifiers) {
+                (KeyCode::Char('r'), KeyModifiers::CONTROL) if nth + 1 < matches.len() => {
+                    nth += 1;
+                }
+                (KeyCode::Esc, _)
+                | (KeyCode::Char('c'), KeyModifiers::CONTROL)
+                | (KeyCode::Char('g'), KeyModifiers::CONTROL) => return None,
+                (KeyCode::Enter, _) | (KeyCode::Tab, _) | (KeyCode::Right, _) => {
+                    return current.map(|s| s.to_string());
+                }
+                (KeyCode::Backspace, _) => {
+                    query.pop();
+                    nth = 0;
+                }
+                (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    query.push(c);
+                    nth = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Clear the input text from the terminal
     fn clear_input(&self, input: &str, cursor_pos: usize) {
         // Move cursor to start of input
@@ -648,6 +930,27 @@ impl<B: LlmBackend> Repl<B> {
             .collect()
     }
 
+    /// Completions for the `@` reference under the cursor (see `CompletionEngine::complete_at_cursor`)
+    fn get_at_completions(
+        &self,
+        input: &str,
+        cursor: usize,
+    ) -> Vec<(String, Option<String>, CompletionKind)> {
+        let ctx = CompletionContext {
+            context_files: self.context.list_files(),
+            cwd: self.context.initial_cwd(),
+            models: self.cached_models.clone(),
+            history: &self.history,
+        };
+
+        self.completion_engine
+            .complete_at_cursor(input, cursor, &ctx)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| (c.text, c.description, c.kind))
+            .collect()
+    }
+
     /// Display a formatted completion menu (non-interactive, for display only)
     fn show_completion_menu(&self, completions: &[(String, Option<String>, CompletionKind)]) {
         let max_items = self.config.ui.max_completion_items;
@@ -742,7 +1045,6 @@ impl<B: LlmBackend> Repl<B> {
 
     /// Show interactive completion menu with arrow key navigation
     /// Returns the selected completion text or None if cancelled
-    #[allow(dead_code)]
     fn show_interactive_completion_menu(
         &self,
         completions: &[(String, Option<String>, CompletionKind)],
@@ -751,26 +1053,35 @@ impl<B: LlmBackend> Repl<B> {
             return None;
         }
 
-        let max_items = self.config.ui.max_completion_items;
-        let visible_items: Vec<_> = completions.iter().take(max_items).collect();
+        // Long lists (such as every pulled model) scroll within a window of
+        // `max_completion_items` rows instead of cutting off the rest
+        let window = self
+            .config
+            .ui
+            .max_completion_items
+            .clamp(1, completions.len());
         let mut selected: usize = 0;
+        let mut offset: usize = 0;
         let mut stdout = io::stdout();
 
-        // Calculate dimensions
-        let max_text_len = visible_items
+        // Calculate dimensions over every item so the width stays put while scrolling
+        let max_text_len = completions
             .iter()
             .map(|(t, _, _)| t.len())
             .max()
             .unwrap_or(10);
         let width = (max_text_len + 35).min(60);
-        let menu_height = visible_items.len() + 3; // items + borders + hint
+        let hidden = completions.len() - window;
+        let menu_height = window + 2 + usize::from(hidden > 0); // items + borders
+        let visible_items =
+            |offset: usize| -> Vec<_> { completions[offset..offset + window].iter().collect() };
 
         // Print initial menu
         println!(
             "{}",
             style(format!("╭─ Completions {}╮", "─".repeat(width - 15))).dim()
         );
-        for (idx, (text, desc, kind)) in visible_items.iter().enumerate() {
+        for (idx, (text, desc, kind)) in visible_items(offset).iter().enumerate() {
             self.print_completion_item(
                 idx,
                 Some(selected),
@@ -780,11 +1091,59 @@ impl<B: LlmBackend> Repl<B> {
                 max_text_len,
             );
         }
-        if completions.len() > max_items {
+        Self::print_menu_footer(hidden, width);
+        stdout.flush().ok();
+
+        // Interactive loop. Raw mode is only held while waiting for a key so the
+        // menu itself can be drawn with ordinary line endings.
+        loop {
+            crossterm::terminal::enable_raw_mode().ok();
+            let event = event::read();
+            crossterm::terminal::disable_raw_mode().ok();
+
+            let Ok(event) = event else {
+                self.clear_menu_lines(menu_height);
+                return None;
+            };
+            if let Event::Key(key_event) = event {
+                let moved = match (key_event.code, key_event.modifiers) {
+                    (KeyCode::Up, _) if selected > 0 => Some(selected - 1),
+                    (KeyCode::Down, _) if selected + 1 < completions.len() => Some(selected + 1),
+                    (KeyCode::Enter, _) | (KeyCode::Tab, _) => {
+                        // Clear menu and return selection (Tab also selects)
+                        self.clear_menu_lines(menu_height);
+                        return Some(completions[selected].0.clone());
+                    }
+                    (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                        // Clear menu and cancel
+                        self.clear_menu_lines(menu_height);
+                        return None;
+                    }
+                    _ => None,
+                };
+                if let Some(next) = moved {
+                    selected = next;
+                    offset = menu_scroll_offset(selected, offset, window);
+                    self.redraw_menu(
+                        &visible_items(offset),
+                        selected - offset,
+                        max_text_len,
+                        width,
+                        menu_height,
+                        hidden,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Print the "and N more" line (if anything was cut off) and the key hint border
+    fn print_menu_footer(hidden: usize, width: usize) {
+        if hidden > 0 {
             println!(
                 "│ {} {}│",
                 style("...").dim(),
-                style(format!("and {} more", completions.len() - max_items)).dim()
+                style(format!("and {} more", hidden)).dim()
             );
         }
         println!(
@@ -795,57 +1154,6 @@ impl<B: LlmBackend> Repl<B> {
             ))
             .dim()
         );
-        stdout.flush().ok();
-
-        // Interactive loop
-        loop {
-            if event::poll(Duration::from_millis(100)).unwrap_or(false) {
-                if let Ok(Event::Key(key_event)) = event::read() {
-                    match key_event.code {
-                        KeyCode::Up => {
-                            if selected > 0 {
-                                selected -= 1;
-                                self.redraw_menu(
-                                    &visible_items,
-                                    selected,
-                                    max_text_len,
-                                    width,
-                                    menu_height,
-                                );
-                            }
-                        }
-                        KeyCode::Down => {
-                            if selected < visible_items.len() - 1 {
-                                selected += 1;
-                                self.redraw_menu(
-                                    &visible_items,
-                                    selected,
-                                    max_text_len,
-                                    width,
-                                    menu_height,
-                                );
-                            }
-                        }
-                        KeyCode::Enter => {
-                            // Clear menu and return selection
-                            self.clear_menu_lines(menu_height);
-                            return Some(visible_items[selected].0.clone());
-                        }
-                        KeyCode::Esc => {
-                            // Clear menu and cancel
-                            self.clear_menu_lines(menu_height);
-                            return None;
-                        }
-                        KeyCode::Tab => {
-                            // Tab also selects
-                            self.clear_menu_lines(menu_height);
-                            return Some(visible_items[selected].0.clone());
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
     }
 
     /// Redraw the completion menu (moves cursor up, redraws, moves back)
@@ -856,6 +1164,7 @@ impl<B: LlmBackend> Repl<B> {
         max_text_len: usize,
         width: usize,
         menu_height: usize,
+        hidden: usize,
     ) {
         let mut stdout = io::stdout();
 
@@ -877,14 +1186,7 @@ impl<B: LlmBackend> Repl<B> {
                 max_text_len,
             );
         }
-        println!(
-            "{}",
-            style(format!(
-                "╰─ ↑↓ navigate, Enter select, Esc cancel {}╯",
-                "─".repeat(width.saturating_sub(40))
-            ))
-            .dim()
-        );
+        Self::print_menu_footer(hidden, width);
 
         stdout.flush().ok();
     }
@@ -902,6 +1204,27 @@ impl<B: LlmBackend> Repl<B> {
         let parts: Vec<&str> = command.split_whitespace().collect();
         let cmd = parts.first().unwrap_or(&"").trim_start_matches('/');
 
+        // Project shortcuts from `[commands]` expand to a template plus preset arguments;
+        // built-in commands always win over a shortcut of the same name
+        if let Some((template, mut args)) = self
+            .config
+            .resolve_command(cmd)
+            .filter(|_| !is_builtin_command(cmd))
+        {
+            if !self.templates.is_template_command(&template) {
+                println!(
+                    "{} /{} points to unknown template: {}",
+                    style("Error:").red(),
+                    cmd,
+                    template
+                );
+                return Ok(true);
+            }
+            args.extend(parts[1..].iter().map(|s| s.to_string()));
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            return self.handle_template_command(&template, &args).await;
+        }
+
         // Check if it's a template command first
         if self.templates.is_template_command(cmd) {
             return self.handle_template_command(cmd, &parts[1..]).await;
@@ -917,30 +1240,121 @@ impl<B: LlmBackend> Repl<B> {
                 Ok(true)
             }
             "exit" | "quit" | "q" => Ok(false),
+            "compact" => {
+                let total = self.context.messages().len();
+                // By default keep the last two exchanges verbatim
+                let count = match parts.get(1) {
+                    Some(n) => match n.parse::<usize>() {
+                        Ok(n) => n,
+                        Err(_) => {
+                            println!("{} /compact [n]", style("Usage:").dim());
+                            return Ok(true);
+                        }
+                    },
+                    None => total.saturating_sub(4),
+                };
+                if count.min(total) < 2 {
+                    println!("{}", style("Not enough messages to compact.").dim());
+                    return Ok(true);
+                }
+
+                let before = self.context.summary();
+                match self.compact_history(count).await {
+                    Ok(replaced) => {
+                        let after = self.context.summary();
+                        println!(
+                            "{} Compacted {} message(s) into a summary",
+                            style("✓").green(),
+                            replaced
+                        );
+                        println!(
+                            "  {} {} → {}, ~{} → ~{} of {} tokens",
+                            style("Messages:").dim(),
+                            before.messages_count,
+                            after.messages_count,
+                            before.tokens_used,
+                            after.tokens_used,
+                            after.token_budget
+                        );
+                    }
+                    Err(e) => println!("{} Compaction failed: {}", style("Error:").red(), e),
+                }
+                Ok(true)
+            }
+            "clear" if parts.len() > 1 => {
+                let keep = parts[1]
+                    .strip_prefix("keep=")
+                    .and_then(|n| n.parse::<usize>().ok());
+                let Some(keep) = keep else {
+                    println!("{} /clear [keep=N]", style("Usage:").dim());
+                    return Ok(true);
+                };
+                let dropped = self.context.keep_last_messages(keep);
+                // Marks point into the remaining messages; ones before the cut move to the start
+                shift_marks(&mut self.marks, 0, dropped, 0);
+                println!(
+                    "{}",
+                    style(format!(
+                        "Dropped {} message(s), kept the last {}.",
+                        dropped,
+                        self.context.messages().len()
+                    ))
+                    .dim()
+                );
+                Ok(true)
+            }
             "clear" => {
                 Term::stdout().clear_screen().ok();
                 self.context.clear_messages();
+                self.marks.clear();
                 println!("{}", style("Conversation cleared.").dim());
                 println!();
                 Ok(true)
             }
+            "model" if parts.get(1) == Some(&"pull") => {
+                match parts.get(2) {
+                    Some(name) => {
+                        if let Err(e) = self.pull_and_switch_model(name).await {
+                            println!("{} {}", style("Error:").red(), e);
+                        }
+                    }
+                    None => println!("{} /model pull <name>", style("Usage:").dim()),
+                }
+                Ok(true)
+            }
             "model" => {
-                if parts.len() > 1 {
-                    self.model = parts[1].to_string();
-                    println!(
-                        "{} {}",
-                        style("Switched to model:").dim(),
-                        style(&self.model).yellow()
-                    );
-                } else {
-                    println!(
-                        "{} {}",
-                        style("Current model:").dim(),
-                        style(&self.model).yellow()
-                    );
+                match parts.get(1) {
+                    None | Some(&"list") => self.pick_model().await,
+                    Some(name) => self.switch_model(name).await,
+                }
+                Ok(true)
+            }
+            "context" if parts.get(1) == Some(&"export") => {
+                let content = self.context.build_system_content();
+                match parts.get(2) {
+                    Some(path) => match std::fs::write(path, &content) {
+                        Ok(()) => println!(
+                            "{} Wrote system content (~{} tokens) to {}",
+                            style("✓").green(),
+                            crate::context::estimate_tokens(&content),
+                            style(path).cyan()
+                        ),
+                        Err(e) => {
+                            println!("{} Failed to write {}: {}", style("Error:").red(), path, e)
+                        }
+                    },
+                    None => println!("{}", content),
                 }
                 Ok(true)
             }
+            "temp" => {
+                self.handle_sampling_override("temp", parts.get(1).copied(), (0.0, 2.0));
+                Ok(true)
+            }
+            "top_p" => {
+                self.handle_sampling_override("top_p", parts.get(1).copied(), (0.0, 1.0));
+                Ok(true)
+            }
             "context" => {
                 let summary = self.context.summary();
                 println!("{}", style("Context:").cyan().bold());
@@ -966,9 +1380,82 @@ impl<B: LlmBackend> Repl<B> {
                     style("Rules:").dim(),
                     if summary.has_rules { "yes" } else { "no" }
                 );
-                Ok(true)
+                let model_config = self.config.get_model_config(&self.model);
+                for (label, value, overridden) in [
+                    (
+                        "Temperature:",
+                        self.temperature_override
+                            .unwrap_or(model_config.temperature),
+                        self.temperature_override.is_some(),
+                    ),
+                    (
+                        "Top-p:",
+                        self.top_p_override.unwrap_or(model_config.top_p),
+                        self.top_p_override.is_some(),
+                    ),
+                ] {
+                    println!(
+                        "  {} {}{}",
+                        style(label).dim(),
+                        value,
+                        if overridden { " (override)" } else { "" }
+                    );
+                }
+                if self.json_mode {
+                    println!("  {} on", style("JSON mode:").dim());
+                }
+                Ok(true)
+            }
+            "cost" => {
+                let usage = SessionUsage::from_messages(self.context.messages());
+                let model_config = self.config.get_model_config(&self.model);
+                println!("{}", style("Session usage (estimated):").cyan().bold());
+                println!(
+                    "  {} {} tokens ({} message(s))",
+                    style("Input: ").dim(),
+                    usage.input_tokens,
+                    usage.input_messages
+                );
+                println!(
+                    "  {} {} tokens ({} message(s))",
+                    style("Output:").dim(),
+                    usage.output_tokens,
+                    usage.output_messages
+                );
+                println!("  {} {} tokens", style("Total: ").dim(), usage.total());
+                println!(
+                    "  {} {} tokens/message",
+                    style("Average:").dim(),
+                    usage.average()
+                );
+                if let Some(cost) = usage.cost(
+                    model_config.prompt_cost_per_1k,
+                    model_config.completion_cost_per_1k,
+                ) {
+                    println!("  {} ${:.4}", style("Cost:  ").dim(), cost);
+                }
+                Ok(true)
             }
             "tokens" => {
+                if parts.get(1) == Some(&"--model") {
+                    match self.client.llm_context_length(&self.model).await {
+                        Ok(Some(window)) => println!(
+                            "{} {} tokens ({} uses {}% of it)",
+                            style("Model window:").dim(),
+                            window,
+                            self.context.token_count(),
+                            (self.context.token_count() * 100)
+                                .checked_div(window)
+                                .unwrap_or(0)
+                        ),
+                        Ok(None) => println!(
+                            "{}",
+                            style("The backend does not report a context window for this model.")
+                                .dim()
+                        ),
+                        Err(e) => println!("{} {}", style("Error:").red(), e),
+                    }
+                }
                 let summary = self.context.summary();
                 println!(
                     "{} {} / {} tokens",
@@ -1005,18 +1492,60 @@ impl<B: LlmBackend> Repl<B> {
                 }
                 Ok(true)
             }
+            "tree" => {
+                let files: Vec<(PathBuf, usize)> = self
+                    .context
+                    .list_files()
+                    .into_iter()
+                    .map(|path| {
+                        let tokens = self
+                            .context
+                            .get_file_content(path)
+                            .map(|c| c.len() / 4)
+                            .unwrap_or(0);
+                        (path.clone(), tokens)
+                    })
+                    .collect();
+                if files.is_empty() {
+                    println!("{}", style("No files in context.").dim());
+                } else {
+                    println!("{}", style("Files in context:").cyan().bold());
+                    for line in crate::ui::file_tree(&files, self.box_style.chars()) {
+                        let prefix = style(&line.prefix).dim();
+                        match line.tokens {
+                            Some(tokens) => println!(
+                                "  {}{} {}",
+                                prefix,
+                                style(&line.name).green(),
+                                style(format!("(~{} tokens)", tokens)).dim()
+                            ),
+                            None => println!("  {}{}", prefix, style(&line.name).cyan()),
+                        }
+                    }
+                }
+                Ok(true)
+            }
             "add" => {
-                if parts.len() < 2 {
+                let respect_gitignore = !parts[1..].contains(&"--no-gitignore");
+                let paths: Vec<&str> = parts[1..]
+                    .iter()
+                    .copied()
+                    .filter(|p| *p != "--no-gitignore")
+                    .collect();
+                if paths.is_empty() {
                     println!(
-                        "{} /add <file|directory> [file2 ...]",
+                        "{} /add [--no-gitignore] <file|directory> [file2 ...]",
                         style("Usage:").dim()
                     );
                     return Ok(true);
                 }
                 let mut any_change = false;
-                for path in &parts[1..] {
+                for path in &paths {
                     if self.context.is_directory(path) {
-                        match self.context.add_directory(path) {
+                        match self
+                            .context
+                            .add_directory_with_options(path, respect_gitignore)
+                        {
                             Ok((added, skipped)) => {
                                 any_change = true;
                                 println!(
@@ -1032,7 +1561,7 @@ impl<B: LlmBackend> Repl<B> {
                                     }
                                 } else if !skipped.is_empty() {
                                     println!(
-                                        "{} Skipped {} file(s) (binary/unreadable)",
+                                        "{} Skipped {} file(s) (binary/unreadable/too large/over budget)",
                                         style("⚠").yellow(),
                                         skipped.len()
                                     );
@@ -1063,6 +1592,30 @@ impl<B: LlmBackend> Repl<B> {
                 }
                 Ok(true)
             }
+            "add-url" => {
+                if parts.len() < 2 {
+                    println!("{} /add-url <url> [url2 ...]", style("Usage:").dim());
+                    return Ok(true);
+                }
+                let mut any_change = false;
+                for url in &parts[1..] {
+                    match self.context.add_url(url).await {
+                        Ok(stored) => {
+                            any_change = true;
+                            println!(
+                                "{} Added {} to context",
+                                style("✓").green(),
+                                style(stored.display()).cyan()
+                            );
+                        }
+                        Err(e) => println!("{} {}", style("Error:").red(), e),
+                    }
+                }
+                if any_change {
+                    self.update_rules_for_context();
+                }
+                Ok(true)
+            }
             "remove" | "rm" => {
                 if parts.len() < 2 {
                     println!("{} /remove <file> [file2 ...]", style("Usage:").dim());
@@ -1093,6 +1646,12 @@ impl<B: LlmBackend> Repl<B> {
             "fileops" => {
                 if parts.len() > 1 {
                     match parts[1] {
+                        "on" | "enable" if self.readonly => {
+                            println!(
+                                "{}",
+                                style("Read-only mode: file operations stay disabled.").yellow()
+                            );
+                        }
                         "on" | "enable" => {
                             self.file_ops_enabled = true;
                             println!("{}", style("File operations enabled").green());
@@ -1118,6 +1677,84 @@ impl<B: LlmBackend> Repl<B> {
                 }
                 Ok(true)
             }
+            "resend-with" => {
+                match parts.get(1) {
+                    Some(model) => self.resend_with(model).await?,
+                    None => println!("{} /resend-with <model>", style("Usage:").dim()),
+                }
+                Ok(true)
+            }
+            "theme" => {
+                let current = ThemeName::from_str(&self.config.ui.theme);
+                let target = match parts.get(1).copied() {
+                    None => {
+                        let names: Vec<&str> = ThemeName::all().iter().map(|t| t.name()).collect();
+                        println!("{} {}", style("Theme:").dim(), style(current.name()).cyan());
+                        println!("{} {}", style("Available:").dim(), names.join(", "));
+                        return Ok(true);
+                    }
+                    Some("save") => {
+                        match Config::load(None).and_then(|mut config| {
+                            config.ui.theme = current.name().to_string();
+                            config.save()
+                        }) {
+                            Ok(()) => println!(
+                                "{} Saved ui.theme = {}",
+                                style("✓").green(),
+                                current.name()
+                            ),
+                            Err(e) => println!("{} {}", style("Error:").red(), e),
+                        }
+                        return Ok(true);
+                    }
+                    Some("next") => current.next(&self.config.ui.custom_theme),
+                    Some(name) => match ThemeName::parse(name) {
+                        Some(theme) => theme,
+                        None => {
+                            println!(
+                                "{} Unknown theme '{}'. Run /theme to list themes.",
+                                style("Error:").red(),
+                                name
+                            );
+                            return Ok(true);
+                        }
+                    },
+                };
+                self.set_theme(target);
+                if self.config.ui.show_status_bar {
+                    self.print_status_bar();
+                }
+                println!(
+                    "{} Theme: {} {}",
+                    style("✓").green(),
+                    self.theme.primary.apply_to(target.name()),
+                    style("(/theme save to keep it)").dim()
+                );
+                Ok(true)
+            }
+            "json" => {
+                match parts.get(1).copied() {
+                    Some("on") => {
+                        self.json_mode = true;
+                        println!(
+                            "{}",
+                            style("JSON mode ON — responses are constrained to valid JSON.")
+                                .green()
+                        );
+                    }
+                    Some("off") => {
+                        self.json_mode = false;
+                        println!("{}", style("JSON mode OFF.").dim());
+                    }
+                    Some(_) => println!("{} /json [on|off]", style("Usage:").dim()),
+                    None => println!(
+                        "{} {}",
+                        style("JSON mode:").dim(),
+                        if self.json_mode { "on" } else { "off" }
+                    ),
+                }
+                Ok(true)
+            }
             "watch" => {
                 let enabled = !self.context.watch_mode();
                 self.context.set_watch_mode(enabled);
@@ -1132,6 +1769,10 @@ impl<B: LlmBackend> Repl<B> {
                 Ok(true)
             }
             "templates" => {
+                if parts.get(1) == Some(&"reload") {
+                    self.reload_templates();
+                    return Ok(true);
+                }
                 let templates = self.templates.list();
                 if templates.is_empty() {
                     println!("{}", style("No templates loaded.").dim());
@@ -1153,7 +1794,10 @@ impl<B: LlmBackend> Repl<B> {
                     println!("{}", style("No rules loaded.").dim());
                     println!(
                         "{}",
-                        style("Add rules to .slab/rules/ (.yaml, .md, or .txt)").dim()
+                        style(
+                            "Add rules to .slab/rules/ or ~/.config/slab/rules/ (.yaml, .md, or .txt)"
+                        )
+                        .dim()
                     );
                 } else {
                     println!("{}", style("Loaded rules:").cyan().bold());
@@ -1200,56 +1844,147 @@ impl<B: LlmBackend> Repl<B> {
                     );
                     return Ok(true);
                 }
-                #[cfg(unix)]
-                let output = Command::new("sh").arg("-c").arg(cmd_line).output();
-                #[cfg(windows)]
-                let output = Command::new("cmd").args(["/C", cmd_line]).output();
-                match output {
-                    Ok(o) => {
-                        if !o.stdout.is_empty() {
-                            print!("{}", String::from_utf8_lossy(&o.stdout));
-                        }
-                        if !o.stderr.is_empty() {
-                            eprint!("{}", String::from_utf8_lossy(&o.stderr));
-                        }
-                        io::stdout().flush().ok();
-                        if !o.status.success() {
-                            if let Some(code) = o.status.code() {
-                                println!("{} {}", style("Exit code:").dim(), code);
-                            }
-                        }
-                        // Add command and output to context so the model has it
-                        let stdout = String::from_utf8_lossy(&o.stdout);
-                        let stderr = String::from_utf8_lossy(&o.stderr);
-                        let code = o
-                            .status
-                            .code()
-                            .map(|c| c.to_string())
-                            .unwrap_or_else(|| "—".into());
-                        let mut ctx_msg = format!("[Ran shell command]\n$ {cmd_line}\n\n");
-                        if !stdout.is_empty() {
-                            ctx_msg.push_str("stdout:\n");
-                            ctx_msg.push_str(&stdout);
-                            if !stdout.ends_with('\n') {
-                                ctx_msg.push('\n');
-                            }
-                        }
-                        if !stderr.is_empty() {
-                            ctx_msg.push_str("stderr:\n");
-                            ctx_msg.push_str(&stderr);
-                            if !stderr.ends_with('\n') {
-                                ctx_msg.push('\n');
-                            }
-                        }
-                        ctx_msg.push_str(&format!("exit code: {code}\n"));
-                        self.context.add_message(Message::user(&ctx_msg));
+                if self.readonly {
+                    println!(
+                        "{}",
+                        style("Read-only mode: commands are disabled.").yellow()
+                    );
+                    return Ok(true);
+                }
+                self.run_exec_command(cmd_line);
+                Ok(true)
+            }
+            "replay" => {
+                if self.readonly {
+                    println!(
+                        "{}",
+                        style("Read-only mode: commands are disabled.").yellow()
+                    );
+                    return Ok(true);
+                }
+                let commands = self.replay_commands();
+                if commands.is_empty() {
+                    println!(
+                        "{}",
+                        style("No exec blocks in the last response to replay.").dim()
+                    );
+                    return Ok(true);
+                }
+                println!(
+                    "{} Replaying {} command(s) from the last response",
+                    style("→").cyan(),
+                    commands.len()
+                );
+                for cmd_line in &commands {
+                    if is_risky_command(cmd_line) && !confirm_risky_command(cmd_line)? {
+                        continue;
                     }
-                    Err(e) => {
-                        println!("{} {}", style("Exec failed:").red(), e);
-                        let ctx_msg =
-                            format!("[Shell command failed]\n$ {cmd_line}\n\nerror: {e}\n");
-                        self.context.add_message(Message::user(&ctx_msg));
+                    println!("{} {}", style("$").dim(), style(cmd_line).cyan());
+                    self.run_exec_command(cmd_line);
+                }
+                println!();
+                Ok(true)
+            }
+            "edit" => {
+                let Some(last) = self.last_user_input() else {
+                    println!("{}", style("No message to edit yet.").dim());
+                    return Ok(true);
+                };
+                let replacement = parts[1..].join(" ");
+                if replacement.is_empty() {
+                    // Pre-fill the editor; the submitted text replaces the message
+                    println!(
+                        "{}",
+                        style("Editing the last message — Enter re-sends it, Ctrl+C keeps the original.")
+                            .dim()
+                    );
+                    self.prefill = Some(last);
+                    self.replace_last_user = true;
+                } else {
+                    self.context.truncate_from_last_user();
+                    self.send_message(&replacement).await?;
+                }
+                Ok(true)
+            }
+            "retry" => {
+                let temperature = match parts.get(1).map(|arg| arg.strip_prefix("temp=")) {
+                    None => None,
+                    Some(Some(value)) => match value.parse::<f32>() {
+                        Ok(t) if (0.0..=2.0).contains(&t) => Some(t),
+                        _ => {
+                            println!(
+                                "{} temperature must be a number between 0.0 and 2.0",
+                                style("Error:").red()
+                            );
+                            return Ok(true);
+                        }
+                    },
+                    Some(None) => {
+                        println!("{} /retry [temp=<value>]", style("Usage:").dim());
+                        return Ok(true);
                     }
+                };
+                // Drop the replies and any command output after them, but keep the
+                // (already expanded) typed message as sent
+                let Some(user) = self.context.truncate_from_last_user() else {
+                    println!("{}", style("No message to retry yet.").dim());
+                    return Ok(true);
+                };
+                self.context.add_message(user);
+
+                let mut request = self.build_chat_request();
+                if let (Some(t), Some(options)) = (temperature, request.options.as_mut()) {
+                    options.temperature = Some(t);
+                }
+                self.respond(request).await?;
+                Ok(true)
+            }
+            "undo" => {
+                if self.last_applied.is_empty() {
+                    println!("{}", style("Nothing to undo.").dim());
+                    return Ok(true);
+                }
+                self.undo_file_operations()?;
+                Ok(true)
+            }
+            "explain-diff" => {
+                let Some((count, prompt)) = self.explain_diff_prompt() else {
+                    println!(
+                        "{}",
+                        style("No file operations in the last response to explain.").dim()
+                    );
+                    return Ok(true);
+                };
+                println!(
+                    "{} Asking the model to explain {} proposed change(s)",
+                    style("→").cyan(),
+                    count
+                );
+                // Not a typed turn: /retry and /edit go back to the prompt that
+                // produced the changes rather than resending this one through respond()
+                self.context.add_message(Message::user(&prompt));
+                self.fit_context_budget()?;
+                let request = self.build_chat_request();
+
+                // Keep history short: the diffs are already in the previous response
+                self.context.pop_message();
+                self.context.add_message(Message::user(format!(
+                    "[Asked to explain {} proposed change(s)]",
+                    count
+                )));
+
+                // The explanation quotes the diffs, so it is never parsed for file
+                // operations or exec blocks
+                let response = if self.streaming {
+                    self.stream_response(request).await?
+                } else {
+                    self.wait_response(request).await?
+                };
+                if let Some(reply) = Some(response)
+                    .filter(|r| !r.is_empty())
+                    .or_else(|| self.take_partial_response())
+                {
+                    self.context.add_message(Message::assistant(reply));
                 }
                 Ok(true)
             }
@@ -1299,11 +2034,46 @@ impl<B: LlmBackend> Repl<B> {
                 }
                 Ok(true)
             }
+            "history" => {
+                if parts.get(1) == Some(&"search") {
+                    let term = parts[2..].join(" ");
+                    if term.is_empty() {
+                        println!("{} /history search <term>", style("Usage:").dim());
+                        return Ok(true);
+                    }
+                    let matches = search_history(&self.history, &term);
+                    if matches.is_empty() {
+                        println!(
+                            "{}",
+                            style(format!("No history entries match '{}'.", term)).dim()
+                        );
+                    } else {
+                        for (idx, entry) in matches {
+                            println!(
+                                "  {} {}",
+                                style(format!("{:>4}", idx + 1)).dim(),
+                                entry.replace('\n', "↵")
+                            );
+                        }
+                    }
+                } else if self.history.is_empty() {
+                    println!("{}", style("No history yet.").dim());
+                } else {
+                    let start = self.history.len().saturating_sub(20);
+                    for (idx, entry) in self.history.iter().enumerate().skip(start) {
+                        println!(
+                            "  {} {}",
+                            style(format!("{:>4}", idx + 1)).dim(),
+                            entry.replace('\n', "↵")
+                        );
+                    }
+                }
+                Ok(true)
+            }
             "export" => {
                 use chrono::Local;
-                use std::fmt::Write as FmtWrite;
 
-                let messages = self.context.messages();
+                let mut messages = self.context.messages();
                 if messages.is_empty() {
                     println!(
                         "{}",
@@ -1312,43 +2082,88 @@ impl<B: LlmBackend> Repl<B> {
                     return Ok(true);
                 }
 
+                let mut append = false;
+                let mut format: Option<ExportFormat> = None;
+                let mut name_parts: Vec<&str> = Vec::new();
+                let mut iter = parts[1..].iter();
+                while let Some(arg) = iter.next() {
+                    let format_arg = match *arg {
+                        "--append" | "-a" => {
+                            append = true;
+                            continue;
+                        }
+                        "--from" => {
+                            let Some(label) = iter.next() else {
+                                println!("{} --from needs a mark label", style("Error:").red());
+                                return Ok(true);
+                            };
+                            match messages_after_mark(messages, &self.marks, label) {
+                                Some(slice) => messages = slice,
+                                None => {
+                                    println!(
+                                        "{} No mark named '{}' (see /marks)",
+                                        style("Error:").red(),
+                                        label
+                                    );
+                                    return Ok(true);
+                                }
+                            }
+                            continue;
+                        }
+                        "--format" => iter.next().copied(),
+                        _ => arg.strip_prefix("--format="),
+                    };
+                    match format_arg {
+                        Some(value) => match ExportFormat::parse(value) {
+                            Some(f) => format = Some(f),
+                            None => {
+                                println!(
+                                    "{} Unknown export format '{}' (expected md or txt)",
+                                    style("Error:").red(),
+                                    value
+                                );
+                                return Ok(true);
+                            }
+                        },
+                        None if arg.starts_with("--format") => {
+                            println!("{} --format needs md or txt", style("Error:").red());
+                            return Ok(true);
+                        }
+                        None => name_parts.push(arg),
+                    }
+                }
+
+                if messages.is_empty() {
+                    println!("{}", style("Nothing to export after that mark.").dim());
+                    return Ok(true);
+                }
+
+                // Without --format, a .md filename selects markdown
+                let format = format.unwrap_or(if name_parts.join(" ").ends_with(".md") {
+                    ExportFormat::Markdown
+                } else {
+                    ExportFormat::Text
+                });
+
                 // Determine output path
-                let filename = if parts.len() > 1 {
-                    parts[1..].join(" ")
+                let filename = if name_parts.is_empty() {
+                    format!(
+                        "slab-export-{}.{}",
+                        Local::now().format("%Y-%m-%d-%H%M%S"),
+                        format.extension()
+                    )
                 } else {
-                    format!("slab-export-{}.txt", Local::now().format("%Y-%m-%d-%H%M%S"))
+                    name_parts.join(" ")
                 };
                 let output_path = std::path::Path::new(&filename);
 
-                // Build plain-text export (no ANSI codes — safe for tmux/pipes)
-                let mut out = String::new();
-                let _ = writeln!(out, "=== Slab Chat Export ===");
-                let _ = writeln!(
-                    out,
-                    "Date:     {}",
-                    Local::now().format("%Y-%m-%d %H:%M:%S")
-                );
-                let _ = writeln!(out, "Model:    {}", self.model);
-                let _ = writeln!(out, "Messages: {}", messages.len());
-                let _ = writeln!(out, "{}", "=".repeat(40));
-
-                for (i, msg) in messages.iter().enumerate() {
-                    let _ = writeln!(out);
-                    let role = msg.role.to_uppercase();
-                    let _ = writeln!(out, "[{}] {}", i + 1, role);
-                    let _ = writeln!(out, "{}", "-".repeat(40));
-                    let _ = writeln!(out, "{}", msg.content);
-                }
-
-                let _ = writeln!(out);
-                let _ = writeln!(out, "{}", "=".repeat(40));
-                let _ = writeln!(out, "End of export");
-
-                match std::fs::write(output_path, &out) {
+                let out = format_export(messages, &self.model, format);
+                match write_export(output_path, &out, append, format) {
                     Ok(()) => {
                         println!(
-                            "{} Exported {} message(s) to {}",
+                            "{} {} {} message(s) to {}",
                             style("✓").green(),
+                            if append { "Appended" } else { "Exported" },
                             messages.len(),
                             style(output_path.display()).cyan()
                         );
@@ -1359,6 +2174,80 @@ impl<B: LlmBackend> Repl<B> {
                 }
                 Ok(true)
             }
+            "load" => {
+                let Some(name) = parts.get(1) else {
+                    println!("{} /load <session>", style("Usage:").dim());
+                    return Ok(true);
+                };
+                let Some(dir) = self.sessions_dir.clone().or_else(Session::session_dir) else {
+                    println!(
+                        "{} Could not determine the session directory",
+                        style("Error:").red()
+                    );
+                    return Ok(true);
+                };
+                let session = match Session::load_in(&dir, name) {
+                    Ok(session) => session,
+                    Err(e) => {
+                        println!("{} {}", style("Error:").red(), e);
+                        return Ok(true);
+                    }
+                };
+
+                let current = self.context.messages().len();
+                if current > 0 && !confirm_replace_conversation(current, name)? {
+                    return Ok(true);
+                }
+                // The session's files replace the current ones rather than merging
+                self.context.clear_messages();
+                self.context.clear_files();
+                self.marks.clear();
+                self.load_session(session);
+                self.update_rules_for_context();
+                Ok(true)
+            }
+            "mark" => {
+                let Some(label) = parts.get(1) else {
+                    println!("{} /mark <label>", style("Usage:").dim());
+                    return Ok(true);
+                };
+                if parts.len() > 2 {
+                    println!(
+                        "{} Mark labels are a single word, e.g. /mark before-refactor",
+                        style("Error:").red()
+                    );
+                    return Ok(true);
+                }
+                let index = self.context.messages().len();
+                self.marks.retain(|m| m.label != *label);
+                self.marks.push(Mark {
+                    label: label.to_string(),
+                    index,
+                });
+                println!(
+                    "{} Marked '{}' after {} message(s)",
+                    style("✓").green(),
+                    style(label).cyan(),
+                    index
+                );
+                Ok(true)
+            }
+            "marks" => {
+                if self.marks.is_empty() {
+                    println!("{}", style("No marks set. Use /mark <label>.").dim());
+                    return Ok(true);
+                }
+                let messages = self.context.messages();
+                for mark in &self.marks {
+                    let since = messages.len().saturating_sub(mark.index);
+                    println!(
+                        "  {} {}",
+                        style(&mark.label).cyan(),
+                        style(format!("after {} message(s), {} since", mark.index, since)).dim()
+                    );
+                }
+                Ok(true)
+            }
             "pwd" => {
                 let cwd = std::env::current_dir().unwrap_or_else(|_| self.project_root.clone());
                 println!("{}", style(cwd.display()).cyan());
@@ -1381,6 +2270,14 @@ impl<B: LlmBackend> Repl<B> {
     ) -> Result<()> {
         use crate::templates::{PhaseFeedback, PhaseOutcome};
 
+        if self.readonly {
+            println!(
+                "{}",
+                style("Read-only mode: template phases are skipped.").yellow()
+            );
+            return Ok(());
+        }
+
         let mut pass = 1usize;
         loop {
             if pass > max_iterations {
@@ -1421,13 +2318,33 @@ impl<B: LlmBackend> Repl<B> {
                     }
                 };
 
+                let cwd = match &phase.cwd {
+                    Some(dir) => match interpolate_phase_cmd(dir, &self.context) {
+                        Some(d) => Some(d),
+                        None => {
+                            println!(
+                                "  {} [{}] skipped: no files in context",
+                                style("⚠").yellow(),
+                                label
+                            );
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
+
                 println!(
                     "  {} {}",
                     style(format!("[{}]", label)).dim(),
                     style(&cmd_str).cyan()
                 );
 
-                let output = Command::new("sh").arg("-c").arg(&cmd_str).output();
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(&cmd_str).envs(&phase.env);
+                if let Some(dir) = &cwd {
+                    command.current_dir(dir);
+                }
+                let output = command.output();
 
                 match output {
                     Err(e) => {
@@ -1551,8 +2468,9 @@ impl<B: LlmBackend> Repl<B> {
             }
         };
 
-        // Parse --output/-o flag before processing other args
+        // Parse --output/-o and --package flags before processing other args
         let mut output_file: Option<String> = None;
+        let mut package: Option<String> = None;
         let mut filtered_args: Vec<&str> = Vec::new();
         let mut iter = args.iter().peekable();
         while let Some(arg) = iter.next() {
@@ -1560,6 +2478,12 @@ impl<B: LlmBackend> Repl<B> {
                 if let Some(next) = iter.next() {
                     output_file = Some(next.to_string());
                 }
+            } else if *arg == "--package" {
+                if let Some(next) = iter.next() {
+                    package = Some(next.to_string());
+                }
+            } else if let Some(val) = arg.strip_prefix("--package=") {
+                package = Some(val.to_string());
             } else if let Some(val) = arg
                 .strip_prefix("--output=")
                 .or_else(|| arg.strip_prefix("-o="))
@@ -1587,6 +2511,11 @@ impl<B: LlmBackend> Repl<B> {
         if !content_parts.is_empty() {
             variables.insert("content".to_string(), content_parts.join(" "));
         }
+        if let Some(package) = package {
+            variables.insert("package".to_string(), package);
+        }
+
+        self.auto_add_template_files(&template.auto_add);
 
         // Render the template
         let prompt = match self
@@ -1636,6 +2565,15 @@ impl<B: LlmBackend> Repl<B> {
         }
 
         // Determine target file: from flag or interactive prompt
+        if self.readonly {
+            if let Some(path) = output_file {
+                println!(
+                    "{}",
+                    style(format!("Read-only mode: not saving to {}.", path)).yellow()
+                );
+            }
+            return Ok(true);
+        }
         let target_file = if let Some(f) = output_file {
             Some(f)
         } else {
@@ -1669,33 +2607,23 @@ impl<B: LlmBackend> Repl<B> {
         let width = terminal_width().min(70);
         let renderer = BoxRenderer::new(self.box_style, self.theme.clone()).with_width(width);
 
-        // Built-in commands section
-        let commands = [
-            ("/help", "Show this help"),
-            ("/exit", "Exit the REPL"),
-            ("/clear", "Clear conversation"),
-            ("/model [name]", "Show/set current model"),
-            ("/context", "Show context summary"),
-            ("/tokens", "Show token usage"),
-            ("/files", "List files in context"),
-            ("/add <path> [...]", "Add file/directory to context"),
-            ("/remove <file> [...]", "Remove file from context"),
-            ("/pwd", "Print working directory"),
-            ("/fileops [on|off]", "Toggle file operations"),
-            ("/templates", "List available templates"),
-            ("/rules", "Show loaded rules"),
-            ("/rule enable|disable", "Enable/disable a rule"),
-            ("/exec <command>", "Run a shell command"),
-            ("/export [file]", "Export chat to a text file"),
-        ];
-
+        // Built-in commands, grouped by category
         let mut content = String::new();
-        for (cmd, desc) in commands {
+        for category in HelpCategory::ALL {
+            if !content.is_empty() {
+                content.push('\n');
+            }
             content.push_str(&format!(
-                "  {} - {}\n",
-                self.theme.success.apply_to(format!("{:<16}", cmd)),
-                self.theme.muted.apply_to(desc)
+                "{}\n",
+                self.theme.primary.apply_to(category.title())
             ));
+            for (_, cmd, desc) in HELP_COMMANDS.iter().filter(|(c, _, _)| *c == category) {
+                content.push_str(&format!(
+                    "  {} - {}\n",
+                    self.theme.success.apply_to(format!("{:<20}", cmd)),
+                    self.theme.muted.apply_to(desc)
+                ));
+            }
         }
         // Remove trailing newline
         content.pop();
@@ -1774,29 +2702,59 @@ impl<B: LlmBackend> Repl<B> {
                 "Exits the chat session. You can also use Ctrl+D to exit.",
             ),
             "clear" => (
-                "/clear",
+                "/clear [keep=N]",
                 "Clear conversation history",
                 "Clears all conversation messages from the current session. Files added \
-                 to context are preserved. Use Ctrl+L to clear the screen without clearing history.",
+                 to context are preserved. Use Ctrl+L to clear the screen without clearing history.\n\n\
+                 With keep=N, only the most recent N messages are kept and the screen is left \
+                 alone, which trims the context without losing the latest turns. Whole \
+                 exchanges are dropped, so an odd N never leaves a reply without its question.\n\n\
+                 Examples:\n  /clear         - Remove every message\n  /clear keep=4  - Keep the last two exchanges",
+            ),
+            "compact" => (
+                "/compact [n]",
+                "Summarize the oldest messages to reclaim tokens",
+                "Asks the model to summarize the oldest n messages, then replaces them with a \
+                 single assistant message tagged [compacted]. Without n, everything except the last \
+                 four messages is compacted. Files, rules and the system prompt are untouched.\n\n\
+                 Examples:\n  /compact      - Keep the last two exchanges verbatim\n  /compact 10   - Compact the oldest 10 messages",
             ),
             "model" => (
-                "/model [name]",
+                "/model [list | name] | /model pull <name>",
                 "Show or change the current model",
-                "Without arguments, shows the current model. With a model name, switches \
-                 to that model for subsequent messages.\n\n\
-                 Examples:\n  /model           - Show current model\n  /model qwen2.5:7b - Switch to qwen2.5:7b",
+                "Without arguments (or with 'list'), shows the current model and an \
+                 arrow-key menu of installed models with their sizes; Enter switches to the \
+                 highlighted one, Esc keeps the current model. With a model name, switches \
+                 to that model for subsequent messages. '/model pull <name>' downloads the \
+                 model through Ollama with a progress bar (like `slab pull`) and switches to \
+                 it once the pull succeeds.\n\n\
+                 Examples:\n  /model           - Pick a model from a menu\n  /model qwen2.5:7b - Switch to qwen2.5:7b\n  /model pull qwen3:8b - Download qwen3:8b and switch to it",
             ),
             "context" => (
-                "/context",
+                "/context [export [file]]",
                 "Show context summary",
                 "Displays a summary of the current context including message count, \
-                 files in context, token usage, and whether system prompt/rules are set.",
+                 files in context, token usage, and whether system prompt/rules are set.\n\n\
+                 '/context export' prints the fully assembled system content sent to the model \
+                 (system prompt, rules, and file blocks); give a file to write it there instead.\n\n\
+                 Examples:\n  /context\n  /context export\n  /context export prompt-debug.md",
+            ),
+            "cost" => (
+                "/cost",
+                "Estimate tokens sent and received this session",
+                "Adds up the estimated tokens (chars/4) of your messages (input) and the \
+                 model's replies (output), with the total and the average per message. If \
+                 the current model's [models.<name>] entry sets prompt_cost_per_1k and/or \
+                 completion_cost_per_1k, a price estimate is shown too.",
             ),
             "tokens" => (
-                "/tokens",
+                "/tokens [--model]",
                 "Show token usage",
                 "Shows the current token count and remaining budget. Token count is \
-                 estimated using a chars/4 approximation.",
+                 estimated using a chars/4 approximation.\n\n\
+                 The budget follows the model's native context window (from Ollama's \
+                 /api/show) unless context_limit is set in config. --model also prints \
+                 that window and how much of it the current context uses.",
             ),
             "files" => (
                 "/files",
@@ -1804,13 +2762,31 @@ impl<B: LlmBackend> Repl<B> {
                 "Shows all files currently added to the context, along with their \
                  approximate token counts.",
             ),
+            "tree" => (
+                "/tree",
+                "Show context files as a directory tree",
+                "Like /files, but groups the files in context by directory and draws them \
+                 as an indented tree (directories first, using the configured box style), \
+                 with each file's approximate token count.",
+            ),
             "add" => (
-                "/add <file|directory> [file2 ...]",
+                "/add [--no-gitignore] <file|directory> [file2 ...]",
                 "Add a file or directory to context",
                 "Adds file contents to the conversation context. If a directory is specified, \
                  all files are added recursively. Multiple paths can be specified.\n\n\
-                 Directories skip: hidden files, node_modules, target, .git, binary files\n\n\
-                 Examples:\n  /add src/main.rs\n  /add src/main.rs src/lib.rs\n  /add src/\n  /add ../other/",
+                 Directories skip: hidden files, node_modules, target, .git, binary files, \
+                 and anything matched by .gitignore. Pass --no-gitignore to include \
+                 gitignored files.\n\n\
+                 Examples:\n  /add src/main.rs\n  /add src/main.rs src/lib.rs\n  /add src/\n  /add ../other/\n  /add --no-gitignore build/",
+            ),
+            "add-url" => (
+                "/add-url <url> [url2 ...]",
+                "Fetch a URL into context",
+                "Downloads each URL and stores the body as a context entry named \
+                 url:<host><path>, which you can /remove like a file. Only text responses \
+                 (text/*, JSON, XML, YAML, ...) are accepted, and the body counts toward \
+                 the token budget and ui.max_file_tokens.\n\n\
+                 Examples:\n  /add-url https://docs.rs/serde/latest/serde/\n  /add-url https://gist.githubusercontent.com/user/id/raw/notes.md",
             ),
             "remove" | "rm" => (
                 "/remove <file> [file2 ...], /rm <file> [file2 ...]",
@@ -1828,21 +2804,60 @@ impl<B: LlmBackend> Repl<B> {
                 "Toggle file operations",
                 "Controls whether the REPL automatically detects and offers to apply \
                  file operations from model responses. When enabled, code blocks with \
-                 filenames (e.g., ```rust:src/main.rs) are parsed as file operations.\n\n\
+                 filenames (e.g., ```rust:src/main.rs) are parsed as file operations. \
+                 They cannot be enabled in a `slab chat --readonly` session.\n\n\
                  Examples:\n  /fileops     - Show current status\n  /fileops on  - Enable\n  /fileops off - Disable",
             ),
             "templates" => (
-                "/templates",
+                "/templates [reload]",
                 "List available templates",
                 "Shows all loaded prompt templates and their slash commands. Templates \
-                 are loaded from .slab/templates/ and ~/.config/slab/templates/.",
+                 are loaded from .slab/templates/ and ~/.config/slab/templates/ at startup; \
+                 'reload' reads them again so edits, new files and deletions take effect \
+                 without restarting.\n\n\
+                 Examples:\n  /templates         - List templates\n  /templates reload  - Pick up edited template files",
             ),
             "rules" => (
                 "/rules",
                 "Show loaded rules",
-                "Displays all rules loaded from .slab/rules/. Rules provide persistent \
+                "Displays all rules loaded from .slab/rules/ and ~/.config/slab/rules/ \
+                 (project rules shadow global ones with the same name). Rules provide persistent \
                  guidelines that are injected into every conversation context.",
             ),
+            "edit" => (
+                "/edit [new message]",
+                "Edit and re-send the last message",
+                "Puts your last message back in the input line so you can fix it. Submitting \
+                 replaces that message, drops the replies to it, and sends it again; Ctrl+C \
+                 or running another command leaves the conversation unchanged. With text, the \
+                 last message is replaced and re-sent straight away.\n\n\
+                 Examples:\n  /edit                     - Edit the last message in place\n  /edit explain lifetimes   - Replace and re-send",
+            ),
+            "retry" => (
+                "/retry [temp=<value>]",
+                "Regenerate the last response",
+                "Drops the model's reply to your last message and sends that message again \
+                 for a fresh sample. temp= sets the temperature for this one request only; \
+                 /temp sets it for the rest of the session.\n\n\
+                 Examples:\n  /retry           - Ask again\n  /retry temp=1.2  - Ask again with more variety",
+            ),
+            "undo" => (
+                "/undo",
+                "Revert the last applied file operations",
+                "Reverts the last batch of file operations applied from a model response, \
+                 newest first: created files are deleted, edited files get their previous \
+                 content back, deleted files are restored and renames are reversed. Only \
+                 one batch is kept, so a second /undo has nothing to revert.\n\n\
+                 Example:\n  /undo",
+            ),
+            "explain-diff" => (
+                "/explain-diff",
+                "Ask the model to justify its last proposed changes",
+                "Parses the file operations from the last response, diffs each one against \
+                 the file on disk, and sends the diffs back asking the model to explain and \
+                 justify every change. Nothing is applied.\n\n\
+                 Example:\n  /explain-diff",
+            ),
             "rule" => (
                 "/rule enable|disable <name>",
                 "Enable or disable a rule",
@@ -1852,17 +2867,111 @@ impl<B: LlmBackend> Repl<B> {
                 "/exec <command>",
                 "Run a shell command",
                 "Runs the given command in the system shell (sh -c on Unix, cmd /C on Windows). \
-                 Use for one-off commands like podman/docker exec, running scripts, etc.\n\n\
+                 Use for one-off commands like podman/docker exec, running scripts, etc. \
+                 The full output is printed, but only ui.exec_output_max_lines lines of each \
+                 stream (head and tail) are added to context.\n\n\
                  Example:\n  /exec podman exec container echo hello world",
             ),
+            "replay" => (
+                "/replay",
+                "Re-run the exec blocks from the last response",
+                "Parses the exec/run blocks in the most recent assistant message and runs all \
+                 of them again, adding the fresh output to context. Risky commands (rm -rf, \
+                 sudo, ...) still ask for confirmation before they run. \
+                 Useful after editing files to re-check the same build or test commands.\n\n\
+                 Example:\n  /replay",
+            ),
             "export" => (
-                "/export [filename]",
-                "Export chat to a plain-text file",
-                "Writes the full conversation to a plain-text file with no ANSI escape codes, \
+                "/export [--append] [--format md|txt] [--from <mark>] [filename]",
+                "Export chat to a plain-text or markdown file",
+                "Writes the full conversation to a file with no ANSI escape codes, \
                  making it safe to open in any editor or share via email/ticket. \
                  Works correctly through tmux and other terminal multiplexers.\n\n\
-                 If no filename is given, a timestamped file is created in the current directory.\n\n\
-                 Examples:\n  /export                     - Save to slab-export-YYYY-MM-DD-HHMMSS.txt\n  /export chat.txt            - Save to chat.txt\n  /export /tmp/debug-chat.txt - Save to an absolute path",
+                 If no filename is given, a timestamped file is created in the current directory. \
+                 --append adds the transcript to the end of an existing file after a separator \
+                 instead of overwriting it. --format md writes a markdown transcript with a \
+                 heading per message, keeping code fences intact; it is also picked automatically \
+                 for .md filenames. --from exports only the messages after a mark set with /mark.\n\n\
+                 Examples:\n  /export                     - Save to slab-export-YYYY-MM-DD-HHMMSS.txt\n  /export chat.txt            - Save to chat.txt\n  /export --append chat.log    - Append to a running log\n  /export --format md notes   - Markdown transcript\n  /export --from fix fix.md    - Only what came after /mark fix\n  /export /tmp/debug-chat.txt - Save to an absolute path",
+            ),
+            "load" => (
+                "/load <session>",
+                "Load a saved session",
+                "Replaces the current conversation with a session saved in .slab/sessions/ \
+                 (by `slab chat --session`): its messages, marks and files in context are \
+                 restored and its model is selected. Files added before the load are dropped. If the current conversation has \
+                 messages, you are asked before they are discarded. Tab completes session \
+                 names.\n\n\
+                 Example:\n  /load parser-refactor",
+            ),
+            "mark" => (
+                "/mark <label>",
+                "Bookmark the current point in the conversation",
+                "Records how many messages the conversation has under a one-word label. \
+                 Marking an existing label moves it. Marks are saved with the session; \
+                 /marks lists them and /export --from <label> exports only the messages \
+                 after one. /clear removes all marks.\n\n\
+                 Examples:\n  /mark before-refactor\n  /export --from before-refactor refactor.md",
+            ),
+            "marks" => (
+                "/marks",
+                "List bookmarks set with /mark",
+                "Shows each mark with the number of messages before it and how many \
+                 have been added since.\n\n\
+                 Example:\n  /marks",
+            ),
+            "temp" => (
+                "/temp [value|reset]",
+                "Override the sampling temperature for this session",
+                "Sets the temperature sent with every following message without touching the \
+                 config file. Accepts 0.0 to 2.0. 'reset' goes back to the configured value; \
+                 with no argument the current value is shown.\n\n\
+                 Examples:\n  /temp 0        - Deterministic answers\n  /temp reset    - Back to config",
+            ),
+            "resend-with" => (
+                "/resend-with <model>",
+                "Ask another model your last message, once",
+                "Sends the last user message, with the conversation before it, to the \
+                 named model and prints the answer under the model's name. The session \
+                 model does not change and the answer is not added to the conversation, \
+                 so it is a quick way to compare models.\n\n\
+                 Example:\n  /resend-with codellama:13b",
+            ),
+            "theme" => (
+                "/theme [name|next|save]",
+                "Switch the color theme for this session",
+                "Applies a theme immediately and redraws the status bar. 'next' cycles \
+                 through the built-in themes in order, wrapping around; 'save' writes the \
+                 current theme to the project config as ui.theme. With no argument the \
+                 current and available themes are listed.\n\n\
+                 Examples:\n  /theme nord\n  /theme next\n  /theme save",
+            ),
+            "json" => (
+                "/json [on|off]",
+                "Force JSON output for this session",
+                "Sends format: \"json\" with every following message so the model returns \
+                 valid, parseable JSON (response_format json_object on OpenAI-compatible \
+                 backends). Describe the shape you want in the prompt. With no argument the \
+                 current state is shown.\n\n\
+                 Examples:\n  /json on   - Constrain replies to JSON\n  /json off  - Back to free text",
+            ),
+            "top_p" => (
+                "/top_p [value|reset]",
+                "Override nucleus sampling (top-p) for this session",
+                "Sets the top_p sent with every following message without touching the \
+                 config file. Accepts 0.0 to 1.0. 'reset' goes back to the configured value; \
+                 with no argument the current value is shown.\n\n\
+                 Examples:\n  /top_p 0.5\n  /top_p reset",
+            ),
+            "history" => (
+                "/history [search <term>]",
+                "Show or search input history",
+                "Without arguments, lists the last 20 inputs with their indices. \
+                 With 'search', lists every past input containing the term \
+                 (case-insensitive), most recent first.\n\n\
+                 Press Ctrl+R at the prompt for incremental reverse-search: type to filter, \
+                 Ctrl+R again for the next older match, Enter to insert, Esc to cancel.\n\n\
+                 Examples:\n  /history\n  /history search explain",
             ),
             _ => {
                 // Check if it's a template command
@@ -1925,25 +3034,50 @@ impl<B: LlmBackend> Repl<B> {
 
         // Expand @file references before sending to the LLM
         let expanded = self.context.expand_file_references(content);
-        // Add the expanded message to context
-        self.context.add_message(Message::user(&expanded));
+        // Add the expanded message to context, remembering what was typed for /edit
+        self.context
+            .add_message(Message::user(&expanded).with_typed(content));
+        self.fit_context_budget()?;
 
-        // Build messages from context
-        let messages = self.context.build_messages();
-
-        let model_config = self.config.get_model_config(&self.model);
+        let request = self.build_chat_request();
+        self.respond(request).await
+    }
 
-        let mut request = ChatRequest {
-            model: self.model.clone(),
-            messages,
-            stream: Some(self.streaming),
-            options: Some(ModelOptions {
-                temperature: Some(model_config.temperature),
-                top_p: Some(model_config.top_p),
-                num_ctx: Some(self.config.context_limit),
-            }),
-        };
+    /// Refuse to send an over-budget context rather than let the backend silently
+    /// truncate it, pruning old messages first when `auto_prune` is on. On failure
+    /// the message just added is removed again.
+    fn fit_context_budget(&mut self) -> Result<()> {
+        // Pruning keeps a leading system message, so marks shift from after it
+        let prune_start = usize::from(
+            self.context
+                .messages()
+                .first()
+                .is_some_and(|m| m.role == "system"),
+        );
+        match self.context.ensure_within_budget(self.config.auto_prune) {
+            Ok(0) => {}
+            Ok(pruned) => {
+                shift_marks(&mut self.marks, prune_start, pruned, 0);
+                println!(
+                    "{}",
+                    style(format!(
+                        "✂  Pruned {} old message(s) to fit the context budget.",
+                        pruned
+                    ))
+                    .dim()
+                );
+            }
+            Err(e) => {
+                self.context.pop_message();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
 
+    /// Send `request`, add the reply to context and handle its file and exec
+    /// operations, continuing for as long as approved exec output is fed back
+    async fn respond(&mut self, mut request: ChatRequest) -> Result<()> {
         loop {
             let response = if self.streaming {
                 self.stream_response(request).await?
@@ -1952,6 +3086,11 @@ impl<B: LlmBackend> Repl<B> {
             };
 
             if response.is_empty() {
+                // An interrupted answer is kept so "continue" can pick it up, but it
+                // isn't complete enough to apply file operations or exec blocks from
+                if let Some(partial) = self.take_partial_response() {
+                    self.context.add_message(Message::assistant(partial));
+                }
                 break;
             }
 
@@ -1964,27 +3103,265 @@ impl<B: LlmBackend> Repl<B> {
 
             // If the LLM ran exec blocks and the user approved, feed the output
             // back so the LLM can continue rather than dropping back to the prompt.
-            if !self.process_exec_operations(&response)? {
+            if self.readonly || !self.process_exec_operations(&response)? {
                 break;
             }
 
-            let messages = self.context.build_messages();
-            let model_config = self.config.get_model_config(&self.model);
-            request = ChatRequest {
-                model: self.model.clone(),
-                messages,
-                stream: Some(self.streaming),
-                options: Some(ModelOptions {
-                    temperature: Some(model_config.temperature),
-                    top_p: Some(model_config.top_p),
-                    num_ctx: Some(self.config.context_limit),
-                }),
-            };
+            request = self.build_chat_request();
+        }
+
+        Ok(())
+    }
+
+    /// Switch to `name`, adopting its context window and warning if it isn't installed
+    async fn switch_model(&mut self, name: &str) {
+        self.model = name.to_string();
+        println!(
+            "{} {}",
+            style("Switched to model:").dim(),
+            style(&self.model).yellow()
+        );
+        self.adopt_model_context_length().await;
+        let known = self
+            .cached_models
+            .as_ref()
+            .is_none_or(|models| models.contains(&self.model));
+        if !known {
+            println!(
+                "{} '{}' is not in the list of available models.",
+                style("⚠").yellow(),
+                self.model
+            );
+            self.print_model_suggestions(&self.model);
+        }
+    }
+
+    /// Show the installed models in an arrow-key menu and switch to the chosen one
+    async fn pick_model(&mut self) {
+        println!(
+            "{} {}",
+            style("Current model:").dim(),
+            style(&self.model).yellow()
+        );
+
+        // Sizes aren't cached, so ask the backend; fall back to cached names if it's unreachable
+        let mut sizes = HashMap::new();
+        match self.client.llm_list_models().await {
+            Ok(models) => {
+                for model in &models {
+                    if let Some(size) = model.size_label() {
+                        sizes.insert(model.name.clone(), size);
+                    }
+                }
+                self.cached_models = Some(models.into_iter().map(|m| m.name).collect());
+            }
+            Err(e) if self.cached_models.is_none() => {
+                println!("{} Could not list models: {}", style("Error:").red(), e);
+                return;
+            }
+            Err(_) => {}
+        }
+        let names = self.cached_models.clone().unwrap_or_default();
+        if names.is_empty() {
+            println!("{} No models available.", style("⚠").yellow());
+            return;
+        }
+        let items = model_menu_items(&names, &sizes, &self.model);
+
+        if !io::stdin().is_terminal() {
+            for (name, desc, _) in &items {
+                println!(
+                    "  {} {}",
+                    style(name).yellow(),
+                    style(desc.as_deref().unwrap_or("")).dim()
+                );
+            }
+            return;
+        }
+        if let Some(name) = self.show_interactive_completion_menu(&items) {
+            self.switch_model(&name).await;
         }
+    }
+
+    /// Pull `name` with a progress bar (same as `slab pull`), then switch to it
+    async fn pull_and_switch_model(&mut self, name: &str) -> Result<()> {
+        let rx = self.client.llm_pull_model(name).await?;
+        show_pull_progress(rx).await?;
+        println!("{} Pulled {}", style("✓").green(), style(name).cyan());
 
+        if let Ok(models) = self.client.llm_list_models().await {
+            self.cached_models = Some(models.into_iter().map(|m| m.name).collect());
+        }
+        if let Some(models) = self.cached_models.as_mut() {
+            if !models.iter().any(|m| m == name) {
+                models.push(name.to_string());
+            }
+        }
+        self.switch_model(name).await;
         Ok(())
     }
 
+    /// Shrink the token budget to the model's native context window when that is
+    /// smaller than the default limit, unless `context_limit` was set explicitly in
+    /// config. Larger windows are not adopted, so Ollama doesn't allocate a
+    /// full-size KV cache for every request; `num_ctx` always matches the budget.
+    async fn adopt_model_context_length(&mut self) {
+        if self.config.context_limit.is_some() {
+            return;
+        }
+        if let Ok(Some(window)) = self.client.llm_context_length(&self.model).await {
+            self.context
+                .set_token_budget(window.min(self.config.effective_context_limit()));
+        }
+    }
+
+    /// Summarize the oldest `count` messages with the model and replace them with a
+    /// single `[compacted]` assistant message. Returns how many messages were replaced.
+    async fn compact_history(&mut self, count: usize) -> Result<usize> {
+        let count = count.min(self.context.messages().len());
+        if count < 2 {
+            return Ok(0);
+        }
+
+        let transcript = self.context.messages()[..count]
+            .iter()
+            .map(|m| format!("{}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let mut request = self.build_chat_request();
+        request.stream = Some(false);
+        request.messages = vec![
+            Message::system(
+                "Summarize the conversation below for your own future reference. \
+                 Keep decisions, file names, code changes, open questions and any facts \
+                 the user stated. Be concise; use bullet points.",
+            ),
+            Message::user(transcript),
+        ];
+
+        let mut summary = self.client.llm_chat(request).await?.content;
+        if self.config.ui.strip_think_blocks {
+            summary = strip_think_blocks(&summary);
+        }
+        if summary.trim().is_empty() {
+            return Err(SlabError::StreamError(
+                "Model returned an empty summary".to_string(),
+            ));
+        }
+
+        let replaced = self.context.compact_messages(
+            count,
+            Message::assistant(format!(
+                "[compacted] Summary of {} earlier message(s):\n{}",
+                count,
+                summary.trim()
+            )),
+        );
+        shift_marks(&mut self.marks, 0, replaced, 1);
+        Ok(replaced)
+    }
+
+    /// Prompt asking the model to justify the file operations in its last response,
+    /// with a plain diff of each one. Returns the operation count alongside it.
+    fn explain_diff_prompt(&self) -> Option<(usize, String)> {
+        let response = self.context.last_assistant_message()?;
+        let mut operations = parse_file_operations(response, &self.project_root);
+        if operations.is_empty() {
+            return None;
+        }
+
+        let mut prompt = String::from(
+            "Explain the changes you just proposed. For each diff below, say what it changes \
+             and why it is needed, and point out any risk or behavior change.\n",
+        );
+        for op in &mut operations {
+            op.load_original(&self.project_root);
+            prompt.push_str(&format!(
+                "\n### {} {}\n```diff\n{}```\n",
+                op.kind(),
+                op.path().display(),
+                op.diff_text()
+            ));
+        }
+        Some((operations.len(), prompt))
+    }
+
+    /// Build a request from the current context, applying any /temp or /top_p overrides
+    fn build_chat_request(&self) -> ChatRequest {
+        let model_config = self.config.get_model_config(&self.model);
+        ChatRequest {
+            model: self.model.clone(),
+            messages: self.context.build_messages(),
+            stream: Some(self.streaming),
+            options: Some(ModelOptions {
+                temperature: Some(
+                    self.temperature_override
+                        .unwrap_or(model_config.temperature),
+                ),
+                top_p: Some(self.top_p_override.unwrap_or(model_config.top_p)),
+                num_ctx: Some(self.context.token_budget()),
+                seed: None,
+                num_predict: None,
+                stop: Vec::new(),
+            }),
+            format: self.json_mode.then(|| "json".to_string()),
+        }
+    }
+
+    /// Handle `/temp` and `/top_p`: show, set (within `range`) or reset a sampling override
+    fn handle_sampling_override(&mut self, cmd: &str, arg: Option<&str>, range: (f32, f32)) {
+        let label = if cmd == "temp" {
+            "Temperature"
+        } else {
+            "Top-p"
+        };
+        let model_config = self.config.get_model_config(&self.model);
+        let (current, configured) = if cmd == "temp" {
+            (&mut self.temperature_override, model_config.temperature)
+        } else {
+            (&mut self.top_p_override, model_config.top_p)
+        };
+
+        match arg {
+            None => match current {
+                Some(value) => println!(
+                    "{} {} {}",
+                    style(format!("{}:", label)).dim(),
+                    value,
+                    style(format!("(override, config: {})", configured)).dim()
+                ),
+                None => println!("{} {}", style(format!("{}:", label)).dim(), configured),
+            },
+            Some("reset") => {
+                *current = None;
+                println!(
+                    "{} {} reset to config value {}",
+                    style("✓").green(),
+                    label,
+                    configured
+                );
+            }
+            Some(raw) => match raw.parse::<f32>() {
+                Ok(value) if (range.0..=range.1).contains(&value) => {
+                    *current = Some(value);
+                    println!(
+                        "{} {} set to {} for this session",
+                        style("✓").green(),
+                        label,
+                        value
+                    );
+                }
+                _ => println!(
+                    "{} {} must be a number between {} and {}",
+                    style("Error:").red(),
+                    label,
+                    range.0,
+                    range.1
+                ),
+            },
+        }
+    }
+
     async fn stream_response(&mut self, request: ChatRequest) -> Result<String> {
         let mut rx = self.client.llm_stream(request).await?;
 
@@ -2015,14 +3392,46 @@ impl<B: LlmBackend> Repl<B> {
 
         let mut interrupted = false;
 
+        // Until the first non-whitespace chunk arrives, show an in-place counter on the
+        // prefix line so long silent stretches don't look frozen
+        let term = Term::stdout();
+        let started = Instant::now();
+        let mut waiting = self.config.ui.show_stream_progress;
+        let mut progress_drawn = false;
+        let mut chunks = 0usize;
+        let mut received_chars = 0usize;
+        let mut held = String::new();
+        let mut think = self.config.ui.strip_think_blocks.then(ThinkFilter::default);
+        let tick = Duration::from_millis(250);
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + tick, tick);
+
         loop {
             tokio::select! {
                 chunk = rx.recv() => {
                     match chunk {
                         Some(Ok(text)) => {
-                            full_response.push_str(&text);
+                            chunks += 1;
+                            received_chars += text.chars().count();
+                            let segments = match think.as_mut() {
+                                Some(filter) => filter.push(&text),
+                                None => vec![ThinkSegment::Answer(text)],
+                            };
+                            let mut display = split_think_display(segments, &mut full_response);
+                            if waiting {
+                                held.push_str(&display);
+                                if console::strip_ansi_codes(&held).trim().is_empty() {
+                                    continue;
+                                }
+                                waiting = false;
+                                if progress_drawn {
+                                    term.clear_line().ok();
+                                    print!("{} ", style("┃").blue());
+                                }
+                                // Flush what was held back along with this chunk
+                                display = std::mem::take(&mut held);
+                            }
                             // In raw mode, \n doesn't reset to column 0; use \r\n instead
-                            print!("{}", text.replace('\n', "\r\n"));
+                            print!("{}", display.replace('\n', "\r\n"));
                             io::stdout().flush().ok();
                         }
                         Some(Err(e)) => {
@@ -2033,6 +3442,16 @@ impl<B: LlmBackend> Repl<B> {
                         None => break,
                     }
                 }
+                _ = ticker.tick(), if waiting => {
+                    term.clear_line().ok();
+                    print!(
+                        "{} {}",
+                        style("┃").blue(),
+                        style(format_stream_progress(chunks, received_chars, started.elapsed())).dim()
+                    );
+                    io::stdout().flush().ok();
+                    progress_drawn = true;
+                }
                 _ = cancel_rx.recv() => {
                     interrupted = true;
                     break;
@@ -2040,22 +3459,41 @@ impl<B: LlmBackend> Repl<B> {
             }
         }
 
+        if waiting && progress_drawn {
+            term.clear_line().ok();
+            print!("{} ", style("┃").blue());
+        }
+
+        // Release a trailing fragment the think filter was holding back
+        if let Some(filter) = think.as_mut() {
+            held.push_str(&split_think_display(filter.finish(), &mut full_response));
+            print!("{}", held.replace('\n', "\r\n"));
+            full_response = full_response.trim_start().to_string();
+        }
+
         crossterm::terminal::disable_raw_mode().ok();
 
         if interrupted {
             // Drop the receiver so the spawned stream task stops
             drop(rx);
-            println!("\n{}", style("(interrupted)").dim());
+            if !full_response.trim().is_empty() {
+                self.partial_response = Some(full_response);
+            }
+            if self.config.ui.keep_partial_on_interrupt && self.partial_response.is_some() {
+                println!("\n{}", style("(interrupted — partial response kept)").dim());
+            } else {
+                println!("\n{}", style("(interrupted)").dim());
+            }
             println!();
             return Ok(String::new());
         }
 
         println!();
 
-        // Re-print with syntax highlighting if there are code blocks
-        if full_response.contains("```") {
+        // Re-print with syntax highlighting and aligned tables if there are any
+        if full_response.contains("```") || has_markdown_table(&full_response) {
             println!();
-            let highlighted = self.highlighter.format_response(&full_response);
+            let highlighted = self.render_response(&full_response);
             for line in highlighted.lines() {
                 println!("{}", line);
             }
@@ -2066,6 +3504,47 @@ impl<B: LlmBackend> Repl<B> {
         Ok(full_response)
     }
 
+    /// Ask `model` the last user message once, with the conversation up to it, without
+    /// switching models or adding the answer to the conversation
+    async fn resend_with(&mut self, model: &str) -> Result<()> {
+        let mut request = self.build_chat_request();
+        // The last typed message, not command or hook output that followed it
+        let Some(last_user) = request.messages.iter().rposition(|m| m.typed.is_some()) else {
+            println!("{}", style("No message to resend yet.").dim());
+            return Ok(());
+        };
+        request.messages.truncate(last_user + 1);
+        request.model = model.to_string();
+
+        println!(
+            "{} {}",
+            self.theme.warning.apply_to(format!("[{}]", model)),
+            style("(one-off, not added to the conversation)").dim()
+        );
+        if self.streaming {
+            self.stream_response(request).await?;
+        } else {
+            self.wait_response(request).await?;
+        }
+        // One-off answers never join the conversation, even partially
+        self.partial_response = None;
+        Ok(())
+    }
+
+    /// The text streamed before an interrupt, if ui.keep_partial_on_interrupt keeps it
+    fn take_partial_response(&mut self) -> Option<String> {
+        self.partial_response
+            .take()
+            .filter(|_| self.config.ui.keep_partial_on_interrupt)
+    }
+
+    /// Highlight code blocks and redraw markdown tables with the current box style
+    fn render_response(&self, response: &str) -> String {
+        let renderer = BoxRenderer::new(self.box_style, self.theme.clone());
+        self.highlighter
+            .format_response(&render_markdown_tables(response, &renderer))
+    }
+
     async fn wait_response(&mut self, request: ChatRequest) -> Result<String> {
         let spinner = ProgressBar::new_spinner();
         spinner.set_style(
@@ -2099,7 +3578,7 @@ impl<B: LlmBackend> Repl<B> {
         });
 
         let response = tokio::select! {
-            resp = self.client.llm_chat(request) => resp?,
+            resp = self.client.llm_chat(request) => resp?.content,
             _ = cancel_rx.recv() => {
                 crossterm::terminal::disable_raw_mode().ok();
                 spinner.finish_and_clear();
@@ -2112,9 +3591,15 @@ impl<B: LlmBackend> Repl<B> {
         crossterm::terminal::disable_raw_mode().ok();
         spinner.finish_and_clear();
 
-        // Format with syntax highlighting if there are code blocks
-        if response.contains("```") {
-            let highlighted = self.highlighter.format_response(&response);
+        let response = if self.config.ui.strip_think_blocks {
+            strip_think_blocks(&response)
+        } else {
+            response
+        };
+
+        // Format with syntax highlighting and aligned tables if there are any
+        if response.contains("```") || has_markdown_table(&response) {
+            let highlighted = self.render_response(&response);
             for line in highlighted.lines() {
                 println!("{}", line);
             }
@@ -2142,6 +3627,60 @@ impl<B: LlmBackend> Repl<B> {
         }
     }
 
+    /// The last message the user typed, before `@file` expansion. Command and hook
+    /// output added as user messages is skipped.
+    fn last_user_input(&self) -> Option<String> {
+        self.context
+            .messages()
+            .iter()
+            .rev()
+            .find_map(|m| m.typed.clone())
+    }
+
+    /// Re-read template files and refresh the template slash commands
+    fn reload_templates(&mut self) {
+        let failures = self
+            .templates
+            .reload(&get_template_directories(&self.project_root));
+        let count = self.templates.list().len();
+        self.completion_engine
+            .set_template_commands(template_completions(&self.templates, &self.config));
+
+        println!("{} Reloaded {} template(s)", style("✓").green(), count);
+        for (path, e) in failures {
+            println!(
+                "  {} {}: {}",
+                style("✗").red(),
+                style(path.display()).cyan(),
+                e
+            );
+        }
+    }
+
+    /// Persist the conversation to the autosave session once at least
+    /// `ui.autosave_interval` messages were added or removed since the last one
+    fn maybe_autosave(&mut self) {
+        let Some(interval) = self.config.ui.autosave_interval.filter(|n| *n > 0) else {
+            return;
+        };
+        let count = self.context.messages().len();
+        if count.abs_diff(self.autosaved_count) < interval {
+            return;
+        }
+        let Some(dir) = self.sessions_dir.clone().or_else(Session::session_dir) else {
+            return;
+        };
+
+        let mut session = Session::new(AUTOSAVE_NAME, &self.model);
+        session.messages = self.context.messages().to_vec();
+        session.marks = self.marks.clone();
+        session.files = self.context.file_entries();
+        match session.save_autosave_in(&dir) {
+            Ok(()) => self.autosaved_count = count,
+            Err(e) => eprintln!("{} {}", style("Warning:").yellow(), e),
+        }
+    }
+
     /// Load a session into the REPL
     pub fn load_session(&mut self, session: Session) {
         // Update model if different
@@ -2149,9 +3688,14 @@ impl<B: LlmBackend> Repl<B> {
             self.model = session.model.clone();
         }
 
-        // Load messages into context
+        // Load messages into context. What was typed isn't saved, so user turns
+        // other than command output count as typed as stored.
         for msg in &session.messages {
-            self.context.add_message(msg.clone());
+            let mut msg = msg.clone();
+            if msg.role == "user" && !is_command_output(&msg.content) {
+                msg.typed = Some(msg.content.clone());
+            }
+            self.context.add_message(msg);
         }
 
         // Add to command history
@@ -2160,12 +3704,36 @@ impl<B: LlmBackend> Repl<B> {
                 self.history.push(msg.content.clone());
             }
         }
+        self.autosaved_count = self.context.messages().len();
+        self.marks = session.marks.clone();
+        self.session_name = Some(session.name.clone());
+
+        // Restore files in context, re-read from disk; ones deleted since the save
+        // fall back to the saved copy
+        let restored = session.files.len();
+        for (path, content) in &session.files {
+            if !self.context.restore_file(path.clone(), content.clone()) {
+                println!(
+                    "{} {} no longer exists; using the copy saved with the session",
+                    style("⚠").yellow(),
+                    path.display()
+                );
+            }
+        }
+        if restored > 0 {
+            self.update_rules_for_context();
+        }
 
         println!(
-            "{} Loaded session '{}' with {} messages",
+            "{} Loaded session '{}' with {} messages{}",
             style("✓").green(),
             style(&session.name).cyan(),
-            session.messages.len()
+            session.messages.len(),
+            if restored > 0 {
+                format!(" and {} file(s)", restored)
+            } else {
+                String::new()
+            }
         );
     }
 
@@ -2184,7 +3752,7 @@ impl<B: LlmBackend> Repl<B> {
                         );
                         if !skipped.is_empty() {
                             println!(
-                                "{} Skipped {} file(s) (binary/unreadable)",
+                                "{} Skipped {} file(s) (binary/unreadable/too large/over budget)",
                                 style("⚠").yellow(),
                                 skipped.len()
                             );
@@ -2214,12 +3782,57 @@ impl<B: LlmBackend> Repl<B> {
         }
     }
 
+    /// Add a template's `auto_add` paths/globs to context. Patterns that match
+    /// nothing, and files that can't be added, are reported but don't abort.
+    fn auto_add_template_files(&mut self, patterns: &[String]) {
+        let cwd = self.context.initial_cwd().clone();
+        let mut added = 0;
+        for pattern in patterns {
+            let full_pattern = cwd.join(pattern);
+            let matches: Vec<PathBuf> = glob::glob(&full_pattern.to_string_lossy())
+                .map(|paths| paths.flatten().filter(|p| p.is_file()).collect())
+                .unwrap_or_default();
+            if matches.is_empty() {
+                println!(
+                    "{} auto_add: no files match {}",
+                    style("⚠").yellow(),
+                    style(pattern).cyan()
+                );
+                continue;
+            }
+            for path in matches {
+                let display = path.strip_prefix(&cwd).unwrap_or(&path).to_path_buf();
+                if self.context.has_file(&display) {
+                    continue;
+                }
+                match self.context.add_file(&display) {
+                    Ok(()) => {
+                        added += 1;
+                        println!(
+                            "{} Added {} to context",
+                            style("✓").green(),
+                            style(display.display()).cyan()
+                        );
+                    }
+                    Err(e) => {
+                        println!("{} auto_add: {}", style("⚠").yellow(), e);
+                    }
+                }
+            }
+        }
+        if added > 0 {
+            self.update_rules_for_context();
+        }
+    }
+
     /// Send a template as an initial message (used with --template flag in chat mode)
     pub async fn send_template(&mut self, template_name: &str) -> Result<()> {
         let template = self.templates.get(template_name).ok_or_else(|| {
             crate::error::SlabError::TemplateError(format!("Template not found: {}", template_name))
         })?;
         let template_display_name = template.name.clone();
+        let auto_add = template.auto_add.clone();
+        self.auto_add_template_files(&auto_add);
 
         // Render with defaults only (no user variables in chat mode)
         let variables = HashMap::new();
@@ -2239,115 +3852,114 @@ impl<B: LlmBackend> Repl<B> {
         Ok(())
     }
 
+    /// Name of the session currently loaded, which follows /load
+    pub fn session_name(&self) -> Option<&str> {
+        self.session_name.as_deref()
+    }
+
     /// Save the current session
     pub fn save_session(&self, name: &str) -> std::result::Result<(), String> {
-        let messages = self.context.messages().to_vec();
-        let mut session = Session::new(name, &self.model);
-        session.messages = messages;
+        // Keep the original created_at when overwriting an existing session
+        let mut session = Session::load(name).unwrap_or_else(|_| Session::new(name, &self.model));
+        if session.created_at.is_empty() {
+            session.created_at = chrono::Local::now().to_rfc3339();
+        }
+        session.model = self.model.clone();
+        session.messages = self.context.messages().to_vec();
+        session.marks = self.marks.clone();
+        session.files = self.context.file_entries();
+        session.touch();
         session.save()
     }
 
-    fn process_file_operations(&self, response: &str) -> Result<()> {
-        let mut operations = parse_file_operations(response, &self.project_root);
-
-        if operations.is_empty() {
-            return Ok(());
+    fn process_file_operations(&mut self, response: &str) -> Result<()> {
+        let operations = filter_allowed_operations(
+            parse_file_operations(response, &self.project_root),
+            &self.config.ui.allowed_file_ops,
+        );
+        let (applied, hook_runs) = review_and_apply(
+            operations,
+            &self.project_root,
+            self.config.ui.auto_apply_file_ops,
+            &mut self.config.hooks,
+        )?;
+        for run in &hook_runs {
+            self.add_hook_output(run);
         }
-
-        // Filter to only safe operations
-        let safe_indices: Vec<usize> = operations
-            .iter()
-            .enumerate()
-            .filter(|(_, op)| op.safety_check(&self.project_root).is_ok())
-            .map(|(i, _)| i)
-            .collect();
-
-        if safe_indices.is_empty() {
-            println!(
-                "{} All file operations failed safety checks",
-                style("⚠").yellow()
-            );
-            return Ok(());
+        if !applied.is_empty() {
+            self.track_renames(&applied);
+            self.last_applied = applied;
         }
+        Ok(())
+    }
 
-        // Filter out edits that would truncate files (LLM output only a snippet)
-        let mut truncated = Vec::new();
-        let safe_indices: Vec<usize> = safe_indices
-            .into_iter()
-            .filter(|&i| {
-                if let Some((orig, new)) = operations[i].truncation_check() {
-                    truncated.push((i, orig, new));
-                    false
-                } else {
-                    true
-                }
-            })
-            .collect();
+    /// Revert the last applied batch of file operations in reverse order
+    fn undo_file_operations(&mut self) -> Result<()> {
+        let applied = std::mem::take(&mut self.last_applied);
+        println!();
+        println!(
+            "{} Undoing {} file operation(s):",
+            style("→").cyan(),
+            applied.len()
+        );
 
-        if !truncated.is_empty() {
-            println!();
-            for &(i, orig, new) in &truncated {
-                println!(
-                    "{} {} — blocked edit to {} ({} → {} lines). The model likely output only a snippet instead of the complete file.",
-                    style("⚠").red(),
-                    style("TRUNCATION BLOCKED").red().bold(),
-                    style(operations[i].path().display()).cyan(),
-                    orig,
-                    new,
-                );
+        let mut rollbacks = Vec::new();
+        let mut unknown = 0;
+        for op in applied.iter().rev() {
+            match op.rollback() {
+                Some(rollback) => rollbacks.push(rollback),
+                None => {
+                    println!(
+                        "  {} {} {} - previous content unknown",
+                        style("✗").red(),
+                        op.kind(),
+                        op.path().display()
+                    );
+                    unknown += 1;
+                }
             }
         }
 
-        if safe_indices.is_empty() && !truncated.is_empty() {
+        let all: Vec<usize> = (0..rollbacks.len()).collect();
+        let (succeeded, failed) = execute_operations(&rollbacks, &all, &self.project_root)?;
+        let failed = failed + unknown;
+        println!();
+        if failed == 0 {
             println!(
-                "{} All edits were blocked due to truncation. Ask the model to output the complete file.",
-                style("⚠").yellow()
+                "{} {} operation(s) undone",
+                style("✓").green(),
+                succeeded.len()
             );
-            return Ok(());
-        }
-
-        let approved = if self.config.ui.auto_apply_file_ops {
-            // Auto-apply mode: show what we're doing and apply all safe operations
-            println!();
+        } else {
             println!(
-                "{} Auto-applying {} file operation(s):",
-                style("→").cyan(),
-                safe_indices.len()
+                "{} {} undone, {} failed",
+                style("⚠").yellow(),
+                succeeded.len(),
+                failed
             );
-            for &i in &safe_indices {
-                let op = &operations[i];
-                println!("  {} {}", style("•").dim(), op.path().display());
-            }
-            safe_indices
-        } else {
-            // Interactive mode: ask for confirmation
-            let ui = FileOperationUI::new();
-            ui.confirm_operations(&mut operations, &self.project_root)?
-        };
+        }
 
-        if !approved.is_empty() {
-            let (success, failed) = execute_operations(&operations, &approved, &self.project_root)?;
-            println!();
-            if failed == 0 {
-                println!(
-                    "{} {} operation(s) applied successfully",
-                    style("✓").green(),
-                    success
-                );
-            } else {
-                println!(
-                    "{} {} succeeded, {} failed",
-                    style("⚠").yellow(),
-                    success,
-                    failed
-                );
+        let reverted: Vec<FileOperation> =
+            succeeded.iter().map(|&i| rollbacks[i].clone()).collect();
+        self.track_renames(&reverted);
+        Ok(())
+    }
+
+    /// Keep renamed files tracked in context under their new path
+    fn track_renames(&mut self, operations: &[FileOperation]) {
+        for op in operations {
+            if let FileOperation::Rename { from, to } = op {
+                let from = self.project_root.join(from);
+                let to = self.project_root.join(to);
+                if let Some(new_key) = self.context.rename_file(&from, &to) {
+                    println!(
+                        "{} Context now tracks {}",
+                        style("→").cyan(),
+                        style(new_key.display()).cyan()
+                    );
+                }
             }
-        } else if !operations.is_empty() {
-            println!("{}", style("No operations applied.").dim());
         }
-
-        println!();
-        Ok(())
     }
 
     fn process_exec_operations(&mut self, response: &str) -> Result<bool> {
@@ -2365,18 +3977,30 @@ impl<B: LlmBackend> Repl<B> {
         println!();
         for (i, cmd) in commands.iter().enumerate() {
             let preview = cmd.lines().next().unwrap_or(cmd).trim();
-            let display = if preview.len() > 60 {
-                format!("{}...", &preview[..57])
+            let display = truncate_str(preview, 60);
+            if is_risky_command(cmd) {
+                println!(
+                    "  {} {} {}",
+                    style(format!("[{}]", i + 1)).dim(),
+                    style(display).red(),
+                    style("⚠ risky").red().bold()
+                );
             } else {
-                preview.to_string()
-            };
+                println!(
+                    "  {} {}",
+                    style(format!("[{}]", i + 1)).dim(),
+                    style(display).cyan()
+                );
+            }
+        }
+        println!();
+        if commands.iter().any(|c| is_risky_command(c)) {
             println!(
-                "  {} {}",
-                style(format!("[{}]", i + 1)).dim(),
-                style(display).cyan()
+                "{}",
+                style("Commands marked ⚠ will also ask for an explicit 'yes' before running.")
+                    .yellow()
             );
         }
-        println!();
         print!(
             "{} ",
             style("[r]un all, [s]kip all, or enter numbers to run (e.g. 1 3):").dim()
@@ -2413,61 +4037,633 @@ impl<B: LlmBackend> Repl<B> {
             return Ok(false);
         }
 
+        let mut ran = 0;
         for &idx in &to_run {
-            let cmd_line = &commands[idx];
-            #[cfg(unix)]
-            let output = Command::new("sh").arg("-c").arg(cmd_line).output();
-            #[cfg(windows)]
-            let output = Command::new("cmd").args(["/C", cmd_line]).output();
-
-            match output {
-                Ok(o) => {
-                    if !o.stdout.is_empty() {
-                        print!("{}", String::from_utf8_lossy(&o.stdout));
-                    }
-                    if !o.stderr.is_empty() {
-                        eprint!("{}", String::from_utf8_lossy(&o.stderr));
-                    }
-                    io::stdout().flush().ok();
-                    if !o.status.success() {
-                        if let Some(code) = o.status.code() {
-                            println!("{} {}", style("Exit code:").dim(), code);
-                        }
-                    }
-                    let stdout = String::from_utf8_lossy(&o.stdout);
-                    let stderr = String::from_utf8_lossy(&o.stderr);
-                    let code = o
-                        .status
-                        .code()
-                        .map(|c| c.to_string())
-                        .unwrap_or_else(|| "—".into());
-                    let mut ctx_msg = format!("[Ran shell command]\n$ {cmd_line}\n\n");
-                    if !stdout.is_empty() {
-                        ctx_msg.push_str("stdout:\n");
-                        ctx_msg.push_str(&stdout);
-                        if !stdout.ends_with('\n') {
-                            ctx_msg.push('\n');
-                        }
-                    }
-                    if !stderr.is_empty() {
-                        ctx_msg.push_str("stderr:\n");
-                        ctx_msg.push_str(&stderr);
-                        if !stderr.ends_with('\n') {
-                            ctx_msg.push('\n');
-                        }
-                    }
-                    ctx_msg.push_str(&format!("exit code: {code}\n"));
-                    self.context.add_message(Message::user(&ctx_msg));
+            if is_risky_command(&commands[idx]) && !confirm_risky_command(&commands[idx])? {
+                continue;
+            }
+            self.run_exec_command(&commands[idx]);
+            ran += 1;
+        }
+        println!();
+        Ok(ran > 0)
+    }
+
+    /// Print close matches for an unknown model name from the cached model list
+    fn print_model_suggestions(&self, name: &str) {
+        let Some(models) = &self.cached_models else {
+            return;
+        };
+        let suggestions = suggest_similar(name, models, 3);
+        if !suggestions.is_empty() {
+            println!(
+                "  {} {}",
+                style("Did you mean:").dim(),
+                style(suggestions.join(", ")).cyan()
+            );
+        }
+    }
+
+    /// Exec commands parsed from the most recent assistant message
+    fn replay_commands(&self) -> Vec<String> {
+        self.context
+            .last_assistant_message()
+            .map(parse_exec_operations)
+            .unwrap_or_default()
+    }
+
+    /// Run one shell command, echo its output, and add command + output to context
+    fn run_exec_command(&mut self, cmd_line: &str) {
+        let output = shell_command(cmd_line).output();
+        match &output {
+            Ok(o) => print_command_output(o),
+            Err(e) => println!("{} {}", style("Exec failed:").red(), e),
+        }
+        let ctx_msg = shell_context_message(
+            "shell command",
+            cmd_line,
+            &output,
+            self.config.ui.exec_output_max_lines,
+        );
+        self.context.add_message(Message::user(&ctx_msg));
+    }
+
+    /// Add a `[hooks]` command's output to context so the model sees e.g. formatter
+    /// errors. It is left untyped so /edit, /retry and /resend-with skip over it.
+    fn add_hook_output(&mut self, run: &HookRun) {
+        let ctx_msg = shell_context_message(
+            &format!("{} hook", run.name),
+            &run.command,
+            &run.output,
+            self.config.ui.exec_output_max_lines,
+        );
+        self.context.add_message(Message::user(&ctx_msg));
+    }
+}
+
+/// Whether a user message is output recorded by `shell_context_message` rather
+/// than something the user typed
+fn is_command_output(content: &str) -> bool {
+    let first_line = content.lines().next().unwrap_or_default();
+    first_line.starts_with("[Ran ")
+        || (first_line.starts_with('[') && first_line.ends_with(" failed]"))
+}
+
+/// Context message recording a shell command and its output. The terminal got the
+/// full output; only a head/tail slice of `max_lines` goes to context.
+fn shell_context_message(
+    label: &str,
+    cmd_line: &str,
+    output: &io::Result<std::process::Output>,
+    max_lines: usize,
+) -> String {
+    let o = match output {
+        Ok(o) => o,
+        Err(e) => {
+            let mut label = label.to_string();
+            label[..1].make_ascii_uppercase();
+            return format!("[{label} failed]\n$ {cmd_line}\n\nerror: {e}\n");
+        }
+    };
+    let stdout = String::from_utf8_lossy(&o.stdout);
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    let code = o
+        .status
+        .code()
+        .map(|c| c.to_string())
+        .unwrap_or_else(|| "—".into());
+    let stdout = truncate_middle_lines(&stdout, max_lines);
+    let stderr = truncate_middle_lines(&stderr, max_lines);
+    let mut ctx_msg = format!("[Ran {label}]\n$ {cmd_line}\n\n");
+    if !stdout.is_empty() {
+        ctx_msg.push_str("stdout:\n");
+        ctx_msg.push_str(&stdout);
+        if !stdout.ends_with('\n') {
+            ctx_msg.push('\n');
+        }
+    }
+    if !stderr.is_empty() {
+        ctx_msg.push_str("stderr:\n");
+        ctx_msg.push_str(&stderr);
+        if !stderr.ends_with('\n') {
+            ctx_msg.push('\n');
+        }
+    }
+    ctx_msg.push_str(&format!("exit code: {code}\n"));
+    ctx_msg
+}
+
+/// Template commands and `[commands]` shortcuts, with descriptions, for completion
+fn template_completions(templates: &TemplateManager, config: &Config) -> Vec<(String, String)> {
+    let mut commands: Vec<(String, String)> = templates
+        .list()
+        .iter()
+        .map(|t| (t.command.clone(), t.description.clone()))
+        .collect();
+    let mut shortcuts: Vec<_> = config
+        .commands
+        .iter()
+        .filter(|(name, _)| !is_builtin_command(name))
+        .collect();
+    shortcuts.sort();
+    commands.extend(
+        shortcuts
+            .into_iter()
+            .map(|(name, expansion)| (name.clone(), format!("→ {}", expansion))),
+    );
+    commands
+}
+
+/// Aliases `handle_command` dispatches that no `HELP_COMMANDS` usage spells out
+const COMMAND_ALIASES: &[&str] = &["quit", "q", "rm"];
+
+/// True when `name` (without the leading `/`) is a built-in REPL command
+fn is_builtin_command(name: &str) -> bool {
+    COMMAND_ALIASES.contains(&name)
+        || HELP_COMMANDS.iter().any(|(_, usage, _)| {
+            usage
+                .split_whitespace()
+                .next()
+                .and_then(|cmd| cmd.strip_prefix('/'))
+                == Some(name)
+        })
+}
+
+/// True when the input ends in a `\` continuation marker right at the cursor
+fn continues_line(input: &str, cursor_pos: usize) -> bool {
+    cursor_pos == input.len() && input.ends_with('\\')
+}
+
+/// Token totals for `/cost`: user messages count as input, assistant replies as output
+#[derive(Debug, Default, PartialEq)]
+struct SessionUsage {
+    input_tokens: usize,
+    output_tokens: usize,
+    input_messages: usize,
+    output_messages: usize,
+}
+
+impl SessionUsage {
+    fn from_messages(messages: &[Message]) -> Self {
+        let mut usage = Self::default();
+        for message in messages {
+            let tokens = crate::context::estimate_tokens(&message.content);
+            match message.role.as_str() {
+                "user" => {
+                    usage.input_tokens += tokens;
+                    usage.input_messages += 1;
                 }
-                Err(e) => {
-                    println!("{} {}", style("Exec failed:").red(), e);
-                    let ctx_msg = format!("[Shell command failed]\n$ {cmd_line}\n\nerror: {e}\n");
-                    self.context.add_message(Message::user(&ctx_msg));
+                "assistant" => {
+                    usage.output_tokens += tokens;
+                    usage.output_messages += 1;
                 }
+                _ => {}
             }
         }
-        println!();
+        usage
+    }
+
+    fn total(&self) -> usize {
+        self.input_tokens + self.output_tokens
+    }
+
+    fn average(&self) -> usize {
+        self.total()
+            .checked_div(self.input_messages + self.output_messages)
+            .unwrap_or(0)
+    }
+
+    /// Price estimate from per-1k token rates; None when neither rate is set
+    fn cost(&self, prompt_per_1k: Option<f64>, completion_per_1k: Option<f64>) -> Option<f64> {
+        if prompt_per_1k.is_none() && completion_per_1k.is_none() {
+            return None;
+        }
+        let input = self.input_tokens as f64 / 1000.0 * prompt_per_1k.unwrap_or(0.0);
+        let output = self.output_tokens as f64 / 1000.0 * completion_per_1k.unwrap_or(0.0);
+        Some(input + output)
+    }
+}
+
+/// Fill `{name}` placeholders in a `ui.prompt_format` string in a single
+/// pass, so substituted values are never expanded again; unknown
+/// placeholders are left as written
+fn render_prompt_format(format: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('}').and_then(|close| {
+            let name = &after[..close];
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                out.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Keep the first and last lines of `text` when it has more than `max_lines`,
+/// replacing the middle with an elision marker. `0` disables truncation.
+fn truncate_middle_lines(text: &str, max_lines: usize) -> std::borrow::Cow<'_, str> {
+    let lines: Vec<&str> = text.lines().collect();
+    if max_lines == 0 || lines.len() <= max_lines {
+        return std::borrow::Cow::Borrowed(text);
+    }
+
+    let head = max_lines.div_ceil(2);
+    let tail = max_lines - head;
+    let mut out = lines[..head].join("\n");
+    out.push_str(&format!(
+        "\n... [{} lines omitted] ...\n",
+        lines.len() - max_lines
+    ));
+    for line in &lines[lines.len() - tail..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    std::borrow::Cow::Owned(out)
+}
+
+/// How close the context is to its token budget, used to color the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BudgetLevel {
+    Normal,
+    /// 75% or more of the budget used
+    Warning,
+    /// 90% or more of the budget used
+    Critical,
+}
+
+impl BudgetLevel {
+    fn from_usage(used: usize, budget: usize) -> Self {
+        let pct = (used * 100).checked_div(budget).unwrap_or(0);
+        if pct >= 90 {
+            BudgetLevel::Critical
+        } else if pct >= 75 {
+            BudgetLevel::Warning
+        } else {
+            BudgetLevel::Normal
+        }
+    }
+}
+
+/// Section a built-in command is listed under in `/help`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HelpCategory {
+    Context,
+    Files,
+    Model,
+    Session,
+    Templates,
+    Misc,
+}
+
+impl HelpCategory {
+    const ALL: [HelpCategory; 6] = [
+        HelpCategory::Context,
+        HelpCategory::Files,
+        HelpCategory::Model,
+        HelpCategory::Session,
+        HelpCategory::Templates,
+        HelpCategory::Misc,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            HelpCategory::Context => "Context",
+            HelpCategory::Files => "Files",
+            HelpCategory::Model => "Model",
+            HelpCategory::Session => "Session",
+            HelpCategory::Templates => "Templates & Rules",
+            HelpCategory::Misc => "Misc",
+        }
+    }
+}
+
+/// Built-in commands shown by `/help`: (category, usage, description)
+const HELP_COMMANDS: &[(HelpCategory, &str, &str)] = &[
+    (
+        HelpCategory::Context,
+        "/context [export]",
+        "Show or export context",
+    ),
+    (HelpCategory::Context, "/tokens", "Show token usage"),
+    (
+        HelpCategory::Context,
+        "/cost",
+        "Estimate session token usage",
+    ),
+    (
+        HelpCategory::Context,
+        "/compact [n]",
+        "Summarize old messages",
+    ),
+    (
+        HelpCategory::Context,
+        "/clear [keep=N]",
+        "Clear conversation",
+    ),
+    (
+        HelpCategory::Context,
+        "/edit [message]",
+        "Edit and re-send last message",
+    ),
+    (
+        HelpCategory::Context,
+        "/retry [temp=<v>]",
+        "Regenerate last response",
+    ),
+    (HelpCategory::Files, "/files", "List files in context"),
+    (HelpCategory::Files, "/tree", "Show context files as a tree"),
+    (
+        HelpCategory::Files,
+        "/add <path> [...]",
+        "Add file/directory to context",
+    ),
+    (
+        HelpCategory::Files,
+        "/add-url <url> [...]",
+        "Fetch a URL into context",
+    ),
+    (
+        HelpCategory::Files,
+        "/remove <file> [...]",
+        "Remove file from context",
+    ),
+    (
+        HelpCategory::Files,
+        "/watch [on|off]",
+        "Toggle auto-refresh of files",
+    ),
+    (HelpCategory::Files, "/pwd", "Print working directory"),
+    (
+        HelpCategory::Files,
+        "/fileops [on|off]",
+        "Toggle file operations",
+    ),
+    (
+        HelpCategory::Files,
+        "/undo",
+        "Revert last applied file changes",
+    ),
+    (
+        HelpCategory::Files,
+        "/explain-diff",
+        "Explain last proposed changes",
+    ),
+    (
+        HelpCategory::Model,
+        "/model [list|pull] [name]",
+        "Pick, set or pull a model",
+    ),
+    (
+        HelpCategory::Model,
+        "/temp <v|reset>",
+        "Override temperature",
+    ),
+    (HelpCategory::Model, "/top_p <v|reset>", "Override top-p"),
+    (HelpCategory::Model, "/json [on|off]", "Force JSON output"),
+    (
+        HelpCategory::Model,
+        "/resend-with <model>",
+        "Ask another model once",
+    ),
+    (
+        HelpCategory::Session,
+        "/export [--append] [file]",
+        "Export chat to a text or markdown file",
+    ),
+    (
+        HelpCategory::Session,
+        "/mark <label>",
+        "Bookmark this point",
+    ),
+    (HelpCategory::Session, "/marks", "List bookmarks"),
+    (
+        HelpCategory::Session,
+        "/load <session>",
+        "Load a saved session",
+    ),
+    (
+        HelpCategory::Session,
+        "/history [search]",
+        "Show or search input history",
+    ),
+    (
+        HelpCategory::Templates,
+        "/templates [reload]",
+        "List or reload templates",
+    ),
+    (HelpCategory::Templates, "/rules", "Show loaded rules"),
+    (
+        HelpCategory::Templates,
+        "/rule enable|disable",
+        "Enable/disable a rule",
+    ),
+    (HelpCategory::Misc, "/help", "Show this help"),
+    (
+        HelpCategory::Misc,
+        "/theme [name|next]",
+        "Switch color theme",
+    ),
+    (HelpCategory::Misc, "/exit", "Exit the REPL"),
+    (HelpCategory::Misc, "/exec <command>", "Run a shell command"),
+    (HelpCategory::Misc, "/replay", "Re-run last exec blocks"),
+];
+
+/// File format for `/export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Text,
+    Markdown,
+}
+
+impl ExportFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "txt" | "text" => Some(ExportFormat::Text),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Render the conversation for `/export` (no ANSI codes — safe for tmux/pipes)
+fn format_export(messages: &[Message], model: &str, format: ExportFormat) -> String {
+    use std::fmt::Write as FmtWrite;
+
+    let date = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let mut out = String::new();
+    match format {
+        ExportFormat::Text => {
+            let _ = writeln!(out, "=== Slab Chat Export ===");
+            let _ = writeln!(out, "Date:     {}", date);
+            let _ = writeln!(out, "Model:    {}", model);
+            let _ = writeln!(out, "Messages: {}", messages.len());
+            let _ = writeln!(out, "{}", "=".repeat(40));
+
+            for (i, msg) in messages.iter().enumerate() {
+                let _ = writeln!(out);
+                let role = msg.role.to_uppercase();
+                let _ = writeln!(out, "[{}] {}", i + 1, role);
+                let _ = writeln!(out, "{}", "-".repeat(40));
+                let _ = writeln!(out, "{}", msg.content);
+            }
+
+            let _ = writeln!(out);
+            let _ = writeln!(out, "{}", "=".repeat(40));
+            let _ = writeln!(out, "End of export");
+        }
+        ExportFormat::Markdown => {
+            let _ = writeln!(out, "# Slab Chat Export");
+            let _ = writeln!(out);
+            let _ = writeln!(out, "- **Date:** {}", date);
+            let _ = writeln!(out, "- **Model:** {}", model);
+            let _ = writeln!(out, "- **Messages:** {}", messages.len());
+
+            for msg in messages {
+                let mut role = msg.role.clone();
+                if let Some(first) = role.get_mut(..1) {
+                    first.make_ascii_uppercase();
+                }
+                let _ = writeln!(out);
+                let _ = writeln!(out, "## {}", role);
+                let _ = writeln!(out);
+                let _ = writeln!(out, "{}", msg.content.trim_end());
+            }
+        }
+    }
+    out
+}
+
+/// Write an export, or append it after a separator when `append` is set and
+/// the file already has content
+fn write_export(
+    path: &std::path::Path,
+    content: &str,
+    append: bool,
+    format: ExportFormat,
+) -> std::io::Result<()> {
+    if !append {
+        return std::fs::write(path, content);
+    }
+
+    let has_content = std::fs::metadata(path).is_ok_and(|m| m.len() > 0);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    if has_content {
+        let separator = match format {
+            ExportFormat::Text => "\n",
+            ExportFormat::Markdown => "\n---\n\n",
+        };
+        file.write_all(separator.as_bytes())?;
+    }
+    file.write_all(content.as_bytes())
+}
+
+/// Append the answer parts of `segments` to `response` and return the text to
+/// print, with thinking dimmed
+fn split_think_display(segments: Vec<ThinkSegment>, response: &mut String) -> String {
+    let mut display = String::new();
+    for segment in segments {
+        match segment {
+            ThinkSegment::Answer(text) => {
+                response.push_str(&text);
+                display.push_str(&text);
+            }
+            ThinkSegment::Thinking(text) => {
+                display.push_str(&style(text).dim().to_string());
+            }
+        }
+    }
+    display
+}
+
+/// Counter shown while a stream has produced no visible content yet, e.g. "12 tokens · 48 chars · 3.4s"
+fn format_stream_progress(chunks: usize, chars: usize, elapsed: Duration) -> String {
+    format!(
+        "{} token{} · {} char{} · {:.1}s",
+        chunks,
+        if chunks == 1 { "" } else { "s" },
+        chars,
+        if chars == 1 { "" } else { "s" },
+        elapsed.as_secs_f64()
+    )
+}
+
+/// The note for `slab run` when the reply stopped at `--max-tokens` rather than ending
+fn max_tokens_note(max_tokens: Option<usize>, done_reason: Option<&str>) -> Option<String> {
+    let limit = max_tokens?;
+    (done_reason == Some("length"))
+        .then(|| format!("Output was truncated by --max-tokens {}.", limit))
+}
+
+/// Ask before `/load` discards a conversation that has messages. Without a
+/// terminal to answer on (e.g. `--script`), the load goes ahead.
+fn confirm_replace_conversation(messages: usize, session: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    print!(
+        "{} Loading '{}' discards the current {} message(s). Continue? [y/N]: ",
+        style("⚠").yellow(),
+        session,
+        messages
+    );
+    io::stdout().flush().ok();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if matches!(input.trim(), "y" | "Y" | "yes") {
+        Ok(true)
+    } else {
+        println!("{}", style("Not loaded.").dim());
+        Ok(false)
+    }
+}
+
+/// Ask before running a command flagged by `is_risky_command`; only a typed "yes" confirms
+fn confirm_risky_command(cmd: &str) -> Result<bool> {
+    println!();
+    println!(
+        "{} {}",
+        style("⚠ Risky command:").red().bold(),
+        style(cmd).red()
+    );
+    print!(
+        "{} ",
+        style("Type 'yes' to run it, anything else skips:").yellow()
+    );
+    io::stdout().flush().ok();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim() == "yes" {
         Ok(true)
+    } else {
+        println!("{}", style("Skipped.").dim());
+        Ok(false)
     }
 }
 
@@ -2506,7 +4702,76 @@ fn get_template_directories(project_root: &std::path::Path) -> Vec<PathBuf> {
     dirs
 }
 
+fn get_rules_directories(project_root: &std::path::Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    // Project-local rules (shadow global rules with the same name)
+    dirs.push(project_root.join(".slab/rules"));
+
+    // Global rules
+    if let Some(config_dir) = dirs_next::config_dir() {
+        dirs.push(config_dir.join("slab/rules"));
+    }
+
+    dirs
+}
+
+/// Menu entries for the `/model` picker: each name with its size, marking the current model
+fn model_menu_items(
+    names: &[String],
+    sizes: &HashMap<String, String>,
+    current: &str,
+) -> Vec<(String, Option<String>, CompletionKind)> {
+    names
+        .iter()
+        .map(|name| {
+            let size = sizes.get(name).cloned();
+            let desc = if name == current {
+                Some(size.map_or("current".to_string(), |s| format!("{} · current", s)))
+            } else {
+                size
+            };
+            (name.clone(), desc, CompletionKind::Model)
+        })
+        .collect()
+}
+
+/// First visible row of a scrolling menu after moving the selection, keeping
+/// `selected` inside a window of `window` rows starting at `offset`
+fn menu_scroll_offset(selected: usize, offset: usize, window: usize) -> usize {
+    if selected < offset {
+        selected
+    } else if selected >= offset + window {
+        selected + 1 - window
+    } else {
+        offset
+    }
+}
+
+/// How `slab run` uses piped stdin
+#[derive(Debug, Clone, Copy)]
+pub enum StdinInput<'a> {
+    /// Added to context as a pseudo-file named "stdin"
+    Context(&'a str),
+    /// Sent as the prompt, or as the `content` variable when a template is used
+    Prompt(&'a str),
+}
+
+impl<'a> StdinInput<'a> {
+    /// Piped input is context for an explicit prompt, except with a template,
+    /// where it always fills the `content` variable and the prompt supplies
+    /// the other variables
+    pub fn piped(content: &'a str, has_prompt: bool, templated: bool) -> Self {
+        if has_prompt && !templated {
+            StdinInput::Context(content)
+        } else {
+            StdinInput::Prompt(content)
+        }
+    }
+}
+
 /// Run a single prompt (non-interactive)
+#[allow(clippy::too_many_arguments)]
 pub async fn run_single_prompt<B: LlmBackend>(
     client: &B,
     config: &Config,
@@ -2514,14 +4779,17 @@ pub async fn run_single_prompt<B: LlmBackend>(
     prompt: &str,
     streaming: bool,
     files: &[PathBuf],
+    stdin: Option<StdinInput<'_>>,
     template_name: Option<&str>,
+    json: bool,
+    max_tokens: Option<usize>,
 ) -> Result<()> {
     let model_config = config.get_model_config(model);
     let project_root = find_project_root()
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
     // Create a ContextManager to handle files and @references
-    let mut context = ContextManager::new(config.context_limit, project_root.clone());
+    let mut context = ContextManager::new(config.effective_context_limit(), project_root.clone());
 
     if let Some(system_prompt) = &model_config.system_prompt {
         context.set_system_prompt(system_prompt.clone());
@@ -2560,6 +4828,16 @@ pub async fn run_single_prompt<B: LlmBackend>(
         }
     }
 
+    let mut piped_prompt = None;
+    match stdin {
+        Some(StdinInput::Context(content)) => match context.add_stdin(content) {
+            Ok(()) => eprintln!("{} Added stdin to context", style("✓").green()),
+            Err(e) => eprintln!("{} {}", style("Error:").red(), e),
+        },
+        Some(StdinInput::Prompt(content)) => piped_prompt = Some(content),
+        None => {}
+    }
+
     // Resolve the actual prompt: either render a template or use as-is
     let actual_prompt = if let Some(tpl_name) = template_name {
         // Load templates
@@ -2586,7 +4864,9 @@ pub async fn run_single_prompt<B: LlmBackend>(
             }
         }
 
-        if !content_parts.is_empty() {
+        if let Some(content) = piped_prompt {
+            variables.insert("content".to_string(), content.to_string());
+        } else if !content_parts.is_empty() {
             variables.insert("content".to_string(), content_parts.join(" "));
         }
 
@@ -2605,11 +4885,12 @@ pub async fn run_single_prompt<B: LlmBackend>(
         rendered
     } else {
         // Expand @file references in the prompt
-        context.expand_file_references(prompt)
+        context.expand_file_references(piped_prompt.unwrap_or(prompt))
     };
 
     // Add user message and build the full message list
     context.add_message(Message::user(&actual_prompt));
+    context.ensure_within_budget(config.auto_prune)?;
     let messages = context.build_messages();
 
     let request = ChatRequest {
@@ -2619,18 +4900,26 @@ pub async fn run_single_prompt<B: LlmBackend>(
         options: Some(ModelOptions {
             temperature: Some(model_config.temperature),
             top_p: Some(model_config.top_p),
-            num_ctx: Some(config.context_limit),
+            num_ctx: Some(config.effective_context_limit()),
+            seed: None,
+            num_predict: max_tokens,
+            stop: Vec::new(),
         }),
+        format: json.then(|| "json".to_string()),
     };
 
-    let response = if streaming {
+    let (response, done_reason) = if streaming {
         let mut rx = client.llm_stream(request).await?;
         let mut full_response = String::new();
+        let mut think = config.ui.strip_think_blocks.then(ThinkFilter::default);
         while let Some(result) = rx.recv().await {
             match result {
                 Ok(chunk) => {
-                    full_response.push_str(&chunk);
-                    print!("{}", chunk);
+                    let segments = match think.as_mut() {
+                        Some(filter) => filter.push(&chunk),
+                        None => vec![ThinkSegment::Answer(chunk)],
+                    };
+                    print!("{}", split_think_display(segments, &mut full_response));
                     io::stdout().flush().ok();
                 }
                 Err(e) => {
@@ -2639,8 +4928,15 @@ pub async fn run_single_prompt<B: LlmBackend>(
                 }
             }
         }
+        if let Some(filter) = think.as_mut() {
+            print!(
+                "{}",
+                split_think_display(filter.finish(), &mut full_response)
+            );
+            full_response = full_response.trim_start().to_string();
+        }
         println!();
-        full_response
+        (full_response, rx.done_reason().map(str::to_string))
     } else {
         let spinner = ProgressBar::new_spinner();
         spinner.set_style(
@@ -2652,37 +4948,62 @@ pub async fn run_single_prompt<B: LlmBackend>(
         spinner.set_message("Thinking...");
         spinner.enable_steady_tick(Duration::from_millis(80));
 
-        let response = client.llm_chat(request).await?;
+        let reply = client.llm_chat(request).await?;
         spinner.finish_and_clear();
+        let mut response = reply.content;
+        if config.ui.strip_think_blocks {
+            response = strip_think_blocks(&response);
+        }
 
         println!("{}", response);
-        response
+        (response, reply.done_reason)
     };
 
+    if let Some(note) = max_tokens_note(max_tokens, done_reason.as_deref()) {
+        eprintln!("{}", style(note).dim());
+    }
+
     // Process file operations for single prompt mode too
-    let mut operations = parse_file_operations(&response, &project_root);
+    let mut operations = filter_allowed_operations(
+        parse_file_operations(&response, &project_root),
+        &config.ui.allowed_file_ops,
+    );
 
     if !operations.is_empty() {
         let ui = FileOperationUI::new();
         let approved = ui.confirm_operations(&mut operations, &project_root)?;
 
-        if !approved.is_empty() {
-            let (success, failed) = execute_operations(&operations, &approved, &project_root)?;
+        let mut hooks = config.hooks.clone();
+        let vetoed = !approved.is_empty()
+            && run_pre_apply_hook(&mut hooks, &operations, &approved, &project_root)
+                .is_some_and(|run| !run.succeeded());
+        if vetoed {
+            println!(
+                "{} pre_apply hook failed; no operations applied",
+                style("⚠").yellow()
+            );
+        } else if !approved.is_empty() {
+            let (succeeded, failed) = execute_operations(&operations, &approved, &project_root)?;
             println!();
             if failed == 0 {
                 println!(
                     "{} {} operation(s) applied successfully",
                     style("✓").green(),
-                    success
+                    succeeded.len()
                 );
             } else {
                 println!(
                     "{} {} succeeded, {} failed",
                     style("⚠").yellow(),
-                    success,
+                    succeeded.len(),
                     failed
                 );
             }
+            run_post_apply_hook(
+                &mut hooks,
+                succeeded.iter().map(|&i| &operations[i]),
+                &project_root,
+            );
         }
     }
 
@@ -2693,7 +5014,9 @@ pub async fn run_single_prompt<B: LlmBackend>(
 mod tests {
     use super::*;
     use crate::config::Config;
-    use crate::ollama::{ChatRequest, LlmBackend, ModelInfo};
+    use crate::ollama::{
+        ChatReply, ChatRequest, ChatStream, LlmBackend, ModelInfo, PullStatus, StreamEvent,
+    };
     use crate::templates::{PhaseFeedback, PhaseOutcome, TemplatePhase};
     use std::path::PathBuf;
     use std::sync::{Arc, Mutex};
@@ -2707,6 +5030,14 @@ mod tests {
         response: String,
         /// The last-user-message content from each `llm_chat` call, in order.
         sent: Arc<Mutex<Vec<String>>>,
+        /// Model names passed to `llm_pull_model`, in order.
+        pulled: Mutex<Vec<String>>,
+        /// Every request passed to `llm_chat`, in order.
+        requests: Mutex<Vec<ChatRequest>>,
+        /// Native context window reported by `llm_context_length`.
+        context_length: Option<usize>,
+        /// Returned as the done reason of every reply.
+        done_reason: Option<String>,
     }
 
     impl MockLlmBackend {
@@ -2715,28 +5046,34 @@ mod tests {
             let backend = Self {
                 response: response.into(),
                 sent: Arc::clone(&sent),
+                pulled: Mutex::new(Vec::new()),
+                requests: Mutex::new(Vec::new()),
+                context_length: None,
+                done_reason: None,
             };
             (backend, sent)
         }
     }
 
     impl LlmBackend for MockLlmBackend {
-        async fn llm_chat(&self, request: ChatRequest) -> crate::error::Result<String> {
+        async fn llm_chat(&self, request: ChatRequest) -> crate::error::Result<ChatReply> {
             // Record the last user message so tests can inspect what was sent.
             if let Some(last) = request.messages.iter().rev().find(|m| m.role == "user") {
                 self.sent.lock().unwrap().push(last.content.clone());
             }
-            Ok(self.response.clone())
+            self.requests.lock().unwrap().push(request);
+            Ok(ChatReply {
+                content: self.response.clone(),
+                done_reason: self.done_reason.clone(),
+            })
         }
 
-        async fn llm_stream(
-            &self,
-            request: ChatRequest,
-        ) -> crate::error::Result<mpsc::Receiver<crate::error::Result<String>>> {
-            let text = self.llm_chat(request).await?;
-            let (tx, rx) = mpsc::channel(1);
+        async fn llm_stream(&self, request: ChatRequest) -> crate::error::Result<ChatStream> {
+            let reply = self.llm_chat(request).await?;
+            let (tx, rx) = ChatStream::channel(2);
             tokio::spawn(async move {
-                let _ = tx.send(Ok(text)).await;
+                let _ = tx.send(Ok(StreamEvent::Chunk(reply.content))).await;
+                let _ = tx.send(Ok(StreamEvent::Done(reply.done_reason))).await;
             });
             Ok(rx)
         }
@@ -2744,6 +5081,23 @@ mod tests {
         async fn llm_list_models(&self) -> crate::error::Result<Vec<ModelInfo>> {
             Ok(vec![])
         }
+
+        async fn llm_context_length(&self, _model: &str) -> crate::error::Result<Option<usize>> {
+            Ok(self.context_length)
+        }
+
+        async fn llm_pull_model(
+            &self,
+            name: &str,
+        ) -> crate::error::Result<mpsc::Receiver<crate::error::Result<PullStatus>>> {
+            self.pulled.lock().unwrap().push(name.to_string());
+            let (tx, rx) = mpsc::channel(1);
+            let done: PullStatus = serde_json::from_str(r#"{"status":"success"}"#).unwrap();
+            tokio::spawn(async move {
+                let _ = tx.send(Ok(done)).await;
+            });
+            Ok(rx)
+        }
     }
 
     // ── Helper ────────────────────────────────────────────────────────────────
@@ -2760,6 +5114,8 @@ mod tests {
             on_failure,
             feedback: PhaseFeedback::OnFailure,
             follow_up: None,
+            cwd: None,
+            env: HashMap::new(),
         }
     }
 
@@ -2835,6 +5191,36 @@ phases:
         );
     }
 
+    #[test]
+    fn test_phase_cwd_and_env_deserialize() {
+        let yaml = r#"
+name: test
+command: /test
+description: test
+prompt: hello
+phases:
+  - name: build
+    run: "cargo build"
+    cwd: "crates/core"
+    env:
+      CARGO_TERM_COLOR: always
+  - name: plain
+    run: "true"
+"#;
+        let tpl: crate::templates::PromptTemplate =
+            serde_yaml::from_str(yaml).expect("parse failed");
+        assert_eq!(tpl.phases[0].cwd.as_deref(), Some("crates/core"));
+        assert_eq!(
+            tpl.phases[0]
+                .env
+                .get("CARGO_TERM_COLOR")
+                .map(String::as_str),
+            Some("always")
+        );
+        assert!(tpl.phases[1].cwd.is_none());
+        assert!(tpl.phases[1].env.is_empty());
+    }
+
     // ── run_phase_loop: Bug 1 — single message per pass ───────────────────────
 
     #[tokio::test]
@@ -2870,6 +5256,8 @@ phases:
             on_failure: PhaseOutcome::Continue,
             feedback: PhaseFeedback::OnFailure,
             follow_up: Some("My phase follow-up.".into()),
+            cwd: None,
+            env: HashMap::new(),
         }];
         repl.run_phase_loop(&phases, 1, None, |_| true)
             .await
@@ -2888,6 +5276,39 @@ phases:
         );
     }
 
+    #[tokio::test]
+    async fn test_phase_runs_in_cwd_with_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = dir.path().canonicalize().unwrap();
+        let (backend, sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+
+        let mut env = HashMap::new();
+        env.insert("SLAB_PHASE_VAR".to_string(), "phase-env-value".to_string());
+        let phases = vec![TemplatePhase {
+            name: Some("where".into()),
+            run: "pwd; echo $SLAB_PHASE_VAR".to_string(),
+            on_success: PhaseOutcome::Continue,
+            on_failure: PhaseOutcome::Continue,
+            feedback: PhaseFeedback::Always,
+            follow_up: None,
+            cwd: Some(dir_path.display().to_string()),
+            env,
+        }];
+        repl.run_phase_loop(&phases, 1, None, |_| true)
+            .await
+            .unwrap();
+
+        let calls = sent.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert!(
+            calls[0].contains(&dir_path.display().to_string()),
+            "phase did not run in cwd: {}",
+            calls[0]
+        );
+        assert!(calls[0].contains("phase-env-value"));
+    }
+
     // ── run_phase_loop: Bug 2 — exec error respects on_failure ────────────────
 
     #[tokio::test]
@@ -2905,6 +5326,8 @@ phases:
             on_failure: PhaseOutcome::Stop, // <-- Stop, not Continue
             feedback: PhaseFeedback::OnFailure,
             follow_up: None,
+            cwd: None,
+            env: HashMap::new(),
         }];
         repl.run_phase_loop(&phases, 10, None, |_| false)
             .await
@@ -2931,6 +5354,8 @@ phases:
             on_failure: PhaseOutcome::Continue,
             feedback: PhaseFeedback::OnFailure,
             follow_up: None,
+            cwd: None,
+            env: HashMap::new(),
         }];
         // confirm=true + max_iterations=1 → message fires on pass 1, loop exits on pass 2.
         repl.run_phase_loop(&phases, 1, None, |_| true)
@@ -2989,6 +5414,8 @@ phases:
             on_failure: PhaseOutcome::Continue,
             feedback: PhaseFeedback::Always,
             follow_up: None,
+            cwd: None,
+            env: HashMap::new(),
         }];
         repl.run_phase_loop(&phases, 1, None, |_| true)
             .await
@@ -3015,6 +5442,8 @@ phases:
             on_failure: PhaseOutcome::Continue,
             feedback: PhaseFeedback::OnFailure,
             follow_up: None,
+            cwd: None,
+            env: HashMap::new(),
         }];
         repl.run_phase_loop(&phases, 1, None, |_| true)
             .await
@@ -3041,6 +5470,8 @@ phases:
             on_failure: PhaseOutcome::Continue,
             feedback: PhaseFeedback::Never,
             follow_up: None,
+            cwd: None,
+            env: HashMap::new(),
         }];
         repl.run_phase_loop(&phases, 1, None, |_| true)
             .await
@@ -3069,6 +5500,8 @@ phases:
             on_failure: PhaseOutcome::Continue,
             feedback: PhaseFeedback::OnFailure,
             follow_up: Some("PER_PHASE_FOLLOW_UP".into()),
+            cwd: None,
+            env: HashMap::new(),
         }];
         repl.run_phase_loop(&phases, 1, Some("TEMPLATE_FOLLOW_UP"), |_| true)
             .await
@@ -3145,4 +5578,1331 @@ phases:
         let calls = sent.lock().unwrap();
         assert_eq!(calls.len(), 3, "loop must stop after max_iterations passes");
     }
+
+    // ── /readonly ─────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_readonly_applies_no_file_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn old() {}\n").unwrap();
+        let (backend, sent) = MockLlmBackend::new(
+            "```rust:lib.rs\nfn new() {}\n```\n\n```rust:added.rs\nfn added() {}\n```\n\n```exec\ntouch ran.txt\n```",
+        );
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        repl.config.ui.auto_apply_file_ops = true;
+        repl.set_readonly(true);
+
+        repl.send_message("rewrite lib.rs").await.unwrap();
+        repl.handle_command("/fileops on").await.unwrap();
+        assert!(!repl.file_ops_enabled);
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+            "fn old() {}\n"
+        );
+        assert!(!dir.path().join("added.rs").exists());
+        assert!(!dir.path().join("ran.txt").exists());
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_readonly_skips_template_phases_and_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (backend, sent) = MockLlmBackend::new("looks fine");
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        repl.set_readonly(true);
+
+        let mut touch = phase("touch ran.txt", PhaseOutcome::Stop, PhaseOutcome::Stop);
+        touch.cwd = Some(dir.path().display().to_string());
+        repl.run_phase_loop(&[touch], 1, None, |_| true)
+            .await
+            .unwrap();
+        assert!(!dir.path().join("ran.txt").exists());
+        assert!(sent.lock().unwrap().is_empty());
+
+        let out = dir.path().join("review.md");
+        let out = out.display().to_string();
+        repl.handle_template_command("review", &["-o", &out])
+            .await
+            .unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(!dir.path().join("review.md").exists());
+    }
+
+    // ── /theme ────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_theme_next_cycles_and_updates_config() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.config.ui.show_status_bar = false;
+        repl.config.ui.theme = "dracula".into();
+
+        repl.handle_command("/theme next").await.unwrap();
+        assert_eq!(repl.config.ui.theme, "default");
+        repl.handle_command("/theme nord").await.unwrap();
+        assert_eq!(repl.config.ui.theme, "nord");
+        repl.handle_command("/theme neon").await.unwrap();
+        assert_eq!(repl.config.ui.theme, "nord");
+    }
+
+    #[tokio::test]
+    async fn test_theme_next_cycles_through_configured_custom_theme() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.config.ui.show_status_bar = false;
+        repl.config.ui.custom_theme.accent = Some("magenta".into());
+        repl.config.ui.theme = "dracula".into();
+
+        repl.handle_command("/theme next").await.unwrap();
+        assert_eq!(repl.config.ui.theme, "custom");
+        repl.handle_command("/theme next").await.unwrap();
+        assert_eq!(repl.config.ui.theme, "default");
+    }
+
+    // ── run_single_prompt stdin ───────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_run_single_prompt_stdin_as_prompt_or_template_content() {
+        let (backend, sent) = MockLlmBackend::new("ok");
+        let config = Config::default();
+        let piped = "thread 'main' panicked at src/lib.rs:3:5\nkey=value stays text";
+        run_single_prompt(
+            &backend,
+            &config,
+            "test-model",
+            "",
+            false,
+            &[],
+            Some(StdinInput::Prompt(piped)),
+            None,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        run_single_prompt(
+            &backend,
+            &config,
+            "test-model",
+            "",
+            false,
+            &[],
+            Some(StdinInput::Prompt(piped)),
+            Some("doc"),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // With a template, a prompt of variables does not turn stdin into context
+        let input = StdinInput::piped(piped, true, true);
+        assert!(matches!(input, StdinInput::Prompt(_)));
+        run_single_prompt(
+            &backend,
+            &config,
+            "test-model",
+            "lang=rust",
+            false,
+            &[],
+            Some(input),
+            Some("doc"),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            StdinInput::piped(piped, true, false),
+            StdinInput::Context(_)
+        ));
+        assert!(matches!(
+            StdinInput::piped(piped, false, false),
+            StdinInput::Prompt(_)
+        ));
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent[0], piped);
+        for rendered in &sent[1..] {
+            assert!(
+                rendered.contains("panicked at src/lib.rs:3:5"),
+                "{}",
+                rendered
+            );
+        }
+    }
+
+    // ── max tokens and done reason ────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_run_single_prompt_max_tokens_sets_num_predict() {
+        let (backend, _sent) = MockLlmBackend::new("short answer");
+        let config = Config::default();
+        run_single_prompt(
+            &backend,
+            &config,
+            "test-model",
+            "hi",
+            false,
+            &[],
+            None,
+            None,
+            false,
+            Some(64),
+        )
+        .await
+        .unwrap();
+
+        let requests = backend.requests.lock().unwrap();
+        let options = requests[0].options.as_ref().unwrap();
+        assert_eq!(options.num_predict, Some(64));
+    }
+
+    #[tokio::test]
+    async fn test_done_reason_comes_back_with_the_reply() {
+        let (mut backend, _sent) = MockLlmBackend::new("The answer is");
+        backend.done_reason = Some("length".into());
+        let request = || ChatRequest {
+            model: "test-model".into(),
+            messages: vec![Message::user("hi")],
+            stream: None,
+            options: None,
+            format: None,
+        };
+        let reply = backend.llm_chat(request()).await.unwrap();
+        assert_eq!(reply.done_reason.as_deref(), Some("length"));
+
+        let mut stream = backend.llm_stream(request()).await.unwrap();
+        assert_eq!(stream.done_reason(), None);
+        assert_eq!(stream.recv().await.unwrap().unwrap(), "The answer is");
+        assert!(stream.recv().await.is_none());
+        assert_eq!(stream.done_reason(), Some("length"));
+
+        for streaming in [false, true] {
+            run_single_prompt(
+                &backend,
+                &Config::default(),
+                "test-model",
+                "hi",
+                streaming,
+                &[],
+                None,
+                None,
+                false,
+                Some(3),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            max_tokens_note(Some(3), Some("length")).as_deref(),
+            Some("Output was truncated by --max-tokens 3.")
+        );
+        assert_eq!(max_tokens_note(Some(3), Some("stop")), None);
+        assert_eq!(max_tokens_note(None, Some("length")), None);
+    }
+
+    // ── /resend-with ──────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_resend_with_uses_override_model_once() {
+        let (backend, _sent) = MockLlmBackend::new("other answer");
+        let mut repl = make_repl(backend);
+        repl.context
+            .add_message(Message::user("explain lifetimes").with_typed("explain lifetimes"));
+        repl.context.add_message(Message::assistant("first answer"));
+        repl.context
+            .add_message(Message::user("[Ran shell command]\n$ cargo check\n"));
+
+        repl.handle_command("/resend-with codellama:13b")
+            .await
+            .unwrap();
+
+        let requests = repl.client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].model, "codellama:13b");
+        assert_eq!(
+            requests[0].messages.last().unwrap().content,
+            "explain lifetimes"
+        );
+        assert_eq!(repl.model, "test-model");
+        assert_eq!(repl.context.messages().len(), 3);
+    }
+
+    // ── streaming cancellation ────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_partial_response_kept_only_when_configured() {
+        // An interrupted reply comes back empty, leaving what streamed in partial_response
+        let (backend, _sent) = MockLlmBackend::new("");
+        let mut repl = make_repl(backend);
+        repl.readonly = true;
+        repl.partial_response = Some("The answer is".into());
+        repl.send_message("question").await.unwrap();
+        let last = repl.context.messages().last().unwrap();
+        assert_eq!(
+            (last.role.as_str(), last.content.as_str()),
+            ("assistant", "The answer is")
+        );
+        assert_eq!(repl.partial_response, None);
+
+        repl.config.ui.keep_partial_on_interrupt = false;
+        repl.partial_response = Some("Another half".into());
+        repl.send_message("again").await.unwrap();
+        let last = repl.context.messages().last().unwrap();
+        assert_eq!(
+            (last.role.as_str(), last.content.as_str()),
+            ("user", "again")
+        );
+        assert_eq!(repl.partial_response, None);
+
+        // A one-off /resend-with answer never joins the conversation, even partially
+        repl.config.ui.keep_partial_on_interrupt = true;
+        let before = repl.context.messages().len();
+        repl.partial_response = Some("One-off half".into());
+        repl.handle_command("/resend-with codellama:13b")
+            .await
+            .unwrap();
+        assert_eq!(repl.partial_response, None);
+        repl.send_message("third").await.unwrap();
+        assert_eq!(repl.context.messages().len(), before + 1);
+        assert!(repl
+            .context
+            .messages()
+            .iter()
+            .all(|m| m.content != "One-off half"));
+    }
+
+    // ── model context window ──────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_adopted_context_window_matches_num_ctx() {
+        // A window larger than the default is capped at the num_ctx actually sent;
+        // a smaller one lowers both
+        for (window, expected) in [(131072, 32768), (8192, 8192)] {
+            let (mut backend, _sent) = MockLlmBackend::new("ok");
+            backend.context_length = Some(window);
+            let mut repl = make_repl(backend);
+
+            repl.adopt_model_context_length().await;
+            assert_eq!(repl.context.token_budget(), expected);
+            assert_eq!(repl.config.context_limit, None);
+
+            repl.send_message("hi").await.unwrap();
+            let requests = repl.client.requests.lock().unwrap();
+            let options = requests[0].options.as_ref().unwrap();
+            assert_eq!(options.num_ctx, Some(repl.context.token_budget()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_context_limit_is_not_replaced() {
+        let (mut backend, _sent) = MockLlmBackend::new("ok");
+        backend.context_length = Some(131072);
+        let config = Config {
+            context_limit: Some(8192),
+            ..Config::default()
+        };
+        let mut repl = Repl::new(backend, config, "test-model".into(), false);
+
+        repl.adopt_model_context_length().await;
+        assert_eq!(repl.context.token_budget(), 8192);
+    }
+
+    // ── /templates reload ─────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_templates_reload_picks_up_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        assert!(!repl.templates.is_template_command("shout"));
+
+        let template_dir = dir.path().join(".slab/templates");
+        std::fs::create_dir_all(&template_dir).unwrap();
+        std::fs::write(
+            template_dir.join("shout.yaml"),
+            "name: shout\ncommand: /shout\ndescription: Say it loud\nprompt: \"{{content}}!\"\n",
+        )
+        .unwrap();
+        std::fs::write(template_dir.join("broken.yaml"), "name: [unclosed").unwrap();
+
+        repl.handle_command("/templates reload").await.unwrap();
+        assert!(repl.templates.is_template_command("shout"));
+        // Built-in templates survive the reload
+        assert!(repl.templates.is_template_command("review"));
+
+        let history = Vec::new();
+        let cwd = dir.path().to_path_buf();
+        let context = CompletionContext {
+            context_files: vec![],
+            cwd: &cwd,
+            models: None,
+            history: &history,
+        };
+        let completions = repl.completion_engine.complete("/sho", &context);
+        assert!(completions.iter().any(|c| c.text == "/shout"));
+    }
+
+    // ── /edit ─────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_edit_replaces_last_message() {
+        let (backend, sent) = MockLlmBackend::new("answer");
+        let mut repl = make_repl(backend);
+        repl.process_input("first question").await.unwrap();
+        repl.process_input("secnod question").await.unwrap();
+
+        repl.handle_command("/edit second question").await.unwrap();
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["first question", "answer", "second question", "answer"]
+        );
+        assert_eq!(sent.lock().unwrap().last().unwrap(), "second question");
+
+        // Without text the editor is pre-filled; a command in between cancels the edit
+        repl.handle_command("/edit").await.unwrap();
+        assert_eq!(repl.prefill.as_deref(), Some("second question"));
+        repl.process_input("/context").await.unwrap();
+        repl.process_input("third question").await.unwrap();
+        assert_eq!(repl.context.messages().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_edit_prefills_message_in_context_not_history() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "some notes").unwrap();
+        let (backend, _sent) = MockLlmBackend::new("answer");
+        let mut repl = make_repl(backend);
+        repl.context = ContextManager::new(4096, dir.path().to_path_buf());
+        repl.context.add_file(dir.path().join("notes.txt")).unwrap();
+
+        repl.process_input("summarize @notes.txt").await.unwrap();
+        assert!(repl.context.messages()[0].content.contains("some notes"));
+        // Recalled or scripted lines that never reached the context don't count
+        repl.history.push("something else entirely".into());
+
+        repl.handle_command("/edit").await.unwrap();
+        assert_eq!(repl.prefill.as_deref(), Some("summarize @notes.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_edit_replaces_typed_prompt_not_exec_output() {
+        let (backend, _sent) = MockLlmBackend::new("Check it:\n```exec\necho ran\n```");
+        let mut repl = make_repl(backend);
+        // Read-only keeps the reply's exec block from prompting; /replay then runs it
+        repl.readonly = true;
+        repl.process_input("run the check").await.unwrap();
+        repl.readonly = false;
+        repl.handle_command("/replay").await.unwrap();
+        let last = repl.context.messages().last().unwrap();
+        assert!(last.content.starts_with("[Ran shell command]"));
+
+        repl.handle_command("/edit").await.unwrap();
+        assert_eq!(repl.prefill.as_deref(), Some("run the check"));
+
+        repl.readonly = true;
+        repl.process_input("run the check again").await.unwrap();
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["run the check again", "Check it:\n```exec\necho ran\n```"]
+        );
+    }
+
+    // ── /retry ────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_retry_regenerates_last_response() {
+        let (backend, _sent) = MockLlmBackend::new("answer");
+        let mut repl = make_repl(backend);
+        repl.handle_command("/retry").await.unwrap();
+        assert!(repl.client.requests.lock().unwrap().is_empty());
+
+        repl.process_input("first question").await.unwrap();
+        repl.process_input("second question").await.unwrap();
+        repl.handle_command("/retry temp=1.5").await.unwrap();
+
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["first question", "answer", "second question", "answer"]
+        );
+        let temperature = |r: &ChatRequest| r.options.as_ref().unwrap().temperature;
+        let configured = {
+            let requests = repl.client.requests.lock().unwrap();
+            assert_eq!(requests.len(), 3);
+            let contents = |r: &ChatRequest| -> Vec<String> {
+                r.messages.iter().map(|m| m.content.clone()).collect()
+            };
+            assert_eq!(contents(&requests[2]), contents(&requests[1]));
+            assert_eq!(temperature(&requests[2]), Some(1.5));
+            temperature(&requests[1])
+        };
+        assert_ne!(configured, Some(1.5));
+
+        // The override only lasts for the retried request
+        repl.handle_command("/retry").await.unwrap();
+        let requests = repl.client.requests.lock().unwrap();
+        assert_eq!(temperature(&requests[3]), configured);
+    }
+
+    #[tokio::test]
+    async fn test_retry_skips_trailing_exec_output() {
+        let reply = "Check it:\n```exec\necho ran\n```";
+        let (backend, _sent) = MockLlmBackend::new(reply);
+        let mut repl = make_repl(backend);
+        repl.readonly = true;
+        repl.process_input("run the check").await.unwrap();
+        repl.readonly = false;
+        repl.handle_command("/replay").await.unwrap();
+
+        repl.readonly = true;
+        repl.handle_command("/retry").await.unwrap();
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["run the check", reply]);
+        let requests = repl.client.requests.lock().unwrap();
+        let retried = requests.last().unwrap().messages.last().unwrap();
+        assert_eq!(retried.content, "run the check");
+    }
+
+    // ── sessions and /load ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_load_session_restores_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("kept.rs");
+        std::fs::write(&kept, "fn on_disk() {}").unwrap();
+        let gone = dir.path().join("gone.rs");
+
+        let mut session = Session::new("work", "test-model");
+        session.messages = vec![Message::user("hi")];
+        session.files = vec![
+            (kept.clone(), "fn saved() {}".to_string()),
+            (gone.clone(), "fn deleted() {}".to_string()),
+            (PathBuf::from("stdin"), "piped".to_string()),
+        ];
+        // Files survive a save/load round trip through JSON
+        let session: Session =
+            serde_json::from_str(&serde_json::to_string(&session).unwrap()).unwrap();
+
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.load_session(session);
+
+        // Files on disk are re-read; the saved copy only stands in for deleted ones
+        let content = |path: &Path| repl.context.get_file_content(path).map(String::as_str);
+        assert_eq!(content(&kept), Some("fn on_disk() {}"));
+        assert_eq!(content(&gone), Some("fn deleted() {}"));
+        assert_eq!(content(Path::new("stdin")), Some("piped"));
+        assert_eq!(repl.context.file_entries().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_load_command_loads_saved_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = Session::new("parser", "other-model");
+        session.messages = vec![Message::user("why?"), Message::assistant("because")];
+        std::fs::write(
+            dir.path().join("parser.json"),
+            serde_json::to_string(&session).unwrap(),
+        )
+        .unwrap();
+
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.sessions_dir = Some(dir.path().to_path_buf());
+        repl.load_session(Session::new("scratch", "test-model"));
+        assert_eq!(repl.session_name(), Some("scratch"));
+
+        let earlier = dir.path().join("earlier.rs");
+        std::fs::write(&earlier, "fn earlier() {}").unwrap();
+        repl.context.add_file(&earlier).unwrap();
+
+        assert!(repl.handle_command("/load missing").await.unwrap());
+        assert!(repl.context.messages().is_empty());
+        assert!(repl.context.has_file(&earlier));
+
+        assert!(repl.handle_command("/load parser").await.unwrap());
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["why?", "because"]);
+        assert_eq!(repl.model, "other-model");
+        // The loaded session's files replace the ones in context, not merge with them
+        assert!(repl.context.list_files().is_empty());
+        // Saving on exit follows the loaded session, not the one slab started with
+        assert_eq!(repl.session_name(), Some("parser"));
+    }
+
+    // ── autosave ──────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_autosave_after_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let (backend, _sent) = MockLlmBackend::new("answer");
+        let mut config = Config::default();
+        config.ui.autosave_interval = Some(4);
+        let mut repl = Repl::new(backend, config, "test-model".into(), false);
+        repl.sessions_dir = Some(dir.path().to_path_buf());
+        let autosave = dir.path().join(format!("{}.json", AUTOSAVE_NAME));
+
+        repl.process_input("one").await.unwrap();
+        assert!(!autosave.exists());
+
+        repl.process_input("two").await.unwrap();
+        let session = Session::load_last_in(dir.path()).unwrap();
+        assert_eq!(session.name, AUTOSAVE_NAME);
+        assert_eq!(session.messages.len(), 4);
+        assert_eq!(session.model, "test-model");
+
+        // Not rewritten until another interval's worth of messages arrives
+        repl.process_input("three").await.unwrap();
+        assert_eq!(Session::load_last_in(dir.path()).unwrap().messages.len(), 4);
+        repl.process_input("four").await.unwrap();
+        assert_eq!(Session::load_last_in(dir.path()).unwrap().messages.len(), 8);
+    }
+
+    // ── /json ─────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_json_mode_sets_request_format() {
+        let (backend, _sent) = MockLlmBackend::new("{}");
+        let mut repl = make_repl(backend);
+        assert_eq!(repl.build_chat_request().format, None);
+
+        repl.handle_command("/json on").await.unwrap();
+        assert_eq!(repl.build_chat_request().format.as_deref(), Some("json"));
+
+        repl.handle_command("/json off").await.unwrap();
+        assert_eq!(repl.build_chat_request().format, None);
+    }
+
+    // ── sampling overrides ────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_temp_override_applies_to_request() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+
+        repl.handle_command("/temp 0.0").await.unwrap();
+        let options = repl.build_chat_request().options.unwrap();
+        assert_eq!(options.temperature, Some(0.0));
+
+        // Out-of-range values are rejected and keep the previous override
+        repl.handle_command("/temp 2.5").await.unwrap();
+        assert_eq!(repl.temperature_override, Some(0.0));
+
+        repl.handle_command("/temp reset").await.unwrap();
+        let options = repl.build_chat_request().options.unwrap();
+        assert_eq!(
+            options.temperature,
+            Some(Config::default().get_model_config("test-model").temperature)
+        );
+    }
+
+    // ── /replay ───────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_replay_reparses_last_assistant_message() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+
+        // Nothing to replay yet
+        assert!(repl.replay_commands().is_empty());
+
+        repl.context.add_message(Message::assistant(
+            "Build it:\n```exec\necho replayed\n```\nthen\n```run\ntrue\n```",
+        ));
+        assert_eq!(repl.replay_commands(), vec!["echo replayed", "true"]);
+
+        repl.handle_command("/replay").await.unwrap();
+        let outputs: Vec<&Message> = repl
+            .context
+            .messages()
+            .iter()
+            .filter(|m| m.role == "user" && m.content.starts_with("[Ran shell command]"))
+            .collect();
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs[0].content.contains("stdout:\nreplayed\n"));
+    }
+
+    // ── status bar ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_budget_level_thresholds() {
+        assert_eq!(BudgetLevel::from_usage(0, 8192), BudgetLevel::Normal);
+        assert_eq!(BudgetLevel::from_usage(6143, 8192), BudgetLevel::Normal);
+        assert_eq!(BudgetLevel::from_usage(6144, 8192), BudgetLevel::Warning);
+        assert_eq!(BudgetLevel::from_usage(89, 100), BudgetLevel::Warning);
+        assert_eq!(BudgetLevel::from_usage(90, 100), BudgetLevel::Critical);
+        assert_eq!(BudgetLevel::from_usage(150, 100), BudgetLevel::Critical);
+        // A zero budget must not panic
+        assert_eq!(BudgetLevel::from_usage(10, 0), BudgetLevel::Normal);
+    }
+
+    // ── --system override ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_system_override_is_first_message() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let config = Config {
+            system_prompt_override: Some("Answer in one word".into()),
+            ..Config::default()
+        };
+        let mut repl = Repl::new(backend, config, "test-model".into(), false);
+        repl.context.add_message(Message::user("capital of France"));
+
+        let messages = repl.build_chat_request().messages;
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.starts_with("Answer in one word"));
+        assert!(!messages[0].content.contains("helpful coding assistant"));
+        assert_eq!(messages.last().unwrap().content, "capital of France");
+    }
+
+    // ── /clear ──────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_clear_keep_drops_oldest_messages() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        for i in 0..3 {
+            repl.context
+                .add_message(Message::user(format!("question {}", i)));
+            repl.context
+                .add_message(Message::assistant(format!("answer {}", i)));
+        }
+        repl.marks.push(Mark {
+            label: "late".into(),
+            index: 4,
+        });
+
+        repl.handle_command("/clear keep=2").await.unwrap();
+        let messages = repl.context.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "question 2");
+        assert_eq!(repl.marks[0].index, 0);
+
+        repl.handle_command("/clear keep=10").await.unwrap();
+        assert_eq!(repl.context.messages().len(), 2);
+        repl.handle_command("/clear keep=x").await.unwrap();
+        assert_eq!(repl.context.messages().len(), 2);
+
+        // An odd count rounds down to whole exchanges instead of starting on a reply
+        repl.context.add_message(Message::user("question 3"));
+        repl.context.add_message(Message::assistant("answer 3"));
+        repl.handle_command("/clear keep=3").await.unwrap();
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["question 3", "answer 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_auto_prune_shifts_marks() {
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let config = Config {
+            auto_prune: true,
+            ..Config::default()
+        };
+        let mut repl = Repl::new(backend, config, "test-model".into(), false);
+        for i in 0..4 {
+            repl.context
+                .add_message(Message::user(format!("question {} {}", i, "x".repeat(40))));
+            repl.context
+                .add_message(Message::assistant(format!("answer {}", i)));
+        }
+        repl.marks.push(Mark {
+            label: "last".into(),
+            index: 6,
+        });
+        // Just full, so the next message prunes the oldest exchange
+        let used = repl.context.token_count();
+        repl.context.set_token_budget(used);
+
+        repl.send_message("one more").await.unwrap();
+        let messages = repl.context.messages();
+        assert!(messages.len() < 10, "nothing was pruned");
+        let after = messages_after_mark(messages, &repl.marks, "last").unwrap();
+        assert_eq!(after[0].content, format!("question 3 {}", "x".repeat(40)));
+    }
+
+    // ── /compact ────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_compact_replaces_oldest_messages_with_summary() {
+        let (backend, sent) = MockLlmBackend::new("- user asked about parsing");
+        let mut repl = make_repl(backend);
+        for i in 0..3 {
+            repl.context
+                .add_message(Message::user(format!("question {}", i)));
+            repl.context
+                .add_message(Message::assistant(format!("answer {}", i)));
+        }
+
+        repl.marks.push(Mark {
+            label: "late".into(),
+            index: 5,
+        });
+        let replaced = repl.compact_history(4).await.unwrap();
+        assert_eq!(replaced, 4);
+        // Still just before "answer 2"
+        assert_eq!(repl.marks[0].index, 2);
+
+        let messages = repl.context.messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].role, "assistant");
+        assert!(messages[0].content.starts_with("[compacted]"));
+        assert!(messages[0].content.contains("user asked about parsing"));
+        assert_eq!(messages[1].content, "question 2");
+
+        // The transcript sent for summarization covers only the compacted messages
+        let transcript = sent.lock().unwrap()[0].clone();
+        assert!(transcript.contains("answer 1"));
+        assert!(!transcript.contains("question 2"));
+    }
+
+    // ── Stream progress ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_format_stream_progress() {
+        assert_eq!(
+            format_stream_progress(0, 0, Duration::ZERO),
+            "0 tokens · 0 chars · 0.0s"
+        );
+        assert_eq!(
+            format_stream_progress(1, 1, Duration::from_millis(250)),
+            "1 token · 1 char · 0.2s"
+        );
+        assert_eq!(
+            format_stream_progress(12, 48, Duration::from_millis(3420)),
+            "12 tokens · 48 chars · 3.4s"
+        );
+    }
+
+    // ── /explain-diff ───────────────────────────────────────────────────────
+
+    #[test]
+    fn test_explain_diff_prompt_contains_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn old() {}\n").unwrap();
+
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        repl.context.add_message(Message::assistant(
+            "```rust:lib.rs\nfn a() {}\nfn new() {}\n```",
+        ));
+
+        let (count, prompt) = repl.explain_diff_prompt().unwrap();
+        assert_eq!(count, 1);
+        assert!(prompt.contains("--- a/lib.rs\n+++ b/lib.rs"));
+        assert!(prompt.contains("-fn old() {}"));
+        assert!(prompt.contains("+fn new() {}"));
+        assert!(!prompt.contains('\x1b'));
+    }
+
+    #[tokio::test]
+    async fn test_explain_diff_reply_is_not_acted_on() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn old() {}\n").unwrap();
+        let reply = "It adds:\n```rust:added.rs\nfn added() {}\n```\n\n```exec\ntouch ran.txt\n```";
+        let (backend, sent) = MockLlmBackend::new(reply);
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        repl.config.ui.auto_apply_file_ops = true;
+        repl.context
+            .add_message(Message::assistant("```rust:lib.rs\nfn new() {}\n```"));
+
+        repl.handle_command("/explain-diff").await.unwrap();
+        assert!(!dir.path().join("added.rs").exists());
+        assert!(!dir.path().join("ran.txt").exists());
+        assert!(sent.lock().unwrap()[0].contains("+fn new() {}"));
+
+        let messages = repl.context.messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[1].content,
+            "[Asked to explain 1 proposed change(s)]"
+        );
+        assert!(messages[1].typed.is_none());
+        assert_eq!(messages[2].content, reply);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_explain_diff_resends_the_real_prompt() {
+        let (backend, _sent) = MockLlmBackend::new("explained");
+        let mut repl = make_repl(backend);
+        repl.context
+            .add_message(Message::user("rename old").with_typed("rename old"));
+        repl.context
+            .add_message(Message::assistant("```rust:lib.rs\nfn new() {}\n```"));
+
+        repl.handle_command("/explain-diff").await.unwrap();
+        repl.handle_command("/retry").await.unwrap();
+
+        let requests = repl.client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].messages.last().unwrap().content, "rename old");
+        let contents: Vec<&str> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["rename old", "explained"]);
+    }
+
+    // ── /undo ───────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_undo_reverts_last_applied_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn old() {}\n").unwrap();
+        std::fs::write(dir.path().join("gone.txt"), "keep me\n").unwrap();
+
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        repl.config.ui.auto_apply_file_ops = true;
+
+        repl.handle_command("/undo").await.unwrap();
+
+        repl.process_file_operations(
+            "```rust:lib.rs\nfn new() {}\n```\n\n```text:notes.txt\nhello\n```\n\nDELETE: gone.txt\n",
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+            "fn new() {}"
+        );
+        assert!(dir.path().join("notes.txt").exists());
+        assert!(!dir.path().join("gone.txt").exists());
+
+        repl.handle_command("/undo").await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+            "fn old() {}\n"
+        );
+        assert!(!dir.path().join("notes.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("gone.txt")).unwrap(),
+            "keep me\n"
+        );
+        assert!(repl.last_applied.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_file_op_hooks_veto_and_report_to_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let (backend, _sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.project_root = dir.path().to_path_buf();
+        repl.config.ui.auto_apply_file_ops = true;
+        repl.config.hooks.pre_apply = Some("test ! -e {{files}}".into());
+        repl.config.hooks.post_apply = Some("cat {{files}}".into());
+
+        repl.process_file_operations("```text:notes.txt\nhello\n```\n")
+            .unwrap();
+        assert!(dir.path().join("notes.txt").exists());
+        let hook_messages: Vec<String> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| m.content.clone())
+            .collect();
+        assert_eq!(hook_messages.len(), 2);
+        // Hook output is never taken for something the user typed
+        assert!(repl.context.messages().iter().all(|m| m.typed.is_none()));
+        assert!(hook_messages.iter().all(|m| is_command_output(m)));
+        assert!(hook_messages[0].starts_with("[Ran pre_apply hook]\n$ test ! -e notes.txt"));
+        assert!(hook_messages[1].starts_with("[Ran post_apply hook]\n$ cat notes.txt"));
+        assert!(hook_messages[1].contains("stdout:\nhello\n"));
+
+        // The file exists now, so pre_apply fails and the edit is not applied
+        repl.process_file_operations("```text:notes.txt\nchanged\n```\n")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "hello"
+        );
+        let last = &repl.context.messages().last().unwrap().content;
+        assert!(last.starts_with("[Ran pre_apply hook]"));
+        assert!(last.ends_with("exit code: 1\n"));
+        assert_eq!(repl.context.messages().len(), 3);
+    }
+
+    // ── template auto_add ────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_template_auto_add_files_before_render() {
+        let dir = tempfile::tempdir().unwrap();
+        let bindings = dir.path().join("bindings.rs");
+        std::fs::write(&bindings, "pub fn c_abs(x: i32) -> i32;").unwrap();
+        let template_path = dir.path().join("port.yaml");
+        std::fs::write(
+            &template_path,
+            format!(
+                "name: port\ncommand: /port\ndescription: Port C\nauto_add: [\"{}\", \"{}\"]\nprompt: \"{{{{files}}}}\"\n",
+                bindings.display(),
+                dir.path().join("missing/*.h").display()
+            ),
+        )
+        .unwrap();
+
+        let (backend, sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.templates.load_template(&template_path).unwrap();
+
+        let out = dir.path().join("out.md");
+        let out_arg = format!("--output={}", out.display());
+        repl.handle_template_command("port", &[out_arg.as_str()])
+            .await
+            .unwrap();
+
+        assert!(repl.context.has_file(&bindings));
+        let sent = sent.lock().unwrap();
+        assert!(sent.last().unwrap().contains("pub fn c_abs"));
+    }
+
+    #[tokio::test]
+    async fn test_command_shortcut_runs_template_with_preset_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let template_path = dir.path().join("shout.yaml");
+        std::fs::write(
+            &template_path,
+            "name: shout\ncommand: /shout\ndescription: Say it\nvariables:\n  - name: tone\n    default: calm\nprompt: \"say it {{tone}}\"\n",
+        )
+        .unwrap();
+
+        let (backend, sent) = MockLlmBackend::new("ok");
+        let mut repl = make_repl(backend);
+        repl.templates.load_template(&template_path).unwrap();
+        repl.config
+            .commands
+            .insert("loud".to_string(), "/shout tone=LOUD".to_string());
+        repl.config
+            .commands
+            .insert("broken".to_string(), "/nope".to_string());
+
+        let out = dir.path().join("out.md");
+        let out_arg = format!("--output={}", out.display());
+        repl.handle_command(&format!("/loud {}", out_arg))
+            .await
+            .unwrap();
+        assert_eq!(sent.lock().unwrap().last().unwrap(), "say it LOUD");
+
+        repl.handle_command("/broken").await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+
+        // A shortcut named like a built-in never shadows it
+        for name in ["tokens", "exit", "q", "rm"] {
+            repl.config
+                .commands
+                .insert(name.to_string(), "/shout".to_string());
+        }
+        repl.handle_command("/tokens").await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(!repl.handle_command("/exit").await.unwrap());
+        assert!(!repl.handle_command("/q").await.unwrap());
+        repl.handle_command("/rm nothing.rs").await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        let completions = template_completions(&repl.templates, &repl.config);
+        assert!(completions
+            .iter()
+            .all(|(name, _)| name != "tokens" && name != "rm"));
+    }
+
+    #[test]
+    fn test_is_builtin_command_covers_aliases() {
+        for name in [
+            "remove",
+            "rm",
+            "exit",
+            "quit",
+            "q",
+            "add-url",
+            "explain-diff",
+        ] {
+            assert!(is_builtin_command(name), "{}", name);
+        }
+        assert!(!is_builtin_command("shout"));
+    }
+
+    // ── run_script ───────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_run_script_drives_commands_and_prompts() {
+        let (backend, sent) = MockLlmBackend::new("scripted reply");
+        let mut repl = make_repl(backend);
+        let script = "# first question\n\n/temp 0.2\nsecond question\n/exit\nnever sent\n";
+
+        repl.run_script(script).await.unwrap();
+
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec!["# first question", "second question"]
+        );
+        let transcript: Vec<(&str, &str)> = repl
+            .context
+            .messages()
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            transcript,
+            vec![
+                ("user", "# first question"),
+                ("assistant", "scripted reply"),
+                ("user", "second question"),
+                ("assistant", "scripted reply"),
+            ]
+        );
+        assert_eq!(repl.temperature_override, Some(0.2));
+    }
+
+    // ── /help categories ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_every_command_has_one_help_category() {
+        let (backend, _) = MockLlmBackend::new("");
+        let repl = make_repl(backend);
+        let help_names: Vec<&str> = HELP_COMMANDS
+            .iter()
+            .map(|(_, usage, _)| usage.split_whitespace().next().unwrap())
+            .collect();
+
+        let known = repl.get_completions("/");
+        assert!(!known.is_empty());
+        for (text, _, kind) in known {
+            // Templates have their own section; /quit is an alias of /exit
+            let is_template = repl.templates.is_template_command(&text[1..]);
+            if kind != CompletionKind::Command || is_template || text == "/quit" {
+                continue;
+            }
+            let count = help_names.iter().filter(|n| **n == text).count();
+            assert_eq!(
+                count, 1,
+                "{} should be listed in exactly one category",
+                text
+            );
+        }
+        for category in HelpCategory::ALL {
+            assert!(HELP_COMMANDS.iter().any(|(c, _, _)| *c == category));
+        }
+    }
+
+    // ── /model pull ──────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_model_pull_routes_to_pull_and_switches() {
+        let (backend, _) = MockLlmBackend::new("");
+        let mut repl = make_repl(backend);
+
+        assert!(repl.handle_command("/model pull qwen3:8b").await.unwrap());
+
+        assert_eq!(*repl.client.pulled.lock().unwrap(), vec!["qwen3:8b"]);
+        assert_eq!(repl.model, "qwen3:8b");
+        assert!(repl
+            .cached_models
+            .as_ref()
+            .is_some_and(|m| m.contains(&"qwen3:8b".to_string())));
+    }
+
+    #[test]
+    fn test_model_menu_items_show_sizes_and_current() {
+        let names = vec!["llama3:8b".to_string(), "qwen3:8b".to_string()];
+        let sizes = HashMap::from([("qwen3:8b".to_string(), "5.2 GB".to_string())]);
+
+        let items = model_menu_items(&names, &sizes, "qwen3:8b");
+        assert_eq!(items[0].0, "llama3:8b");
+        assert_eq!(items[0].1, None);
+        assert_eq!(items[1].1.as_deref(), Some("5.2 GB · current"));
+
+        let items = model_menu_items(&names, &HashMap::new(), "llama3:8b");
+        assert_eq!(items[0].1.as_deref(), Some("current"));
+    }
+
+    #[test]
+    fn test_menu_scroll_offset_follows_selection() {
+        // Inside the window the offset stays put
+        assert_eq!(menu_scroll_offset(3, 0, 5), 0);
+        // Moving past the bottom scrolls down one row at a time
+        assert_eq!(menu_scroll_offset(5, 0, 5), 1);
+        assert_eq!(menu_scroll_offset(11, 6, 5), 7);
+        // Moving above the top scrolls back up
+        assert_eq!(menu_scroll_offset(5, 6, 5), 5);
+        assert_eq!(menu_scroll_offset(0, 1, 5), 0);
+    }
+
+    // ── /cost ────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_session_usage_splits_input_and_output() {
+        let messages = vec![
+            Message::system("x".repeat(400)),
+            Message::user("x".repeat(40)),
+            Message::assistant("x".repeat(400)),
+            Message::user("x".repeat(80)),
+            Message::assistant("x".repeat(800)),
+        ];
+        let usage = SessionUsage::from_messages(&messages);
+        assert_eq!(
+            usage,
+            SessionUsage {
+                input_tokens: 30,
+                output_tokens: 300,
+                input_messages: 2,
+                output_messages: 2,
+            }
+        );
+        assert_eq!(usage.total(), 330);
+        assert_eq!(usage.average(), 82);
+
+        assert_eq!(usage.cost(None, None), None);
+        let cost = usage.cost(Some(1.0), Some(2.0)).unwrap();
+        assert!((cost - 0.63).abs() < 1e-9, "{}", cost);
+        let cost = usage.cost(None, Some(2.0)).unwrap();
+        assert!((cost - 0.6).abs() < 1e-9, "{}", cost);
+
+        assert_eq!(SessionUsage::from_messages(&[]).average(), 0);
+    }
+
+    // ── multiline input ──────────────────────────────────────────────────────
+
+    #[test]
+    fn test_continues_line_only_at_end_of_input() {
+        assert!(continues_line("first line \\", 12));
+        assert!(!continues_line("first line \\", 5));
+        assert!(!continues_line("path\\to file", 12));
+        assert!(!continues_line("", 0));
+    }
+
+    // ── prompt format ────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_render_prompt_format() {
+        let vars = [
+            ("model", "qwen2.5:7b".to_string()),
+            ("bar", "██░░ 50%".to_string()),
+            ("files", "3".to_string()),
+            ("tokens", "1024".to_string()),
+            ("cwd", "~/src/slab".to_string()),
+        ];
+        assert_eq!(
+            render_prompt_format("{cwd} ({model}, {files}f, {tokens}t) {bar} $ ", &vars),
+            "~/src/slab (qwen2.5:7b, 3f, 1024t) ██░░ 50% $ "
+        );
+        assert_eq!(render_prompt_format("{nope} > ", &vars), "{nope} > ");
+
+        // Substituted values are not expanded a second time
+        let vars = [
+            ("model", "{cwd}".to_string()),
+            ("cwd", "/tmp/{model}".to_string()),
+        ];
+        assert_eq!(
+            render_prompt_format("{model} in {cwd} {", &vars),
+            "{cwd} in /tmp/{model} {"
+        );
+    }
+
+    // ── /exec ────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_truncate_middle_lines_keeps_head_and_tail() {
+        let text: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+
+        assert_eq!(
+            truncate_middle_lines(&text, 5),
+            "line 1\nline 2\nline 3\n... [5 lines omitted] ...\nline 9\nline 10\n"
+        );
+        // At or under the cap, and with the cap disabled, output is untouched
+        assert_eq!(truncate_middle_lines(&text, 10), text);
+        assert_eq!(truncate_middle_lines(&text, 0), text);
+        assert_eq!(
+            truncate_middle_lines(&text, 1),
+            "line 1\n... [9 lines omitted] ...\n"
+        );
+    }
+
+    // ── /export ──────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_export_markdown_formats_roles_and_code() {
+        let messages = vec![
+            Message::user("How do I print?"),
+            Message::assistant("Use println!:\n\n```rust\nprintln!(\"hi\");\n```\n"),
+        ];
+        let out = format_export(&messages, "qwen2.5:7b", ExportFormat::Markdown);
+
+        assert!(out.starts_with("# Slab Chat Export\n"));
+        assert!(out.contains("- **Model:** qwen2.5:7b\n- **Messages:** 2\n"));
+        assert!(out.ends_with(
+            "## User\n\nHow do I print?\n\n## Assistant\n\nUse println!:\n\n```rust\nprintln!(\"hi\");\n```\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_export_append_keeps_existing_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chat.log");
+        let path_arg = path.to_string_lossy().to_string();
+
+        let (backend, _) = MockLlmBackend::new("answer");
+        let mut repl = make_repl(backend);
+        repl.context.add_message(Message::user("first"));
+        repl.handle_command(&format!("/export {}", path_arg))
+            .await
+            .unwrap();
+        repl.context.add_message(Message::user("second"));
+        repl.handle_command(&format!("/export --append {}", path_arg))
+            .await
+            .unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.matches("=== Slab Chat Export ===").count(), 2);
+        assert!(log.contains("End of export\n\n=== Slab Chat Export ==="));
+        assert!(log.contains("Messages: 1"));
+        assert!(log.contains("Messages: 2"));
+
+        // Without --append the file is overwritten
+        repl.handle_command(&format!("/export {}", path_arg))
+            .await
+            .unwrap();
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.matches("=== Slab Chat Export ===").count(), 1);
+    }
 }
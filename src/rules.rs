@@ -71,6 +71,23 @@ impl RuleEngine {
         Self { rules: Vec::new() }
     }
 
+    /// Load rules from several directories, highest priority first.
+    /// A rule whose name was already loaded from an earlier directory is skipped,
+    /// so project rules shadow global ones.
+    pub fn load_from_directories(&mut self, dirs: &[PathBuf]) {
+        for dir in dirs {
+            let mut layer = RuleEngine::new();
+            layer.load_from_directory(dir);
+            for rule in layer.rules {
+                if !self.rules.iter().any(|r| r.name == rule.name) {
+                    self.rules.push(rule);
+                }
+            }
+        }
+
+        self.rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
+    }
+
     /// Load rules from a directory
     pub fn load_from_directory(&mut self, dir: &Path) {
         if !dir.exists() || !dir.is_dir() {
@@ -87,7 +104,7 @@ impl RuleEngine {
         self.load_text_rules(dir);
 
         // Sort by priority (descending)
-        self.rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        self.rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
     }
 
     fn load_yaml_rules(&mut self, dir: &Path) {
@@ -119,8 +136,9 @@ impl RuleEngine {
         let pattern = dir.join("*.md");
         if let Ok(entries) = glob::glob(&pattern.to_string_lossy()) {
             for entry in entries.flatten() {
-                if let Ok(rule) = self.parse_markdown_rule(&entry) {
-                    self.rules.push(rule);
+                match self.parse_markdown_rule(&entry) {
+                    Ok(rule) => self.rules.push(rule),
+                    Err(e) => eprintln!("Warning: Failed to load rule {:?}: {}", entry, e),
                 }
             }
         }
@@ -137,23 +155,14 @@ impl RuleEngine {
             .unwrap_or("unnamed")
             .to_string();
 
-        // Check for YAML frontmatter
-        let (metadata, rule_content) = if let Some(after_start) = content.strip_prefix("---") {
-            if let Some(end_idx) = after_start.find("---") {
-                let frontmatter = &after_start[..end_idx];
-                let rest = &after_start[end_idx + 3..];
-
-                // Try to parse frontmatter
-                if let Ok(meta) = serde_yaml::from_str::<RuleMetadata>(frontmatter) {
-                    (Some(meta), rest.trim().to_string())
-                } else {
-                    (None, content)
-                }
-            } else {
-                (None, content)
+        // Check for YAML frontmatter; invalid frontmatter is reported and the rule is skipped
+        let (metadata, rule_content) = match split_front_matter(&content) {
+            Some((frontmatter, body)) => {
+                let meta = serde_yaml::from_str::<RuleMetadata>(frontmatter)
+                    .map_err(|e| format!("Invalid front-matter: {}", e))?;
+                (Some(meta), body.trim().to_string())
             }
-        } else {
-            (None, content)
+            None => (None, content),
         };
 
         Ok(Rule {
@@ -281,6 +290,23 @@ impl Default for RuleEngine {
     }
 }
 
+/// Split a markdown document into its YAML front-matter and body.
+/// The front-matter must open with a `---` line at the very top and close with another `---` line.
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\r\n")
+        .or_else(|| content.strip_prefix("---\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
 /// Optional metadata for markdown rules (in frontmatter)
 #[derive(Debug, Deserialize)]
 struct RuleMetadata {
@@ -328,4 +354,66 @@ mod tests {
         assert!(rule.applies_to_file(Path::new("anything.txt")));
         assert!(rule.applies_to_file(Path::new("src/main.rs")));
     }
+
+    #[test]
+    fn test_markdown_rule_with_front_matter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rust.md");
+        fs::write(
+            &path,
+            "---\nname: rust_guidelines\napplies_to: [\"*.rs\"]\nenabled: false\npriority: 5\n---\n\n# Rules\n\n---\n\nNo unwrap().\n",
+        )
+        .unwrap();
+
+        let rule = RuleEngine::new().parse_markdown_rule(&path).unwrap();
+        assert_eq!(rule.name, "rust_guidelines");
+        assert_eq!(rule.applies_to, vec!["*.rs".to_string()]);
+        assert!(!rule.enabled);
+        assert_eq!(rule.priority, 5);
+        // A horizontal rule in the body must not be mistaken for the closing delimiter
+        assert_eq!(rule.content, "# Rules\n\n---\n\nNo unwrap().");
+    }
+
+    #[test]
+    fn test_markdown_rule_without_front_matter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("style.md");
+        fs::write(&path, "# Style\n\nUse four spaces.\n").unwrap();
+
+        let rule = RuleEngine::new().parse_markdown_rule(&path).unwrap();
+        assert_eq!(rule.name, "style");
+        assert!(rule.applies_to.is_empty());
+        assert!(rule.enabled);
+        assert_eq!(rule.content, "# Style\n\nUse four spaces.\n");
+    }
+
+    #[test]
+    fn test_split_front_matter_requires_closing_line() {
+        assert!(split_front_matter("---\nname: x\nno closing").is_none());
+        assert!(split_front_matter("text\n---\nname: x\n---\n").is_none());
+        let (fm, body) = split_front_matter("---\r\nname: x\r\n---\r\nbody").unwrap();
+        assert_eq!(fm, "name: x\r\n");
+        assert_eq!(body, "body");
+    }
+
+    #[test]
+    fn test_project_rule_shadows_global_rule() {
+        let project = tempfile::tempdir().unwrap();
+        let global = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("style.md"), "Project style").unwrap();
+        fs::write(global.path().join("style.md"), "Global style").unwrap();
+        fs::write(global.path().join("security.txt"), "No secrets").unwrap();
+
+        let mut engine = RuleEngine::new();
+        engine.load_from_directories(&[project.path().to_path_buf(), global.path().to_path_buf()]);
+
+        assert_eq!(engine.rule_count(), 2);
+        let style = engine
+            .all_rules()
+            .iter()
+            .find(|r| r.name == "style")
+            .unwrap();
+        assert_eq!(style.content, "Project style");
+        assert!(engine.all_rules().iter().any(|r| r.name == "security"));
+    }
 }
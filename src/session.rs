@@ -1,10 +1,14 @@
+use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::config::find_project_root;
 use crate::ollama::Message;
 
+/// Name of the session written by `ui.autosave_interval`
+pub const AUTOSAVE_NAME: &str = ".autosave";
+
 /// A saved chat session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -17,22 +21,59 @@ pub struct Session {
     /// Conversation messages
     pub messages: Vec<Message>,
 
+    /// Timestamp when session was first saved (empty for sessions saved before it was tracked)
+    #[serde(default)]
+    pub created_at: String,
+
     /// Timestamp when session was last updated
+    #[serde(default)]
+    pub updated_at: String,
+
+    /// Bookmarks set with /mark
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<Mark>,
+
+    /// Files in context (path and content) when the session was saved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<(PathBuf, String)>,
+}
+
+/// A named position in the conversation: the number of messages before it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mark {
+    pub label: String,
+    pub index: usize,
+}
+
+/// What `slab sessions` shows for each saved session
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    /// File stem, i.e. the name to pass to `--session`
+    pub name: String,
+    pub path: PathBuf,
+    pub model: String,
+    /// None when the file couldn't be read as a session
+    pub message_count: Option<usize>,
+    pub created_at: String,
     pub updated_at: String,
 }
 
 impl Session {
     pub fn new(name: &str, model: &str) -> Self {
+        let now = chrono::Local::now().to_rfc3339();
         Self {
             name: name.to_string(),
             model: model.to_string(),
             messages: Vec::new(),
-            updated_at: chrono::Local::now().to_rfc3339(),
+            created_at: now.clone(),
+            updated_at: now,
+            marks: Vec::new(),
+            files: Vec::new(),
         }
     }
 
     /// Get the session directory
-    fn session_dir() -> Option<PathBuf> {
+    pub fn session_dir() -> Option<PathBuf> {
         // Try project-local first by walking up directory tree
         if let Some(root) = find_project_root() {
             let local_dir = root.join(".slab/sessions");
@@ -79,26 +120,69 @@ impl Session {
         Ok(())
     }
 
+    /// Write the session to `dir` as the crash-recovery autosave, leaving the
+    /// "last" marker alone
+    pub fn save_autosave_in(&self, dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create session directory: {}", e))?;
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize session: {}", e))?;
+
+        fs::write(dir.join(format!("{}.json", AUTOSAVE_NAME)), json)
+            .map_err(|e| format!("Failed to write autosave file: {}", e))
+    }
+
     /// Load a session by name
     pub fn load(name: &str) -> Result<Self, String> {
         let path = Self::session_path(name)
             .ok_or_else(|| "Could not determine session path".to_string())?;
+        Self::load_from(&path)
+    }
 
+    /// Load the session saved as `name` in `dir`
+    pub fn load_in(dir: &Path, name: &str) -> Result<Self, String> {
+        let path = dir.join(format!("{}.json", sanitize_filename(name)));
+        if !path.exists() {
+            return Err(format!("No saved session named '{}'", name));
+        }
+        Self::load_from(&path)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, String> {
         let content =
-            fs::read_to_string(&path).map_err(|e| format!("Failed to read session file: {}", e))?;
+            fs::read_to_string(path).map_err(|e| format!("Failed to read session file: {}", e))?;
 
         serde_json::from_str(&content).map_err(|e| format!("Failed to parse session: {}", e))
     }
 
     /// Load the last used session
     pub fn load_last() -> Result<Self, String> {
-        let last_path = Self::last_session_path()
+        let dir = Self::session_dir()
             .ok_or_else(|| "Could not determine last session path".to_string())?;
+        Self::load_last_in(&dir)
+    }
 
-        let name =
-            fs::read_to_string(&last_path).map_err(|_| "No previous session found".to_string())?;
+    /// Load the last session saved in `dir`, or the autosave when it is newer
+    /// (e.g. the REPL crashed before the session was saved on exit)
+    pub fn load_last_in(dir: &Path) -> Result<Self, String> {
+        let last = fs::read_to_string(dir.join("_last")).ok().and_then(|name| {
+            let file = format!("{}.json", sanitize_filename(name.trim()));
+            Self::load_from(&dir.join(file)).ok()
+        });
+        let autosave = Self::load_from(&dir.join(format!("{}.json", AUTOSAVE_NAME))).ok();
 
-        Self::load(name.trim())
+        match (last, autosave) {
+            (Some(last), Some(autosave)) => Ok(
+                if parse_timestamp(&autosave.updated_at) > parse_timestamp(&last.updated_at) {
+                    autosave
+                } else {
+                    last
+                },
+            ),
+            (Some(session), None) | (None, Some(session)) => Ok(session),
+            (None, None) => Err("No previous session found".to_string()),
+        }
     }
 
     /// List all available sessions
@@ -133,10 +217,103 @@ impl Session {
     }
 
     /// Update the timestamp
-    #[allow(dead_code)]
     pub fn touch(&mut self) {
         self.updated_at = chrono::Local::now().to_rfc3339();
     }
+
+    /// Summaries of every session file in `dir`, most recently updated first.
+    /// Files that fail to parse are still listed by name; the autosave is left out.
+    pub fn summaries_in(dir: &Path) -> Vec<SessionSummary> {
+        let mut summaries = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.extension().map(|e| e == "json").unwrap_or(false) {
+                    continue;
+                }
+                let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                if stem == AUTOSAVE_NAME {
+                    continue;
+                }
+                let session: Option<Session> = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok());
+                summaries.push(match session {
+                    Some(s) => SessionSummary {
+                        name: stem.to_string(),
+                        path: path.clone(),
+                        model: s.model,
+                        message_count: Some(s.messages.len()),
+                        created_at: s.created_at,
+                        updated_at: s.updated_at,
+                    },
+                    None => SessionSummary {
+                        name: stem.to_string(),
+                        path: path.clone(),
+                        model: String::new(),
+                        message_count: None,
+                        created_at: String::new(),
+                        updated_at: String::new(),
+                    },
+                });
+            }
+        }
+
+        sort_by_recency(&mut summaries);
+        summaries
+    }
+}
+
+/// Messages added after the mark named `label`, or `None` if no such mark exists.
+/// A mark past the end (the conversation was cleared or rewound) yields no messages.
+pub fn messages_after_mark<'a>(
+    messages: &'a [Message],
+    marks: &[Mark],
+    label: &str,
+) -> Option<&'a [Message]> {
+    let mark = marks.iter().find(|m| m.label == label)?;
+    Some(&messages[mark.index.min(messages.len())..])
+}
+
+/// Keep marks pointing at the same messages after `removed` messages starting at
+/// `start` were replaced by `inserted` new ones. Marks inside the removed range
+/// move to just after the replacement.
+pub fn shift_marks(marks: &mut [Mark], start: usize, removed: usize, inserted: usize) {
+    for mark in marks.iter_mut().filter(|m| m.index > start) {
+        mark.index = mark.index.saturating_sub(removed).max(start) + inserted;
+    }
+}
+
+/// Parse an RFC 3339 session timestamp; empty or malformed values yield `None`
+pub fn parse_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+impl SessionSummary {
+    /// `{name, path, messages}` for `slab sessions --format json`; `messages` is
+    /// left out for files that couldn't be read
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::json!({
+            "name": self.name,
+            "path": self.path.display().to_string(),
+        });
+        if let Some(count) = self.message_count {
+            value["messages"] = count.into();
+        }
+        value
+    }
+}
+
+/// Most recently updated first; sessions without a timestamp go last, by name
+fn sort_by_recency(summaries: &mut [SessionSummary]) {
+    summaries.sort_by(|a, b| {
+        parse_timestamp(&b.updated_at)
+            .cmp(&parse_timestamp(&a.updated_at))
+            .then_with(|| a.name.cmp(&b.name))
+    });
 }
 
 /// Sanitize a filename to remove problematic characters
@@ -153,6 +330,152 @@ fn sanitize_filename(name: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_session_without_timestamps_still_loads() {
+        let json = r#"{"name":"old","model":"llama3","messages":[{"role":"user","content":"hi"}]}"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert_eq!(session.messages.len(), 1);
+        assert!(session.created_at.is_empty());
+        assert!(session.updated_at.is_empty());
+
+        let json = r#"{"name":"old","model":"llama3","messages":[],"updated_at":"2025-01-02T03:04:05+00:00"}"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert!(session.created_at.is_empty());
+        assert!(parse_timestamp(&session.updated_at).is_some());
+    }
+
+    #[test]
+    fn test_messages_after_mark() {
+        let messages: Vec<Message> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|c| Message::user(c.to_string()))
+            .collect();
+        let marks = vec![
+            Mark {
+                label: "before refactor".to_string(),
+                index: 2,
+            },
+            Mark {
+                label: "stale".to_string(),
+                index: 10,
+            },
+        ];
+
+        let slice = messages_after_mark(&messages, &marks, "before refactor").unwrap();
+        let contents: Vec<&str> = slice.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["c", "d"]);
+
+        assert!(messages_after_mark(&messages, &marks, "stale")
+            .unwrap()
+            .is_empty());
+        assert!(messages_after_mark(&messages, &marks, "missing").is_none());
+    }
+
+    #[test]
+    fn test_shift_marks() {
+        let mark = |index: usize| Mark {
+            label: index.to_string(),
+            index,
+        };
+        let mut marks = vec![mark(1), mark(3), mark(6)];
+
+        // Messages 1..5 pruned: a mark inside the range lands where they were
+        shift_marks(&mut marks, 1, 4, 0);
+        let indices: Vec<usize> = marks.iter().map(|m| m.index).collect();
+        assert_eq!(indices, vec![1, 1, 2]);
+
+        // The first two messages compacted into one summary
+        let mut marks = vec![mark(0), mark(1), mark(2), mark(5)];
+        shift_marks(&mut marks, 0, 2, 1);
+        let indices: Vec<usize> = marks.iter().map(|m| m.index).collect();
+        assert_eq!(indices, vec![0, 1, 1, 4]);
+    }
+
+    #[test]
+    fn test_summaries_sorted_by_recency() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, updated_at: Option<&str>| {
+            let mut session = Session::new(name, "qwen2.5:7b");
+            session.updated_at = updated_at.unwrap_or_default().to_string();
+            fs::write(
+                dir.path().join(format!("{}.json", name)),
+                serde_json::to_string(&session).unwrap(),
+            )
+            .unwrap();
+        };
+        write("older", Some("2026-01-01T10:00:00+00:00"));
+        write("legacy", None);
+        // Later instant despite the smaller wall-clock time, thanks to the offset
+        write("newest", Some("2026-01-01T09:00:00-05:00"));
+        write("middle", Some("2026-01-01T12:00:00+00:00"));
+        fs::write(dir.path().join("notes.txt"), "not a session").unwrap();
+        Session::new(AUTOSAVE_NAME, "qwen2.5:7b")
+            .save_autosave_in(dir.path())
+            .unwrap();
+
+        let names: Vec<String> = Session::summaries_in(dir.path())
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["newest", "middle", "older", "legacy"]);
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = Session::new("work", "qwen2.5:7b");
+        session.messages.push(Message::user("hi"));
+        fs::write(
+            dir.path().join("work.json"),
+            serde_json::to_string(&session).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+
+        let json: Vec<serde_json::Value> = Session::summaries_in(dir.path())
+            .iter()
+            .map(|s| s.to_json())
+            .collect();
+        let path = dir.path().join("work.json").display().to_string();
+        assert!(json.contains(&serde_json::json!({"name": "work", "path": path, "messages": 1})));
+        let broken = json.iter().find(|v| v["name"] == "broken").unwrap();
+        assert!(broken.get("messages").is_none());
+    }
+
+    #[test]
+    fn test_load_last_prefers_newer_autosave() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Session::load_last_in(dir.path()).is_err());
+
+        let mut named = Session::new("work", "qwen2.5:7b");
+        named.updated_at = "2026-01-01T10:00:00+00:00".to_string();
+        fs::write(
+            dir.path().join("work.json"),
+            serde_json::to_string(&named).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.path().join("_last"), "work").unwrap();
+
+        let mut autosave = Session::new(AUTOSAVE_NAME, "qwen2.5:7b");
+        autosave.updated_at = "2026-01-01T09:00:00+00:00".to_string();
+        autosave.save_autosave_in(dir.path()).unwrap();
+        assert_eq!(Session::load_last_in(dir.path()).unwrap().name, "work");
+
+        autosave.updated_at = "2026-01-01T11:00:00+00:00".to_string();
+        autosave.save_autosave_in(dir.path()).unwrap();
+        assert_eq!(
+            Session::load_last_in(dir.path()).unwrap().name,
+            AUTOSAVE_NAME
+        );
+
+        // Without a "last" marker the autosave is still recovered
+        fs::remove_file(dir.path().join("_last")).unwrap();
+        assert_eq!(
+            Session::load_last_in(dir.path()).unwrap().name,
+            AUTOSAVE_NAME
+        );
+    }
+
     #[test]
     fn test_sanitize_filename() {
         assert_eq!(sanitize_filename("my:session"), "my_session");
@@ -36,6 +36,10 @@ pub struct PromptTemplate {
     /// Maximum number of phase loop iterations (default 10)
     #[serde(default)]
     pub max_phases: Option<usize>,
+
+    /// Paths or globs added to context before the template is rendered
+    #[serde(default)]
+    pub auto_add: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +92,12 @@ pub struct TemplatePhase {
     pub feedback: PhaseFeedback,
     #[serde(default)]
     pub follow_up: Option<String>,
+    /// Working directory for the command; supports `{{file}}`/`{{files}}`
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Extra environment variables set for the command
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
 /// Manages prompt templates
@@ -118,28 +128,42 @@ impl TemplateManager {
 
     /// Load all YAML templates from a directory
     pub fn load_from_directory(&mut self, dir: &Path) {
-        let pattern = dir.join("*.yaml");
-        let pattern_str = pattern.to_string_lossy();
+        for (path, e) in self.load_directory_files(dir) {
+            eprintln!("Warning: Failed to load template {:?}: {}", path, e);
+        }
+    }
 
-        if let Ok(entries) = glob::glob(&pattern_str) {
-            for entry in entries.flatten() {
-                if let Err(e) = self.load_template(&entry) {
-                    eprintln!("Warning: Failed to load template {:?}: {}", entry, e);
+    /// Load every `.yaml`/`.yml` file in `dir`, returning the ones that failed
+    fn load_directory_files(&mut self, dir: &Path) -> Vec<(PathBuf, String)> {
+        let mut failures = Vec::new();
+        for ext in ["yaml", "yml"] {
+            let pattern = dir.join(format!("*.{}", ext));
+            let pattern_str = pattern.to_string_lossy();
+
+            if let Ok(entries) = glob::glob(&pattern_str) {
+                for entry in entries.flatten() {
+                    if let Err(e) = self.load_template(&entry) {
+                        failures.push((entry, e));
+                    }
                 }
             }
         }
+        failures
+    }
 
-        // Also try .yml extension
-        let pattern = dir.join("*.yml");
-        let pattern_str = pattern.to_string_lossy();
+    /// Drop every template and load the defaults and `dirs` again, picking up
+    /// edited, added and deleted files. Returns the failed files.
+    pub fn reload(&mut self, dirs: &[PathBuf]) -> Vec<(PathBuf, String)> {
+        *self = Self::new();
+        self.load_defaults();
 
-        if let Ok(entries) = glob::glob(&pattern_str) {
-            for entry in entries.flatten() {
-                if let Err(e) = self.load_template(&entry) {
-                    eprintln!("Warning: Failed to load template {:?}: {}", entry, e);
-                }
+        let mut failures = Vec::new();
+        for dir in dirs {
+            if dir.is_dir() {
+                failures.extend(self.load_directory_files(dir));
             }
         }
+        failures
     }
 
     /// Load a single template from a YAML file
@@ -211,56 +235,34 @@ impl TemplateManager {
             .find(|t| t.name == template_name || t.command.trim_start_matches('/') == template_name)
             .ok_or_else(|| format!("Template not found: {}", template_name))?;
 
-        // Build the render context
-        let mut render_data = HashMap::new();
-
-        // Add user-provided variables
-        for (key, value) in variables {
-            render_data.insert(key.clone(), value.clone());
+        // Declared variables without a default must be supplied by the caller
+        let missing: Vec<String> = template
+            .variables
+            .iter()
+            .filter(|v| v.default.is_none() && !variables.contains_key(&v.name))
+            .map(|v| match &v.description {
+                Some(desc) => format!("{} ({})", v.name, desc),
+                None => v.name.clone(),
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Missing required variable(s): {}. Pass them as name=value",
+                missing.join(", ")
+            ));
         }
 
-        // Add default values for missing variables
-        for var in &template.variables {
-            if !render_data.contains_key(&var.name) {
-                if let Some(default) = &var.default {
-                    render_data.insert(var.name.clone(), default.clone());
-                }
-            }
-        }
-
-        // Add built-in variables
-        render_data.insert(
-            "date".to_string(),
-            chrono::Local::now().format("%Y-%m-%d").to_string(),
-        );
-        render_data.insert(
-            "datetime".to_string(),
-            chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
-        );
+        // Built-ins first, then template defaults, then user-provided variables
+        let mut render_data = builtin_variables(context);
 
-        // Add project name (current directory name)
-        if let Ok(cwd) = std::env::current_dir() {
-            if let Some(name) = cwd.file_name().and_then(|n| n.to_str()) {
-                render_data.insert("project".to_string(), name.to_string());
+        for var in &template.variables {
+            if let Some(default) = &var.default {
+                render_data.insert(var.name.clone(), default.clone());
             }
         }
 
-        // Add files from context
-        let files = context.list_files();
-        if !files.is_empty() {
-            let mut files_content = String::new();
-            for path in files {
-                if let Some(content) = context.get_file_content(path) {
-                    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("txt");
-                    files_content.push_str(&format!(
-                        "### {}\n```{}\n{}\n```\n\n",
-                        path.display(),
-                        ext,
-                        content
-                    ));
-                }
-            }
-            render_data.insert("files".to_string(), files_content);
+        for (key, value) in variables {
+            render_data.insert(key.clone(), value.clone());
         }
 
         // Render the template
@@ -282,6 +284,63 @@ impl Default for TemplateManager {
     }
 }
 
+/// Variables every template can use without declaring them:
+///
+/// - `date` / `datetime`: local time, `YYYY-MM-DD` and `YYYY-MM-DD HH:MM`
+/// - `project`: name of the current directory
+/// - `package`: `[package] name` from `Cargo.toml` in the current directory, else `project`
+/// - `files`: every context file as a headed, fenced block (empty without files)
+/// - `content`: positional text passed to the template command (empty when none)
+///
+/// Template defaults and user-supplied `key=value` pairs override these.
+pub fn builtin_variables(context: &ContextManager) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    let now = chrono::Local::now();
+    vars.insert("date".to_string(), now.format("%Y-%m-%d").to_string());
+    vars.insert(
+        "datetime".to_string(),
+        now.format("%Y-%m-%d %H:%M").to_string(),
+    );
+
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let project = cwd
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let package = cargo_package_name(&cwd).unwrap_or_else(|| project.clone());
+    vars.insert("project".to_string(), project);
+    vars.insert("package".to_string(), package);
+
+    let mut files_content = String::new();
+    for path in context.list_files() {
+        if let Some(content) = context.get_file_content(path) {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("txt");
+            files_content.push_str(&format!(
+                "### {}\n```{}\n{}\n```\n\n",
+                path.display(),
+                ext,
+                content
+            ));
+        }
+    }
+    vars.insert("files".to_string(), files_content);
+    vars.insert("content".to_string(), String::new());
+
+    vars
+}
+
+/// Read `[package] name` from `dir/Cargo.toml`, if there is one
+fn cargo_package_name(dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let value: toml::Value = toml::from_str(&manifest).ok()?;
+    value
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
 /// Get the default built-in templates
 fn get_default_templates() -> Vec<PromptTemplate> {
     vec![
@@ -318,6 +377,7 @@ Please identify:
             phases: vec![],
             phases_follow_up: None,
             max_phases: None,
+            auto_add: Vec::new(),
         },
         PromptTemplate {
             name: "refactor".to_string(),
@@ -348,6 +408,7 @@ For each suggestion:
             phases: vec![],
             phases_follow_up: None,
             max_phases: None,
+            auto_add: Vec::new(),
         },
         PromptTemplate {
             name: "test_gen".to_string(),
@@ -382,6 +443,7 @@ Use descriptive test names that explain what is being tested."#
             phases: vec![],
             phases_follow_up: None,
             max_phases: None,
+            auto_add: Vec::new(),
         },
         PromptTemplate {
             name: "fix".to_string(),
@@ -389,10 +451,13 @@ Use descriptive test names that explain what is being tested."#
             description: "Fix a bug or issue".to_string(),
             variables: vec![TemplateVariable {
                 name: "issue".to_string(),
-                default: None,
-                description: Some("Description of the bug or issue".to_string()),
+                default: Some(String::new()),
+                description: Some(
+                    "Description of the bug or issue (defaults to the text after /fix)"
+                        .to_string(),
+                ),
             }],
-            prompt: r#"Please help fix the following issue: {{issue}}
+            prompt: r#"Please help fix the following issue: {{#if issue}}{{issue}}{{else}}{{content}}{{/if}}
 
 {{#if files}}
 ## Relevant Code
@@ -409,6 +474,7 @@ Please:
             phases: vec![],
             phases_follow_up: None,
             max_phases: None,
+            auto_add: Vec::new(),
         },
         PromptTemplate {
             name: "document".to_string(),
@@ -440,6 +506,7 @@ Include:
             phases: vec![],
             phases_follow_up: None,
             max_phases: None,
+            auto_add: Vec::new(),
         },
     ]
 }
@@ -496,4 +563,109 @@ mod tests {
         let rendered = result.unwrap();
         assert!(rendered.contains("fn main() {}"));
     }
+
+    #[test]
+    fn test_auto_add_deserializes() {
+        let yaml = "name: c_to_rust\ncommand: /c-to-rust\ndescription: Port C\nauto_add: [\"bindings.rs\", \"src/ffi/*.rs\"]\nprompt: \"{{files}}\"\n";
+        let template: PromptTemplate = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(template.auto_add, vec!["bindings.rs", "src/ffi/*.rs"]);
+
+        let yaml = "name: plain\ncommand: /plain\ndescription: No files\nprompt: hi\n";
+        let template: PromptTemplate = serde_yaml::from_str(yaml).unwrap();
+        assert!(template.auto_add.is_empty());
+    }
+
+    #[test]
+    fn test_render_populates_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.yaml");
+        fs::write(
+            &path,
+            "name: report\ncommand: /report\ndescription: Save a report\nprompt: \"path=.slab/reviews/{{package}}.md content=[{{content}}]\"\n",
+        )
+        .unwrap();
+
+        let mut manager = TemplateManager::new();
+        manager.load_template(&path).unwrap();
+        let context = ContextManager::new(4096, PathBuf::from("."));
+
+        // Run from this crate, so the package comes from Cargo.toml
+        let rendered = manager.render("report", &HashMap::new(), &context).unwrap();
+        assert_eq!(rendered, "path=.slab/reviews/slab.md content=[]");
+
+        let mut vars = HashMap::new();
+        vars.insert("package".to_string(), "sonar".to_string());
+        let rendered = manager.render("report", &vars, &context).unwrap();
+        assert!(rendered.starts_with("path=.slab/reviews/sonar.md"));
+    }
+
+    #[test]
+    fn test_render_requires_variables_without_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("port.yaml");
+        fs::write(
+            &path,
+            "name: port
+command: /port
+description: Port code
+variables:
+  - name: language
+    description: Source language
+  - name: target
+  - name: style
+    default: idiomatic
+prompt: \"{{language}} -> {{target}} ({{style}})\"\n",
+        )
+        .unwrap();
+
+        let mut manager = TemplateManager::new();
+        manager.load_template(&path).unwrap();
+        let context = ContextManager::new(4096, PathBuf::from("."));
+
+        let err = manager
+            .render("port", &HashMap::new(), &context)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "Missing required variable(s): language (Source language), target. Pass them as name=value"
+        );
+
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), "C".to_string());
+        let err = manager.render("port", &vars, &context).unwrap_err();
+        assert!(
+            err.contains("target") && !err.contains("language"),
+            "{}",
+            err
+        );
+
+        vars.insert("target".to_string(), "Rust".to_string());
+        let rendered = manager.render("port", &vars, &context).unwrap();
+        assert_eq!(rendered, "C -> Rust (idiomatic)");
+    }
+
+    #[test]
+    fn test_fix_takes_issue_or_content() {
+        let mut manager = TemplateManager::new();
+        manager.load_defaults();
+        let context = ContextManager::new(4096, PathBuf::from("."));
+
+        let mut vars = HashMap::new();
+        vars.insert("content".to_string(), "parser panics".to_string());
+        let rendered = manager.render("fix", &vars, &context).unwrap();
+        assert!(rendered.contains("issue: parser panics"));
+
+        vars.insert("issue".to_string(), "lexer hangs".to_string());
+        let rendered = manager.render("fix", &vars, &context).unwrap();
+        assert!(rendered.contains("issue: lexer hangs"));
+    }
+
+    #[test]
+    fn test_builtin_variables_are_all_present() {
+        let context = ContextManager::new(4096, PathBuf::from("."));
+        let vars = builtin_variables(&context);
+        for name in ["date", "datetime", "project", "package", "files", "content"] {
+            assert!(vars.contains_key(name), "missing built-in {}", name);
+        }
+    }
 }
@@ -1,9 +1,11 @@
 use console::style;
+use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use crate::config::Config;
@@ -26,6 +28,14 @@ pub struct TestCase {
     #[serde(default)]
     pub model: Option<String>,
 
+    /// Optional sampling options (temperature, top_p, seed, num_ctx); unset fields use defaults
+    #[serde(default)]
+    pub options: Option<ModelOptions>,
+
+    /// Output format constraint sent with the request, e.g. "json" (pairs with `valid_json`)
+    #[serde(default)]
+    pub format: Option<String>,
+
     /// Assertions to check against the response
     pub assertions: Vec<Assertion>,
 
@@ -42,6 +52,10 @@ fn default_timeout() -> u64 {
     60
 }
 
+fn default_capture_group() -> usize {
+    1
+}
+
 /// An assertion to validate against LLM response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -52,20 +66,64 @@ pub enum Assertion {
     /// Response must not contain this string
     NotContains { value: String },
 
+    /// Response must contain every one of these strings
+    ContainsAll { values: Vec<String> },
+
+    /// Response must contain at least one of these strings
+    ContainsAny { values: Vec<String> },
+
     /// Response must match this regex pattern
     Regex { pattern: String },
 
     /// Response must not match this regex pattern
     NotRegex { pattern: String },
 
+    /// Capture group `group` of the first match of `pattern` must equal `equals`
+    /// and/or match the `matches` regex
+    RegexCapture {
+        pattern: String,
+        #[serde(default = "default_capture_group")]
+        group: usize,
+        #[serde(default)]
+        equals: Option<String>,
+        #[serde(default)]
+        matches: Option<String>,
+    },
+
     /// Response time must be under this many milliseconds
     MaxLatency { ms: u64 },
 
+    /// Response time must be at least this many milliseconds
+    MinLatency { ms: u64 },
+
     /// Response must be valid JSON
     ValidJson,
 
+    /// Response must not parse as JSON
+    NotValidJson,
+
+    /// Response must be JSON matching a JSON-schema subset: `type`, `required`,
+    /// `properties` and `items`
+    JsonSchema { schema: serde_json::Value },
+
+    /// Response must be JSON with a value at this dotted path (e.g. `result.items.0.name`),
+    /// optionally equal to a JSON value and/or containing a string
+    JsonPath {
+        path: String,
+        #[serde(default)]
+        equals: Option<serde_json::Value>,
+        #[serde(default)]
+        contains: Option<String>,
+    },
+
     /// Response length must be between min and max characters
     LengthBetween { min: usize, max: usize },
+
+    /// Number of non-empty lines must be between min and max
+    LineCount { min: usize, max: usize },
+
+    /// Number of whitespace-separated words must be between min and max
+    WordCount { min: usize, max: usize },
 }
 
 impl Assertion {
@@ -92,6 +150,32 @@ impl Assertion {
                     ))
                 }
             }
+            Assertion::ContainsAll { values } => {
+                let missing: Vec<String> = values
+                    .iter()
+                    .filter(|v| !response.contains(v.as_str()))
+                    .map(|v| format!("'{}'", truncate(v, 30)))
+                    .collect();
+                if missing.is_empty() {
+                    AssertionResult::Pass
+                } else {
+                    AssertionResult::Fail(format!("Response is missing: {}", missing.join(", ")))
+                }
+            }
+            Assertion::ContainsAny { values } => {
+                if values.iter().any(|v| response.contains(v.as_str())) {
+                    AssertionResult::Pass
+                } else {
+                    let expected: Vec<String> = values
+                        .iter()
+                        .map(|v| format!("'{}'", truncate(v, 30)))
+                        .collect();
+                    AssertionResult::Fail(format!(
+                        "Response contains none of: {}",
+                        expected.join(", ")
+                    ))
+                }
+            }
             Assertion::Regex { pattern } => match Regex::new(pattern) {
                 Ok(re) => {
                     if re.is_match(response) {
@@ -118,6 +202,55 @@ impl Assertion {
                 }
                 Err(e) => AssertionResult::Fail(format!("Invalid regex: {}", e)),
             },
+            Assertion::RegexCapture {
+                pattern,
+                group,
+                equals,
+                matches,
+            } => {
+                let re = match Regex::new(pattern) {
+                    Ok(re) => re,
+                    Err(e) => return AssertionResult::Fail(format!("Invalid regex: {}", e)),
+                };
+                let Some(caps) = re.captures(response) else {
+                    return AssertionResult::Fail(format!(
+                        "Response does not match pattern: {}",
+                        truncate(pattern, 50)
+                    ));
+                };
+                let Some(captured) = caps.get(*group).map(|m| m.as_str()) else {
+                    return AssertionResult::Fail(format!(
+                        "Group {} did not capture anything in /{}/",
+                        group,
+                        truncate(pattern, 50)
+                    ));
+                };
+                if let Some(expected) = equals {
+                    if captured != expected {
+                        return AssertionResult::Fail(format!(
+                            "Group {} is '{}', expected '{}'",
+                            group,
+                            truncate(captured, 50),
+                            expected
+                        ));
+                    }
+                }
+                if let Some(inner) = matches {
+                    match Regex::new(inner) {
+                        Ok(inner_re) if !inner_re.is_match(captured) => {
+                            return AssertionResult::Fail(format!(
+                                "Group {} is '{}', which does not match /{}/",
+                                group,
+                                truncate(captured, 50),
+                                truncate(inner, 50)
+                            ));
+                        }
+                        Ok(_) => {}
+                        Err(e) => return AssertionResult::Fail(format!("Invalid regex: {}", e)),
+                    }
+                }
+                AssertionResult::Pass
+            }
             Assertion::MaxLatency { ms } => {
                 if latency_ms <= *ms {
                     AssertionResult::Pass
@@ -128,10 +261,69 @@ impl Assertion {
                     ))
                 }
             }
+            Assertion::MinLatency { ms } => {
+                if latency_ms >= *ms {
+                    AssertionResult::Pass
+                } else {
+                    AssertionResult::Fail(format!(
+                        "Response took {}ms, min expected: {}ms",
+                        latency_ms, ms
+                    ))
+                }
+            }
             Assertion::ValidJson => match serde_json::from_str::<serde_json::Value>(response) {
                 Ok(_) => AssertionResult::Pass,
                 Err(e) => AssertionResult::Fail(format!("Invalid JSON: {}", e)),
             },
+            Assertion::NotValidJson => match serde_json::from_str::<serde_json::Value>(response) {
+                Ok(_) => AssertionResult::Fail("Response is valid JSON".to_string()),
+                Err(_) => AssertionResult::Pass,
+            },
+            Assertion::JsonSchema { schema } => {
+                let root = match serde_json::from_str::<serde_json::Value>(response) {
+                    Ok(root) => root,
+                    Err(e) => return AssertionResult::Fail(format!("Invalid JSON: {}", e)),
+                };
+                match schema_error(&root, schema, "$") {
+                    Some(error) => AssertionResult::Fail(error),
+                    None => AssertionResult::Pass,
+                }
+            }
+            Assertion::JsonPath {
+                path,
+                equals,
+                contains,
+            } => {
+                let root = match serde_json::from_str::<serde_json::Value>(response) {
+                    Ok(root) => root,
+                    Err(e) => return AssertionResult::Fail(format!("Invalid JSON: {}", e)),
+                };
+                let Some(value) = json_path(&root, path) else {
+                    return AssertionResult::Fail(format!("No value at '{}'", path));
+                };
+                if let Some(expected) = equals {
+                    if !json_equals(value, expected) {
+                        return AssertionResult::Fail(format!(
+                            "'{}' is {}, expected {}",
+                            path,
+                            truncate(&value.to_string(), 50),
+                            truncate(&expected.to_string(), 50)
+                        ));
+                    }
+                }
+                let text = json_text(value);
+                if let Some(needle) = contains {
+                    if !text.contains(needle.as_str()) {
+                        return AssertionResult::Fail(format!(
+                            "'{}' is '{}', expected it to contain '{}'",
+                            path,
+                            truncate(&text, 50),
+                            needle
+                        ));
+                    }
+                }
+                AssertionResult::Pass
+            }
             Assertion::LengthBetween { min, max } => {
                 let len = response.len();
                 if len >= *min && len <= *max {
@@ -143,23 +335,204 @@ impl Assertion {
                     ))
                 }
             }
+            Assertion::LineCount { min, max } => {
+                let lines = response.lines().filter(|l| !l.trim().is_empty()).count();
+                if lines >= *min && lines <= *max {
+                    AssertionResult::Pass
+                } else {
+                    AssertionResult::Fail(format!(
+                        "Response has {} non-empty line(s), expected [{}, {}]",
+                        lines, min, max
+                    ))
+                }
+            }
+            Assertion::WordCount { min, max } => {
+                let words = response.split_whitespace().count();
+                if words >= *min && words <= *max {
+                    AssertionResult::Pass
+                } else {
+                    AssertionResult::Fail(format!(
+                        "Response has {} word(s), expected [{}, {}]",
+                        words, min, max
+                    ))
+                }
+            }
         }
     }
 
+    /// Whether the assertion checks response latency rather than content
+    pub fn is_latency(&self) -> bool {
+        matches!(
+            self,
+            Assertion::MaxLatency { .. } | Assertion::MinLatency { .. }
+        )
+    }
+
     /// Get a short description of the assertion
     pub fn description(&self) -> String {
         match self {
             Assertion::Contains { value } => format!("contains '{}'", truncate(value, 30)),
             Assertion::NotContains { value } => format!("not contains '{}'", truncate(value, 30)),
+            Assertion::ContainsAll { values } => {
+                format!("contains all of [{}]", count_values(values.len()))
+            }
+            Assertion::ContainsAny { values } => {
+                format!("contains any of [{}]", count_values(values.len()))
+            }
             Assertion::Regex { pattern } => format!("matches /{}/", truncate(pattern, 30)),
             Assertion::NotRegex { pattern } => format!("not matches /{}/", truncate(pattern, 30)),
+            Assertion::RegexCapture {
+                pattern,
+                group,
+                equals,
+                matches,
+            } => {
+                let capture = format!("/{}/ group {}", truncate(pattern, 30), group);
+                match (equals, matches) {
+                    (Some(v), _) => format!("{} == '{}'", capture, truncate(v, 30)),
+                    (None, Some(re)) => format!("{} matches /{}/", capture, truncate(re, 30)),
+                    (None, None) => format!("{} captured", capture),
+                }
+            }
             Assertion::MaxLatency { ms } => format!("latency <= {}ms", ms),
+            Assertion::MinLatency { ms } => format!("latency >= {}ms", ms),
             Assertion::ValidJson => "valid JSON".to_string(),
+            Assertion::NotValidJson => "not valid JSON".to_string(),
+            Assertion::JsonSchema { schema } => match schema.get("type").and_then(|t| t.as_str()) {
+                Some(kind) => format!("JSON {} matching schema", kind),
+                None => "JSON matching schema".to_string(),
+            },
+            Assertion::JsonPath {
+                path,
+                equals,
+                contains,
+            } => match (equals, contains) {
+                (Some(v), Some(c)) => format!(
+                    "{} == {} and contains '{}'",
+                    path,
+                    truncate(&v.to_string(), 30),
+                    truncate(c, 30)
+                ),
+                (Some(v), None) => format!("{} == {}", path, truncate(&v.to_string(), 30)),
+                (None, Some(c)) => format!("{} contains '{}'", path, truncate(c, 30)),
+                (None, None) => format!("{} exists", path),
+            },
             Assertion::LengthBetween { min, max } => format!("length in [{}, {}]", min, max),
+            Assertion::LineCount { min, max } => format!("lines in [{}, {}]", min, max),
+            Assertion::WordCount { min, max } => format!("words in [{}, {}]", min, max),
         }
     }
 }
 
+/// Strings without their quotes; anything else by its JSON text
+fn json_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `json_path` equality: identical JSON values match, numbers compare numerically
+/// (`1.0` equals `1`, as does `"1"`), and values of different types compare by text,
+/// so a quoted `equals: "2"` in YAML still matches the number 2
+fn json_equals(actual: &serde_json::Value, expected: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    if actual == expected {
+        return true;
+    }
+    let number = |v: &Value| match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    match (actual, expected) {
+        (Value::String(_), Value::String(_)) => false,
+        (Value::Number(_), _) | (_, Value::Number(_)) => match (number(actual), number(expected)) {
+            (Some(a), Some(b)) => a == b,
+            _ => json_text(actual) == json_text(expected),
+        },
+        _ => json_text(actual) == json_text(expected),
+    }
+}
+
+/// Follow a dotted path through objects and arrays; numeric segments index arrays
+fn json_path<'a>(root: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(root, |value, segment| match value {
+            serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => value.get(segment),
+        })
+}
+
+/// The first way `value` breaks `schema`, checking `type` (a name or a list of names),
+/// `required`, `properties` and `items`; other keywords are ignored
+fn schema_error(value: &serde_json::Value, schema: &serde_json::Value, at: &str) -> Option<String> {
+    let types: Vec<&str> = match schema.get("type") {
+        Some(serde_json::Value::String(kind)) => vec![kind.as_str()],
+        Some(serde_json::Value::Array(kinds)) => kinds.iter().filter_map(|k| k.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|kind| json_type_matches(value, kind)) {
+        return Some(format!(
+            "{} is {}, expected {}",
+            at,
+            json_type_name(value),
+            types.join(" or ")
+        ));
+    }
+
+    if let Some(object) = value.as_object() {
+        if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+            let missing: Vec<&str> = required
+                .iter()
+                .filter_map(|key| key.as_str())
+                .filter(|key| !object.contains_key(*key))
+                .collect();
+            if !missing.is_empty() {
+                return Some(format!("{} is missing: {}", at, missing.join(", ")));
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+            for (key, property) in properties {
+                if let Some(field) = object.get(key) {
+                    if let Some(error) = schema_error(field, property, &format!("{}.{}", at, key)) {
+                        return Some(error);
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            if let Some(error) = schema_error(item, item_schema, &format!("{}[{}]", at, i)) {
+                return Some(error);
+            }
+        }
+    }
+    None
+}
+
+fn json_type_matches(value: &serde_json::Value, kind: &str) -> bool {
+    match kind {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        other => json_type_name(value) == other,
+    }
+}
+
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AssertionResult {
     Pass,
@@ -200,6 +573,48 @@ pub fn load_tests_from_directory(dir: &Path) -> Vec<TestCase> {
     tests
 }
 
+/// Whether a changed path is a test file that should trigger a `--watch` re-run
+pub fn is_test_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml") | Some("yml")
+    )
+}
+
+/// Coalesces bursts of file events for `slab test --watch`: changes are collected
+/// until no new event has arrived for `window`, then released together.
+pub struct Debouncer {
+    window: Duration,
+    pending: BTreeSet<PathBuf>,
+    last_event: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: BTreeSet::new(),
+            last_event: None,
+        }
+    }
+
+    /// Record a changed path observed at `now`
+    pub fn push(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path);
+        self.last_event = Some(now);
+    }
+
+    /// The changed paths, once the burst has been quiet for the debounce window
+    pub fn ready(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+        let last = self.last_event?;
+        if now.duration_since(last) < self.window {
+            return None;
+        }
+        self.last_event = None;
+        Some(std::mem::take(&mut self.pending).into_iter().collect())
+    }
+}
+
 /// Load test cases from a single file
 fn load_test_file(path: &Path) -> Result<Vec<TestCase>, String> {
     let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
@@ -217,12 +632,58 @@ fn load_test_file(path: &Path) -> Result<Vec<TestCase>, String> {
     Err("Failed to parse as test case or test list".to_string())
 }
 
+/// Which tests `slab test` runs. `pattern` (`--filter`) matches a substring of
+/// the name or any tag; `--tag` values must all be present, and `--tag '!x'`
+/// excludes tests tagged `x`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestFilter {
+    pub pattern: Option<String>,
+    pub include_tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+}
+
+impl TestFilter {
+    /// Build a filter from `--filter` and the raw `--tag` values
+    pub fn new(pattern: Option<&str>, tags: &[String]) -> Self {
+        let mut filter = Self {
+            pattern: pattern.map(String::from),
+            ..Self::default()
+        };
+        for tag in tags {
+            let tag = tag.trim();
+            match tag.strip_prefix('!') {
+                Some(excluded) if !excluded.is_empty() => {
+                    filter.exclude_tags.push(excluded.to_string())
+                }
+                Some(_) => {}
+                None if !tag.is_empty() => filter.include_tags.push(tag.to_string()),
+                None => {}
+            }
+        }
+        filter
+    }
+
+    pub fn matches(&self, test: &TestCase) -> bool {
+        let has_tag = |tag: &String| test.tags.contains(tag);
+        let pattern_matches = self.pattern.as_deref().is_none_or(|pattern| {
+            test.name.contains(pattern) || test.tags.iter().any(|tag| tag.contains(pattern))
+        });
+        pattern_matches
+            && self.include_tags.iter().all(has_tag)
+            && !self.exclude_tags.iter().any(has_tag)
+    }
+}
+
 /// Test runner that executes tests against any LLM backend.
 pub struct TestRunner<B: LlmBackend> {
     client: B,
     config: Config,
     default_model: String,
     verbose: bool,
+    /// Stop the run after this many failures (`--fail-fast` is 1)
+    bail: Option<usize>,
+    /// How many tests run at once (`--jobs`)
+    jobs: usize,
 }
 
 impl<B: LlmBackend> TestRunner<B> {
@@ -232,29 +693,33 @@ impl<B: LlmBackend> TestRunner<B> {
             config,
             default_model,
             verbose,
+            bail: None,
+            jobs: 1,
         }
     }
 
+    /// Run up to `jobs` tests concurrently
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Stop running tests once `bail` of them have failed
+    pub fn with_bail(mut self, bail: Option<usize>) -> Self {
+        self.bail = bail.filter(|n| *n > 0);
+        self
+    }
+
     /// Run all tests and return results.
     pub async fn run_tests(
         &self,
         tests: &[TestCase],
-        filter: Option<&str>,
+        filter: &TestFilter,
         model_override: Option<&str>,
     ) -> Vec<TestResult> {
         let mut results = Vec::new();
 
-        // Filter tests if pattern provided
-        let tests_to_run: Vec<&TestCase> = tests
-            .iter()
-            .filter(|t| {
-                if let Some(pattern) = filter {
-                    t.name.contains(pattern) || t.tags.iter().any(|tag| tag.contains(pattern))
-                } else {
-                    true
-                }
-            })
-            .collect();
+        let tests_to_run: Vec<&TestCase> = tests.iter().filter(|t| filter.matches(t)).collect();
 
         if tests_to_run.is_empty() {
             println!("{}", style("No tests to run.").yellow());
@@ -267,6 +732,19 @@ impl<B: LlmBackend> TestRunner<B> {
             tests_to_run.len()
         );
 
+        // Concurrent requests queue up on the server, which would count against
+        // latency assertions; run those suites one test at a time
+        let has_latency = tests_to_run
+            .iter()
+            .any(|t| t.assertions.iter().any(Assertion::is_latency));
+        let jobs = if has_latency { 1 } else { self.jobs };
+        if jobs < self.jobs {
+            println!(
+                "{}",
+                style("Latency assertions present; running tests one at a time.").dim()
+            );
+        }
+
         let progress = ProgressBar::new(tests_to_run.len() as u64);
         progress.set_style(
             ProgressStyle::default_bar()
@@ -276,26 +754,49 @@ impl<B: LlmBackend> TestRunner<B> {
         );
         progress.set_prefix("Testing");
 
-        for test in tests_to_run {
-            progress.set_message(truncate(&test.name, 30));
-
-            let result = self.run_single_test(test, model_override).await;
-            results.push(result);
-
+        let total = tests_to_run.len();
+        let mut failures = 0;
+        let mut finished = Vec::new();
+        let mut running = futures_util::stream::iter(tests_to_run.into_iter().enumerate())
+            .map(|(index, test)| async move {
+                (index, self.run_single_test(test, model_override).await)
+            })
+            .buffer_unordered(jobs);
+        while let Some((index, result)) = running.next().await {
+            progress.set_message(truncate(&result.name, 30));
             progress.inc(1);
+
+            let passed = result.passed;
+            let name = result.name.clone();
+            finished.push((index, result));
+
+            if !passed {
+                failures += 1;
+                // Dropping the stream below cancels any tests still in flight
+                if self.bail.is_some_and(|bail| failures >= bail) {
+                    progress.println(format!(
+                        "{} {} failed; stopping after {} failure(s), {} test(s) not run",
+                        style("✗").red(),
+                        style(&name).bold(),
+                        failures,
+                        total - finished.len()
+                    ));
+                    break;
+                }
+            }
         }
+        drop(running);
 
         progress.finish_and_clear();
 
+        // Report in file order no matter which test finished first
+        finished.sort_by_key(|(index, _)| *index);
+        results.extend(finished.into_iter().map(|(_, result)| result));
         results
     }
 
-    async fn run_single_test(&self, test: &TestCase, model_override: Option<&str>) -> TestResult {
-        let model = model_override
-            .map(|s| s.to_string())
-            .or_else(|| test.model.clone())
-            .unwrap_or_else(|| self.default_model.clone());
-
+    /// Build the request for a test, layering its `options` over the defaults
+    fn build_request(&self, test: &TestCase, model: &str) -> ChatRequest {
         let mut messages = Vec::new();
 
         // Add system prompt if specified
@@ -305,16 +806,32 @@ impl<B: LlmBackend> TestRunner<B> {
 
         messages.push(Message::user(&test.prompt));
 
-        let request = ChatRequest {
-            model: model.clone(),
+        let overrides = test.options.clone().unwrap_or_default();
+        ChatRequest {
+            model: model.to_string(),
             messages,
             stream: Some(false),
             options: Some(ModelOptions {
-                temperature: Some(0.7),
-                top_p: Some(0.9),
-                num_ctx: Some(self.config.context_limit),
+                temperature: overrides.temperature.or(Some(0.7)),
+                top_p: overrides.top_p.or(Some(0.9)),
+                num_ctx: overrides
+                    .num_ctx
+                    .or(Some(self.config.effective_context_limit())),
+                seed: overrides.seed,
+                num_predict: overrides.num_predict,
+                stop: overrides.stop,
             }),
-        };
+            format: test.format.clone(),
+        }
+    }
+
+    async fn run_single_test(&self, test: &TestCase, model_override: Option<&str>) -> TestResult {
+        let model = model_override
+            .map(|s| s.to_string())
+            .or_else(|| test.model.clone())
+            .unwrap_or_else(|| self.default_model.clone());
+
+        let request = self.build_request(test, &model);
 
         let start = Instant::now();
 
@@ -325,7 +842,8 @@ impl<B: LlmBackend> TestRunner<B> {
         let latency_ms = start.elapsed().as_millis() as u64;
 
         match response_result {
-            Ok(Ok(response)) => {
+            Ok(Ok(reply)) => {
+                let response = reply.content;
                 // Check all assertions
                 let mut assertion_results = Vec::new();
                 let mut all_passed = true;
@@ -436,15 +954,45 @@ impl<B: LlmBackend> TestRunner<B> {
                 style(failed).dim()
             }
         );
+
+        let latencies: Vec<u64> = results.iter().map(|r| r.latency_ms).collect();
+        if let (Some(p50), Some(p95)) = (
+            latency_percentile(&latencies, 50.0),
+            latency_percentile(&latencies, 95.0),
+        ) {
+            println!("  {} p50 {}ms, p95 {}ms", style("Latency:").dim(), p50, p95);
+        }
         println!();
     }
 }
 
+/// Compute a latency percentile using the nearest-rank method
+fn latency_percentile(latencies: &[u64], percentile: f64) -> Option<u64> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort_unstable();
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    let idx = rank.clamp(1, sorted.len()) - 1;
+    Some(sorted[idx])
+}
+
+/// "1 value" / "3 values", for list-valued assertion descriptions
+fn count_values(n: usize) -> String {
+    if n == 1 {
+        "1 value".to_string()
+    } else {
+        format!("{} values", n)
+    }
+}
+
 fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        let kept: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", kept)
     }
 }
 
@@ -452,6 +1000,178 @@ fn truncate(s: &str, max_len: usize) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_truncate_multibyte_boundary() {
+        // Byte 27 falls inside the crab emoji; slicing there used to panic
+        let s = format!("{}🦀 and more text after it", "a".repeat(26));
+        assert_eq!(truncate(&s, 30), format!("{}...", "a".repeat(26) + "🦀"));
+        assert_eq!(truncate("日本語のテキストです", 5), "日本...");
+        assert_eq!(truncate("short", 30), "short");
+    }
+
+    /// Mounts a chat endpoint answering `reply` after `delay`, and parses one test
+    /// per `(name, prompt)` pair from `yaml` with `{name}` and `{prompt}` filled in.
+    async fn yaml_suite(
+        yaml: &str,
+        cases: &[(&str, &str)],
+        reply: &str,
+        delay: Duration,
+    ) -> (
+        wiremock::MockServer,
+        TestRunner<crate::ollama::OllamaClient>,
+        Vec<TestCase>,
+    ) {
+        use crate::ollama::OllamaClient;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(chat_reply(reply).set_delay(delay))
+            .mount(&server)
+            .await;
+
+        let tests = cases
+            .iter()
+            .map(|(name, prompt)| {
+                serde_yaml::from_str(&yaml.replace("{name}", name).replace("{prompt}", prompt))
+                    .unwrap()
+            })
+            .collect();
+        let runner = TestRunner::new(
+            OllamaClient::new(&server.uri()),
+            Config::default(),
+            "test-model".to_string(),
+            false,
+        );
+        (server, runner, tests)
+    }
+
+    fn chat_reply(content: &str) -> wiremock::ResponseTemplate {
+        wiremock::ResponseTemplate::new(200).set_body_string(format!(
+            r#"{{"message":{{"role":"assistant","content":"{}"}},"done":true}}"#,
+            content
+        ))
+    }
+
+    const CONTAINS_YES: &str =
+        "name: {name}\nprompt: {prompt}\nassertions:\n  - type: contains\n    value: yes";
+
+    #[tokio::test]
+    async fn test_fail_fast_stops_at_first_failure() {
+        let cases = [("first", "hi"), ("second", "hi"), ("third", "hi")];
+        let (_server, runner, tests) =
+            yaml_suite(CONTAINS_YES, &cases, "nope", Duration::ZERO).await;
+        let filter = TestFilter::new(None, &[]);
+
+        assert_eq!(runner.run_tests(&tests, &filter, None).await.len(), 3);
+
+        let runner = runner.with_bail(Some(1));
+        let results = runner.run_tests(&tests, &filter, None).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "first");
+        assert!(!results[0].passed);
+
+        let runner = runner.with_bail(Some(2));
+        assert_eq!(runner.run_tests(&tests, &filter, None).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_results_keep_file_order() {
+        use wiremock::matchers::{body_string_contains, method, path};
+        use wiremock::Mock;
+
+        let cases = [("a", "slow"), ("b", "fast"), ("c", "fast")];
+        let (server, runner, tests) = yaml_suite(CONTAINS_YES, &cases, "yes", Duration::ZERO).await;
+        // The first test answers last
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains("slow"))
+            .respond_with(chat_reply("yes").set_delay(Duration::from_millis(300)))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+
+        let results = runner
+            .with_jobs(3)
+            .run_tests(&tests, &TestFilter::new(None, &[]), None)
+            .await;
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert!(results.iter().all(|r| r.passed));
+    }
+
+    #[tokio::test]
+    async fn test_latency_assertions_run_serially() {
+        let yaml =
+            "name: {name}\nprompt: {prompt}\nassertions:\n  - type: max_latency\n    ms: 5000";
+        let cases = [("a", "hi"), ("b", "hi"), ("c", "hi")];
+        let (_server, runner, tests) =
+            yaml_suite(yaml, &cases, "yes", Duration::from_millis(100)).await;
+
+        let start = Instant::now();
+        let results = runner
+            .with_jobs(3)
+            .run_tests(&tests, &TestFilter::new(None, &[]), None)
+            .await;
+        assert!(results.iter().all(|r| r.passed));
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    fn tagged(name: &str, tags: &[&str]) -> TestCase {
+        serde_yaml::from_str(&format!(
+            "name: {}\nprompt: hi\nassertions: []\ntags: [{}]",
+            name,
+            tags.join(", ")
+        ))
+        .unwrap()
+    }
+
+    fn selected(filter: &TestFilter, tests: &[TestCase]) -> Vec<String> {
+        tests
+            .iter()
+            .filter(|t| filter.matches(t))
+            .map(|t| t.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_tags() {
+        let tests = vec![
+            tagged("quick", &["smoke"]),
+            tagged("quick_rust", &["smoke", "rust"]),
+            tagged("full", &["smoke", "slow"]),
+            tagged("soak", &["slow"]),
+            tagged("untagged", &[]),
+        ];
+        let tags = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        // Include-only: every listed tag is required
+        let filter = TestFilter::new(None, &tags(&["smoke"]));
+        assert_eq!(selected(&filter, &tests), ["quick", "quick_rust", "full"]);
+        let filter = TestFilter::new(None, &tags(&["smoke", "rust"]));
+        assert_eq!(selected(&filter, &tests), ["quick_rust"]);
+
+        // Exclude-only
+        let filter = TestFilter::new(None, &tags(&["!slow"]));
+        assert_eq!(
+            selected(&filter, &tests),
+            ["quick", "quick_rust", "untagged"]
+        );
+
+        // Combined, plus a --filter pattern on top
+        let filter = TestFilter::new(None, &tags(&["smoke", "!slow"]));
+        assert_eq!(selected(&filter, &tests), ["quick", "quick_rust"]);
+        let filter = TestFilter::new(Some("rust"), &tags(&["smoke", "!slow"]));
+        assert_eq!(selected(&filter, &tests), ["quick_rust"]);
+
+        // Tags match exactly, unlike the substring --filter
+        let filter = TestFilter::new(None, &tags(&["smo"]));
+        assert!(selected(&filter, &tests).is_empty());
+        assert_eq!(selected(&TestFilter::default(), &tests).len(), 5);
+    }
+
     #[test]
     fn test_contains_assertion() {
         let assertion = Assertion::Contains {
@@ -467,6 +1187,41 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_contains_all_assertion() {
+        let assertion: Assertion =
+            serde_yaml::from_str("type: contains_all\nvalues: [Result, \"?\", thiserror]").unwrap();
+        assert!(matches!(
+            assertion.check("use thiserror; fn f() -> Result<()> { g()?; }", 0),
+            AssertionResult::Pass
+        ));
+        match assertion.check("fn f() -> Result<()> { g()?; }", 0) {
+            AssertionResult::Fail(msg) => {
+                assert_eq!(msg, "Response is missing: 'thiserror'");
+            }
+            AssertionResult::Pass => panic!("expected failure"),
+        }
+        assert_eq!(assertion.description(), "contains all of [3 values]");
+    }
+
+    #[test]
+    fn test_contains_any_assertion() {
+        let assertion = Assertion::ContainsAny {
+            values: vec!["anyhow".to_string(), "thiserror".to_string()],
+        };
+        assert!(matches!(
+            assertion.check("I'd use anyhow here", 0),
+            AssertionResult::Pass
+        ));
+        match assertion.check("use Box<dyn Error>", 0) {
+            AssertionResult::Fail(msg) => {
+                assert_eq!(msg, "Response contains none of: 'anyhow', 'thiserror'");
+            }
+            AssertionResult::Pass => panic!("expected failure"),
+        }
+        assert_eq!(assertion.description(), "contains any of [2 values]");
+    }
+
     #[test]
     fn test_regex_assertion() {
         let assertion = Assertion::Regex {
@@ -482,6 +1237,51 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_regex_capture_assertion() {
+        let response = "Here is the port:\npub fn parse_header(buf: &[u8]) -> Header {";
+        let assertion =
+            |group: usize, equals: Option<&str>, matches: Option<&str>| Assertion::RegexCapture {
+                pattern: r"fn (\w+)(<\w+>)?\(".to_string(),
+                group,
+                equals: equals.map(String::from),
+                matches: matches.map(String::from),
+            };
+        let fail = |assertion: Assertion, response: &str| match assertion.check(response, 0) {
+            AssertionResult::Fail(msg) => msg,
+            AssertionResult::Pass => panic!("expected failure"),
+        };
+
+        assert!(matches!(
+            assertion(1, Some("parse_header"), Some("^[a-z][a-z0-9_]*$")).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert_eq!(
+            fail(assertion(1, None, Some("^[a-z_]+$")), "fn parseHeader("),
+            "Group 1 is 'parseHeader', which does not match /^[a-z_]+$/"
+        );
+        assert_eq!(
+            fail(assertion(1, Some("parse"), None), response),
+            "Group 1 is 'parse_header', expected 'parse'"
+        );
+        assert_eq!(
+            fail(assertion(2, None, None), response),
+            r"Group 2 did not capture anything in /fn (\w+)(<\w+>)?\(/"
+        );
+        assert!(
+            fail(assertion(1, None, None), "no functions").starts_with("Response does not match")
+        );
+        assert_eq!(
+            assertion(1, None, Some("^[a-z_]+$")).description(),
+            r"/fn (\w+)(<\w+>)?\(/ group 1 matches /^[a-z_]+$/"
+        );
+
+        let parsed: Assertion =
+            serde_yaml::from_str("type: regex_capture\npattern: 'fn (\\w+)'\nequals: main")
+                .unwrap();
+        assert!(matches!(parsed, Assertion::RegexCapture { group: 1, .. }));
+    }
+
     #[test]
     fn test_valid_json_assertion() {
         let assertion = Assertion::ValidJson;
@@ -493,6 +1293,132 @@ mod tests {
             assertion.check("not json", 0),
             AssertionResult::Fail(_)
         ));
+
+        let assertion = Assertion::NotValidJson;
+        assert!(matches!(
+            assertion.check("Plain prose.", 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion.check(r#"{"key": "value"}"#, 0),
+            AssertionResult::Fail(_)
+        ));
+        assert_eq!(assertion.description(), "not valid JSON");
+    }
+
+    #[test]
+    fn test_json_schema_assertion() {
+        let assertion = Assertion::JsonSchema {
+            schema: serde_json::json!({
+                "type": "object",
+                "required": ["name", "tags"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "age": {"type": ["integer", "null"]},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                }
+            }),
+        };
+        let fail = |response: &str| match assertion.check(response, 0) {
+            AssertionResult::Fail(msg) => msg,
+            AssertionResult::Pass => panic!("expected failure for {}", response),
+        };
+
+        assert!(matches!(
+            assertion.check(r#"{"name": "a", "age": null, "tags": ["x"]}"#, 0),
+            AssertionResult::Pass
+        ));
+        assert_eq!(fail(r#"{"name": "a"}"#), "$ is missing: tags");
+        assert_eq!(
+            fail(r#"{"name": "a", "age": 1.5, "tags": []}"#),
+            "$.age is number, expected integer or null"
+        );
+        assert_eq!(
+            fail(r#"{"name": "a", "tags": ["x", 2]}"#),
+            "$.tags[1] is number, expected string"
+        );
+        assert_eq!(fail("[]"), "$ is array, expected object");
+        assert!(fail("nope").starts_with("Invalid JSON"));
+        assert_eq!(assertion.description(), "JSON object matching schema");
+    }
+
+    #[test]
+    fn test_json_path_assertion() {
+        let response =
+            r#"{"result": {"count": 2, "items": [{"name": "alpha"}, {"name": "beta"}]}}"#;
+        let assertion = |path: &str, equals: Option<serde_json::Value>, contains: Option<&str>| {
+            Assertion::JsonPath {
+                path: path.to_string(),
+                equals,
+                contains: contains.map(String::from),
+            }
+        };
+        let json = |v: serde_json::Value| Some(v);
+
+        assert!(matches!(
+            assertion("result.items.0.name", json("alpha".into()), None).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion("result.items.1.name", None, Some("et")).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion("result.count", json(2.into()), None).check(response, 0),
+            AssertionResult::Pass
+        ));
+        // A quoted YAML value still matches the number, and 1.0 matches 1
+        assert!(matches!(
+            assertion("result.count", json("2".into()), None).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion("result.count", json(2.0.into()), None).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion("result.count", json("3".into()), None).check(response, 0),
+            AssertionResult::Fail(_)
+        ));
+        assert!(matches!(
+            assertion("result.flag", json("true".into()), None)
+                .check(r#"{"result": {"flag": true}}"#, 0),
+            AssertionResult::Pass
+        ));
+        let items = serde_json::json!([{"name": "alpha"}, {"name": "beta"}]);
+        assert!(matches!(
+            assertion("result.items", json(items), Some("beta")).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion("result.items", None, None).check(response, 0),
+            AssertionResult::Pass
+        ));
+        assert!(matches!(
+            assertion("result.items.0.name", json("beta".into()), None).check(response, 0),
+            AssertionResult::Fail(_)
+        ));
+        assert!(matches!(
+            assertion("result.items.5.name", None, None).check(response, 0),
+            AssertionResult::Fail(_)
+        ));
+        assert!(matches!(
+            assertion("result", None, None).check("not json", 0),
+            AssertionResult::Fail(_)
+        ));
+        assert_eq!(
+            assertion("result.count", json(2.into()), None).description(),
+            "result.count == 2"
+        );
+        assert_eq!(
+            assertion("result.items.0.name", json("alpha".into()), Some("lph")).description(),
+            "result.items.0.name == \"alpha\" and contains 'lph'"
+        );
+
+        // From YAML, unquoted scalars keep their JSON type
+        let parsed: Assertion =
+            serde_yaml::from_str("type: json_path\npath: result.count\nequals: 2").unwrap();
+        assert!(matches!(parsed.check(response, 0), AssertionResult::Pass));
     }
 
     #[test]
@@ -505,4 +1431,141 @@ mod tests {
             AssertionResult::Fail(_)
         ));
     }
+
+    #[test]
+    fn test_line_count_assertion() {
+        let assertion = Assertion::LineCount { min: 2, max: 3 };
+        assert!(matches!(
+            assertion.check("- one\n\n- two\n   \n- three\n", 0),
+            AssertionResult::Pass
+        ));
+        match assertion.check("- one\n- two\n- three\n- four", 0) {
+            AssertionResult::Fail(msg) => {
+                assert_eq!(msg, "Response has 4 non-empty line(s), expected [2, 3]")
+            }
+            AssertionResult::Pass => panic!("expected failure"),
+        }
+        assert!(matches!(
+            assertion.check("just one", 0),
+            AssertionResult::Fail(_)
+        ));
+    }
+
+    #[test]
+    fn test_word_count_assertion() {
+        let assertion: Assertion =
+            serde_yaml::from_str("type: word_count\nmin: 3\nmax: 5").unwrap();
+        assert!(matches!(
+            assertion.check("Rust is\nfast  and safe", 0),
+            AssertionResult::Pass
+        ));
+        match assertion.check("too short", 0) {
+            AssertionResult::Fail(msg) => {
+                assert_eq!(msg, "Response has 2 word(s), expected [3, 5]")
+            }
+            AssertionResult::Pass => panic!("expected failure"),
+        }
+        assert!(matches!(
+            assertion.check("one two three four five six", 0),
+            AssertionResult::Fail(_)
+        ));
+    }
+
+    #[test]
+    fn test_min_latency_assertion() {
+        let assertion = Assertion::MinLatency { ms: 100 };
+        assert!(matches!(assertion.check("ok", 150), AssertionResult::Pass));
+        assert!(matches!(assertion.check("ok", 100), AssertionResult::Pass));
+        assert!(matches!(assertion.check("ok", 3), AssertionResult::Fail(_)));
+    }
+
+    #[test]
+    fn test_latency_percentile() {
+        let latencies = [50, 10, 90, 30, 70, 20, 100, 40, 80, 60];
+        assert_eq!(latency_percentile(&latencies, 50.0), Some(50));
+        assert_eq!(latency_percentile(&latencies, 95.0), Some(100));
+        assert_eq!(latency_percentile(&[42], 95.0), Some(42));
+        assert_eq!(latency_percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_case_options_deserialize() {
+        let yaml = r#"
+name: deterministic
+prompt: "Say hi"
+options:
+  temperature: 0
+  seed: 42
+assertions:
+  - type: contains
+    value: hi
+"#;
+        let test: TestCase = serde_yaml::from_str(yaml).unwrap();
+        let options = test.options.unwrap();
+        assert_eq!(options.temperature, Some(0.0));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.top_p, None);
+        assert_eq!(options.num_ctx, None);
+    }
+
+    #[test]
+    fn test_request_carries_test_options() {
+        let runner = TestRunner::new(
+            crate::ollama::OllamaClient::new("http://localhost:11434"),
+            Config::default(),
+            "test-model".to_string(),
+            false,
+        );
+        let mut test: TestCase = serde_yaml::from_str(
+            "name: t\nprompt: hi\noptions:\n  temperature: 0.0\n  seed: 7\nassertions: []\n",
+        )
+        .unwrap();
+
+        let options = runner.build_request(&test, "m").options.unwrap();
+        assert_eq!(options.temperature, Some(0.0));
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.top_p, Some(0.9));
+
+        test.options = None;
+        let options = runner.build_request(&test, "m").options.unwrap();
+        assert_eq!(options.temperature, Some(0.7));
+        assert_eq!(options.seed, None);
+    }
+
+    #[test]
+    fn test_debouncer_coalesces_burst() {
+        let window = Duration::from_millis(300);
+        let mut debouncer = Debouncer::new(window);
+        let start = Instant::now();
+        assert_eq!(debouncer.ready(start), None);
+
+        // An editor save often fires several events for the same file
+        for (ms, file) in [
+            (0, "a.yaml"),
+            (20, "a.yaml"),
+            (40, "b.yaml"),
+            (60, "a.yaml"),
+        ] {
+            debouncer.push(PathBuf::from(file), start + Duration::from_millis(ms));
+        }
+
+        // Still inside the quiet window after the last event
+        assert_eq!(debouncer.ready(start + Duration::from_millis(200)), None);
+
+        let changed = debouncer.ready(start + Duration::from_millis(360)).unwrap();
+        assert_eq!(
+            changed,
+            vec![PathBuf::from("a.yaml"), PathBuf::from("b.yaml")]
+        );
+
+        // Released once; nothing pending afterwards
+        assert_eq!(debouncer.ready(start + Duration::from_millis(1000)), None);
+    }
+
+    #[test]
+    fn test_is_test_file() {
+        assert!(is_test_file(Path::new(".slab/tests/a.yaml")));
+        assert!(is_test_file(Path::new("tests/prompt_tests/b.yml")));
+        assert!(!is_test_file(Path::new("tests/prompt_tests/.a.yaml.swp")));
+    }
 }
@@ -1,4 +1,6 @@
-use console::Style;
+use console::{Color, Style};
+
+use crate::config::CustomThemeConfig;
 
 /// Available theme names
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,6 +11,8 @@ pub enum ThemeName {
     Solarized,
     Minimal,
     Dracula,
+    /// Colors from the `[ui.custom_theme]` config table
+    Custom,
 }
 
 impl ThemeName {
@@ -19,11 +23,34 @@ impl ThemeName {
             "solarized" => ThemeName::Solarized,
             "minimal" => ThemeName::Minimal,
             "dracula" => ThemeName::Dracula,
+            "custom" => ThemeName::Custom,
             _ => ThemeName::Default,
         }
     }
 
-    pub fn to_theme(self) -> Theme {
+    /// Look up a theme by name, returning `None` for unknown names
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|t| t.name().eq_ignore_ascii_case(s))
+    }
+
+    /// The theme after this one in `all()`, wrapping around to the first.
+    /// `Custom` is skipped unless `custom` sets at least one color.
+    pub fn next(self, custom: &CustomThemeConfig) -> Self {
+        let all = Self::all();
+        let pos = all.iter().position(|t| *t == self).unwrap_or(0);
+        let next = all[(pos + 1) % all.len()];
+        if next == ThemeName::Custom && *custom == CustomThemeConfig::default() {
+            next.next(custom)
+        } else {
+            next
+        }
+    }
+
+    /// Build the theme; `custom` is only read for `ThemeName::Custom`
+    pub fn to_theme(self, custom: &CustomThemeConfig) -> Theme {
         match self {
             ThemeName::Default => Theme::default(),
             ThemeName::Monokai => Theme::monokai(),
@@ -31,10 +58,10 @@ impl ThemeName {
             ThemeName::Solarized => Theme::solarized(),
             ThemeName::Minimal => Theme::minimal(),
             ThemeName::Dracula => Theme::dracula(),
+            ThemeName::Custom => Theme::custom(custom),
         }
     }
 
-    #[allow(dead_code)]
     pub fn all() -> &'static [ThemeName] {
         &[
             ThemeName::Default,
@@ -43,10 +70,10 @@ impl ThemeName {
             ThemeName::Solarized,
             ThemeName::Minimal,
             ThemeName::Dracula,
+            ThemeName::Custom,
         ]
     }
 
-    #[allow(dead_code)]
     pub fn name(&self) -> &'static str {
         match self {
             ThemeName::Default => "default",
@@ -55,6 +82,7 @@ impl ThemeName {
             ThemeName::Solarized => "solarized",
             ThemeName::Minimal => "minimal",
             ThemeName::Dracula => "dracula",
+            ThemeName::Custom => "custom",
         }
     }
 }
@@ -157,6 +185,91 @@ impl Theme {
             border: Style::new().color256(60),          // Selection
         }
     }
+
+    /// Theme from `[ui.custom_theme]`; roles left unset or with an
+    /// unrecognised color keep their default style
+    pub fn custom(colors: &CustomThemeConfig) -> Self {
+        let base = Self::default();
+        let role = |spec: &Option<String>, fallback: Style, bold: bool| match spec
+            .as_deref()
+            .and_then(parse_color)
+        {
+            Some(color) if bold => Style::new().fg(color).bold(),
+            Some(color) => Style::new().fg(color),
+            None => fallback,
+        };
+        Self {
+            primary: role(&colors.primary, base.primary, true),
+            secondary: role(&colors.secondary, base.secondary, false),
+            success: role(&colors.success, base.success, false),
+            warning: role(&colors.warning, base.warning, false),
+            error: role(&colors.error, base.error, true),
+            muted: role(&colors.muted, base.muted, false),
+            accent: role(&colors.accent, base.accent, false),
+            border: role(&colors.border, base.border, false),
+        }
+    }
+}
+
+/// Parse a color name, a 256-color index or `#rrggbb` (mapped to the
+/// nearest 256-color entry)
+pub fn parse_color(spec: &str) -> Option<Color> {
+    let spec = spec.trim().to_lowercase();
+    if let Some(hex) = spec.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Color256(rgb_to_256(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+        )));
+    }
+    if let Ok(index) = spec.parse::<u8>() {
+        return Some(Color::Color256(index));
+    }
+    match spec.as_str() {
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        "white" => Some(Color::White),
+        _ => None,
+    }
+}
+
+/// Nearest xterm 256-color index, from the 6x6x6 cube or the grayscale ramp
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |v: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&i| LEVELS[i].abs_diff(v))
+            .unwrap_or(0)
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)]
+            .iter()
+            .map(|&(a, b)| u32::from(a.abs_diff(b)).pow(2))
+            .sum::<u32>()
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let avg = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_step = (avg.saturating_sub(8) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_step;
+
+    if distance((gray, gray, gray)) < distance(cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
 }
 
 /// Box drawing style
@@ -261,15 +374,10 @@ pub struct BoxChars {
     pub bottom_right: char,
     pub horizontal: char,
     pub vertical: char,
-    #[allow(dead_code)]
     pub left_tee: char,
-    #[allow(dead_code)]
     pub right_tee: char,
-    #[allow(dead_code)]
     pub top_tee: char,
-    #[allow(dead_code)]
     pub bottom_tee: char,
-    #[allow(dead_code)]
     pub cross: char,
 }
 
@@ -283,6 +391,81 @@ mod tests {
         assert_eq!(ThemeName::from_str("monokai"), ThemeName::Monokai);
         assert_eq!(ThemeName::from_str("NORD"), ThemeName::Nord);
         assert_eq!(ThemeName::from_str("unknown"), ThemeName::Default);
+        assert_eq!(ThemeName::from_str("Custom"), ThemeName::Custom);
+    }
+
+    #[test]
+    fn test_parse_color_specs() {
+        assert_eq!(parse_color("Red"), Some(Color::Red));
+        assert_eq!(parse_color("208"), Some(Color::Color256(208)));
+        assert_eq!(parse_color("#ff0000"), Some(Color::Color256(196)));
+        assert_eq!(parse_color("#808080"), Some(Color::Color256(244)));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("256"), None);
+        assert_eq!(parse_color("chartreuse"), None);
+    }
+
+    #[test]
+    fn test_custom_theme_falls_back_to_default_roles() {
+        let colors = CustomThemeConfig {
+            primary: Some("#ff8800".to_string()),
+            muted: Some("nope".to_string()),
+            ..Default::default()
+        };
+        let custom = ThemeName::Custom.to_theme(&colors);
+        let default = Theme::default();
+        assert_eq!(
+            custom
+                .primary
+                .clone()
+                .force_styling(true)
+                .apply_to("x")
+                .to_string(),
+            Style::new()
+                .color256(208)
+                .bold()
+                .force_styling(true)
+                .apply_to("x")
+                .to_string()
+        );
+        for (custom, default) in [
+            (&custom.muted, &default.muted),
+            (&custom.error, &default.error),
+        ] {
+            assert_eq!(
+                custom.clone().force_styling(true).apply_to("x").to_string(),
+                default
+                    .clone()
+                    .force_styling(true)
+                    .apply_to("x")
+                    .to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_theme_name_next_wraps() {
+        let custom = CustomThemeConfig {
+            accent: Some("magenta".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ThemeName::Default.next(&custom), ThemeName::Monokai);
+        assert_eq!(ThemeName::Dracula.next(&custom), ThemeName::Custom);
+        assert_eq!(ThemeName::Custom.next(&custom), ThemeName::Default);
+
+        let mut theme = ThemeName::Default;
+        for _ in 0..ThemeName::all().len() {
+            theme = theme.next(&custom);
+        }
+        assert_eq!(theme, ThemeName::Default);
+
+        // Without custom colors, cycling never lands on the custom theme
+        let unset = CustomThemeConfig::default();
+        assert_eq!(ThemeName::Dracula.next(&unset), ThemeName::Default);
+        assert_eq!(ThemeName::Custom.next(&unset), ThemeName::Default);
+
+        assert_eq!(ThemeName::parse("Nord"), Some(ThemeName::Nord));
+        assert_eq!(ThemeName::parse("neon"), None);
     }
 
     #[test]
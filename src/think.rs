@@ -0,0 +1,152 @@
+//! Separating `<think>...</think>` reasoning from the answer of reasoning models
+//! (deepseek-r1, qwen3, ...), both for whole responses and across stream chunks.
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// A piece of model output, classified by whether it sits inside a think block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThinkSegment {
+    Answer(String),
+    Thinking(String),
+}
+
+/// Incrementally splits streamed text into answer and thinking segments.
+/// Tags may be split across chunks, and nested blocks are tracked by depth.
+#[derive(Debug, Default)]
+pub struct ThinkFilter {
+    depth: usize,
+    pending: String,
+}
+
+impl ThinkFilter {
+    /// Feed the next chunk; returns the segments that are complete so far.
+    /// A trailing fragment that could still become a tag is held back.
+    pub fn push(&mut self, chunk: &str) -> Vec<ThinkSegment> {
+        self.pending.push_str(chunk);
+        let mut segments = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            let Some(lt) = self.pending.find('<') else {
+                text.push_str(&self.pending);
+                self.pending.clear();
+                break;
+            };
+            text.push_str(&self.pending[..lt]);
+            let rest = &self.pending[lt..];
+
+            if let Some(tag_len) = [OPEN_TAG, CLOSE_TAG]
+                .iter()
+                .find(|tag| rest.starts_with(**tag))
+                .map(|tag| tag.len())
+            {
+                self.emit(&mut segments, std::mem::take(&mut text));
+                if tag_len == OPEN_TAG.len() {
+                    self.depth += 1;
+                } else {
+                    // A stray closing tag outside any block is dropped
+                    self.depth = self.depth.saturating_sub(1);
+                }
+                self.pending.drain(..lt + tag_len);
+            } else if OPEN_TAG.starts_with(rest) || CLOSE_TAG.starts_with(rest) {
+                // Could still become a tag once the next chunk arrives
+                self.pending.drain(..lt);
+                break;
+            } else {
+                text.push('<');
+                self.pending.drain(..lt + 1);
+            }
+        }
+
+        self.emit(&mut segments, text);
+        segments
+    }
+
+    /// Flush anything held back at the end of the stream
+    pub fn finish(&mut self) -> Vec<ThinkSegment> {
+        let mut segments = Vec::new();
+        let text = std::mem::take(&mut self.pending);
+        self.emit(&mut segments, text);
+        segments
+    }
+
+    fn emit(&self, segments: &mut Vec<ThinkSegment>, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        segments.push(if self.depth > 0 {
+            ThinkSegment::Thinking(text)
+        } else {
+            ThinkSegment::Answer(text)
+        });
+    }
+}
+
+/// Remove `<think>...</think>` spans from a complete response. An unterminated
+/// block swallows the rest of the text; leading whitespace left behind is trimmed.
+pub fn strip_think_blocks(text: &str) -> String {
+    let mut filter = ThinkFilter::default();
+    let mut segments = filter.push(text);
+    segments.extend(filter.finish());
+
+    let stripped = segments.len() != 1 || !matches!(segments[0], ThinkSegment::Answer(_));
+    let answer: String = segments
+        .into_iter()
+        .filter_map(|s| match s {
+            ThinkSegment::Answer(t) => Some(t),
+            ThinkSegment::Thinking(_) => None,
+        })
+        .collect();
+
+    if stripped {
+        answer.trim_start().to_string()
+    } else {
+        answer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_think_blocks() {
+        assert_eq!(
+            strip_think_blocks("<think>plan the edit</think>\n\nHere is the fix."),
+            "Here is the fix."
+        );
+        assert_eq!(
+            strip_think_blocks("a <think>outer <think>inner</think> still</think>b"),
+            "a b"
+        );
+        assert_eq!(
+            strip_think_blocks("Answer first.<think>never closed"),
+            "Answer first."
+        );
+        assert_eq!(
+            strip_think_blocks("if a < b { x }  no tags here"),
+            "if a < b { x }  no tags here"
+        );
+        assert_eq!(strip_think_blocks("done</think> ok"), "done ok");
+    }
+
+    #[test]
+    fn test_think_filter_handles_tags_split_across_chunks() {
+        let mut filter = ThinkFilter::default();
+        let mut segments = Vec::new();
+        for chunk in ["<thi", "nk>rea", "soning</th", "ink>", "Answer <", "3"] {
+            segments.extend(filter.push(chunk));
+        }
+        segments.extend(filter.finish());
+        assert_eq!(
+            segments,
+            vec![
+                ThinkSegment::Thinking("rea".into()),
+                ThinkSegment::Thinking("soning".into()),
+                ThinkSegment::Answer("Answer ".into()),
+                ThinkSegment::Answer("<3".into()),
+            ]
+        );
+    }
+}
@@ -1,6 +1,23 @@
 use console::Style;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::BTreeMap;
+use std::path::{Component, PathBuf};
+use tokio::sync::mpsc;
+
+use crate::error::{Result, SlabError};
+use crate::ollama::PullStatus;
+use crate::theme::{BoxChars, BoxStyle, Theme};
+
+/// Whether color output should be turned off, via `--no-color` or a non-empty `NO_COLOR`
+pub fn color_disabled(no_color_flag: bool) -> bool {
+    no_color_flag || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
 
-use crate::theme::{BoxStyle, Theme};
+/// Disable `console` styling on both stdout and stderr for the rest of the process
+pub fn disable_color() {
+    console::set_colors_enabled(false);
+    console::set_colors_enabled_stderr(false);
+}
 
 /// Get the current terminal width
 pub fn terminal_width() -> usize {
@@ -9,15 +26,23 @@ pub fn terminal_width() -> usize {
         .unwrap_or(80)
 }
 
-/// Truncate a string to fit within a given width, adding ellipsis if needed
-#[allow(dead_code)]
+/// Get the current terminal height
+pub fn terminal_height() -> usize {
+    terminal_size::terminal_size()
+        .map(|(_, h)| h.0 as usize)
+        .unwrap_or(24)
+}
+
+/// Truncate a string to at most `max_width` characters, adding ellipsis if needed.
+/// Cuts on character boundaries, so multibyte text never splits mid-character.
 pub fn truncate_str(s: &str, max_width: usize) -> String {
-    if s.len() <= max_width {
+    if s.chars().count() <= max_width {
         s.to_string()
     } else if max_width <= 3 {
         ".".repeat(max_width)
     } else {
-        format!("{}...", &s[..max_width - 3])
+        let kept: String = s.chars().take(max_width - 3).collect();
+        format!("{}...", kept)
     }
 }
 
@@ -31,7 +56,6 @@ pub fn wrap_text(s: &str, width: usize) -> Vec<String> {
 }
 
 /// Calculate the display width of a string (handling Unicode properly)
-#[allow(dead_code)]
 pub fn display_width(s: &str) -> usize {
     unicode_width::UnicodeWidthStr::width(s)
 }
@@ -41,14 +65,17 @@ pub struct BoxRenderer {
     style: BoxStyle,
     theme: Theme,
     width: usize,
+    plain: bool,
 }
 
 impl BoxRenderer {
+    /// Boxes render as plain ASCII whenever stdout styling is disabled
     pub fn new(style: BoxStyle, theme: Theme) -> Self {
         Self {
             style,
             theme,
             width: 60,
+            plain: !console::colors_enabled(),
         }
     }
 
@@ -57,14 +84,36 @@ impl BoxRenderer {
         self
     }
 
+    /// Force plain output: ASCII borders and no ANSI styling
+    pub fn with_plain(mut self, plain: bool) -> Self {
+        self.plain = plain;
+        self
+    }
+
+    fn chars(&self) -> crate::theme::BoxChars {
+        if self.plain {
+            BoxStyle::Ascii.chars()
+        } else {
+            self.style.chars()
+        }
+    }
+
+    fn paint<D: std::fmt::Display>(&self, style: &Style, value: D) -> String {
+        if self.plain {
+            value.to_string()
+        } else {
+            style.apply_to(value).to_string()
+        }
+    }
+
     /// Render a box with an optional title
     pub fn render_titled_box(&self, title: Option<&str>, content: &str) -> String {
-        let chars = self.style.chars();
+        let chars = self.chars();
         let inner_width = self.width.saturating_sub(2);
         let mut output = String::new();
 
         // Top border with title
-        output.push_str(&format!("{}", self.theme.border.apply_to(chars.top_left)));
+        output.push_str(&self.paint(&self.theme.border, chars.top_left));
 
         if let Some(t) = title {
             let title_str = format!(" {} ", t);
@@ -72,28 +121,30 @@ impl BoxRenderer {
             let remaining = inner_width.saturating_sub(title_len);
             output.push_str(&format!(
                 "{}{}",
-                self.theme.primary.apply_to(&title_str),
-                self.theme
-                    .border
-                    .apply_to(std::iter::repeat_n(chars.horizontal, remaining).collect::<String>())
+                self.paint(&self.theme.primary, &title_str),
+                self.paint(
+                    &self.theme.border,
+                    std::iter::repeat_n(chars.horizontal, remaining).collect::<String>()
+                )
             ));
         } else {
-            output.push_str(&format!(
-                "{}",
-                self.theme.border.apply_to(
-                    std::iter::repeat_n(chars.horizontal, inner_width).collect::<String>()
-                )
+            output.push_str(&self.paint(
+                &self.theme.border,
+                std::iter::repeat_n(chars.horizontal, inner_width).collect::<String>(),
             ));
         }
 
         output.push_str(&format!(
             "{}\n",
-            self.theme.border.apply_to(chars.top_right)
+            self.paint(&self.theme.border, chars.top_right)
         ));
 
         // Content lines
         for line in content.lines() {
-            output.push_str(&format!("{} ", self.theme.border.apply_to(chars.vertical)));
+            output.push_str(&format!(
+                "{} ",
+                self.paint(&self.theme.border, chars.vertical)
+            ));
             output.push_str(line);
             output.push('\n');
         }
@@ -101,11 +152,12 @@ impl BoxRenderer {
         // Bottom border
         output.push_str(&format!(
             "{}{}{}",
-            self.theme.border.apply_to(chars.bottom_left),
-            self.theme
-                .border
-                .apply_to(std::iter::repeat_n(chars.horizontal, inner_width).collect::<String>()),
-            self.theme.border.apply_to(chars.bottom_right)
+            self.paint(&self.theme.border, chars.bottom_left),
+            self.paint(
+                &self.theme.border,
+                std::iter::repeat_n(chars.horizontal, inner_width).collect::<String>()
+            ),
+            self.paint(&self.theme.border, chars.bottom_right)
         ));
         output.push('\n');
 
@@ -114,34 +166,35 @@ impl BoxRenderer {
 
     /// Render a box with a styled title (for errors, warnings, etc.)
     pub fn render_styled_box(&self, title: &str, content: &str, title_style: &Style) -> String {
-        let chars = self.style.chars();
+        let chars = self.chars();
         let inner_width = self.width.saturating_sub(2);
         let mut output = String::new();
 
         // Top border with styled title
-        output.push_str(&format!("{}", self.theme.border.apply_to(chars.top_left)));
+        output.push_str(&self.paint(&self.theme.border, chars.top_left));
 
         let title_str = format!(" {} ", title);
         let title_len = title_str.len();
         let remaining = inner_width.saturating_sub(title_len);
         output.push_str(&format!(
             "{}{}",
-            title_style.apply_to(&title_str),
-            self.theme
-                .border
-                .apply_to(std::iter::repeat_n(chars.horizontal, remaining).collect::<String>())
+            self.paint(title_style, &title_str),
+            self.paint(
+                &self.theme.border,
+                std::iter::repeat_n(chars.horizontal, remaining).collect::<String>()
+            )
         ));
 
         output.push_str(&format!(
             "{}\n",
-            self.theme.border.apply_to(chars.top_right)
+            self.paint(&self.theme.border, chars.top_right)
         ));
 
         // Content lines
         for line in content.lines() {
             output.push_str(&format!(
                 "{} {}\n",
-                self.theme.border.apply_to(chars.vertical),
+                self.paint(&self.theme.border, chars.vertical),
                 line
             ));
         }
@@ -149,11 +202,12 @@ impl BoxRenderer {
         // Bottom border
         output.push_str(&format!(
             "{}{}{}",
-            self.theme.border.apply_to(chars.bottom_left),
-            self.theme
-                .border
-                .apply_to(std::iter::repeat_n(chars.horizontal, inner_width).collect::<String>()),
-            self.theme.border.apply_to(chars.bottom_right)
+            self.paint(&self.theme.border, chars.bottom_left),
+            self.paint(
+                &self.theme.border,
+                std::iter::repeat_n(chars.horizontal, inner_width).collect::<String>()
+            ),
+            self.paint(&self.theme.border, chars.bottom_right)
         ));
         output.push('\n');
 
@@ -161,6 +215,198 @@ impl BoxRenderer {
     }
 }
 
+impl BoxRenderer {
+    /// Render rows as a bordered table; the first row is the header.
+    /// Columns are padded to their widest cell; `right_align[i]` right-aligns column i.
+    pub fn render_table(&self, rows: &[Vec<String>], right_align: &[bool]) -> String {
+        let chars = self.chars();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| {
+                rows.iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| display_width(cell))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let rule = |left: char, mid: char, right: char| {
+            let segments: Vec<String> = widths
+                .iter()
+                .map(|w| std::iter::repeat_n(chars.horizontal, w + 2).collect())
+                .collect();
+            let line = format!("{}{}{}", left, segments.join(&mid.to_string()), right);
+            format!("{}\n", self.paint(&self.theme.border, line))
+        };
+
+        let mut output = rule(chars.top_left, chars.top_tee, chars.top_right);
+        for (r, row) in rows.iter().enumerate() {
+            let border = self.paint(&self.theme.border, chars.vertical);
+            output.push_str(&border);
+            for (i, width) in widths.iter().enumerate() {
+                let cell = row.get(i).map(String::as_str).unwrap_or("");
+                let pad = " ".repeat(width - display_width(cell));
+                let cell = if r == 0 {
+                    self.paint(&self.theme.primary, cell)
+                } else {
+                    cell.to_string()
+                };
+                if right_align.get(i).copied().unwrap_or(false) {
+                    output.push_str(&format!(" {}{} ", pad, cell));
+                } else {
+                    output.push_str(&format!(" {}{} ", cell, pad));
+                }
+                output.push_str(&border);
+            }
+            output.push('\n');
+            if r == 0 && rows.len() > 1 {
+                output.push_str(&rule(chars.left_tee, chars.cross, chars.right_tee));
+            }
+        }
+        output.push_str(&rule(
+            chars.bottom_left,
+            chars.bottom_tee,
+            chars.bottom_right,
+        ));
+        output
+    }
+}
+
+/// Split a markdown table row into trimmed cells
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(|c| c.trim().to_string()).collect()
+}
+
+/// Whether a line is a markdown table delimiter row such as `|---|:--:|`.
+/// Returns the right-alignment of each column when it is.
+fn table_delimiter(line: &str) -> Option<Vec<bool>> {
+    let trimmed = line.trim();
+    if !trimmed.contains('-') || !trimmed.contains('|') {
+        return None;
+    }
+    table_cells(trimmed)
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            (!dashes.is_empty() && dashes.chars().all(|c| c == '-'))
+                .then(|| cell.ends_with(':') && !cell.starts_with(':'))
+        })
+        .collect()
+}
+
+/// Whether `text` has a markdown table outside code blocks
+pub fn has_markdown_table(text: &str) -> bool {
+    let mut in_code = false;
+    let lines: Vec<&str> = text.lines().collect();
+    lines.iter().enumerate().any(|(i, line)| {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        !in_code
+            && line.trim_start().starts_with('|')
+            && lines
+                .get(i + 1)
+                .is_some_and(|next| table_delimiter(next).is_some())
+    })
+}
+
+/// Re-render markdown tables in `text` as aligned, bordered tables.
+/// Everything else, including code blocks, passes through unchanged.
+pub fn render_markdown_tables(text: &str, renderer: &BoxRenderer) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut output = String::new();
+    let mut in_code = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+
+        let delimiter = lines.get(i + 1).and_then(|next| table_delimiter(next));
+        if let (false, true, Some(right_align)) =
+            (in_code, line.trim_start().starts_with('|'), delimiter)
+        {
+            let mut rows = vec![table_cells(line)];
+            i += 2;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                rows.push(table_cells(lines[i]));
+                i += 1;
+            }
+            output.push_str(&renderer.render_table(&rows, &right_align));
+            continue;
+        }
+
+        output.push_str(line);
+        output.push('\n');
+        i += 1;
+    }
+
+    if !text.ends_with('\n') && output.ends_with('\n') {
+        output.pop();
+    }
+    output
+}
+
+/// Draw a progress bar for a model pull until Ollama reports success
+pub async fn show_pull_progress(mut rx: mpsc::Receiver<Result<PullStatus>>) -> Result<()> {
+    let progress = ProgressBar::new(0);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{prefix:.bold} [{bar:40.cyan/blue}] {bytes}/{total_bytes} {msg}")
+            .unwrap()
+            .progress_chars("━━─"),
+    );
+    progress.set_prefix("Pulling");
+
+    let mut succeeded = false;
+    while let Some(item) = rx.recv().await {
+        let status = match item {
+            Ok(status) => status,
+            Err(e) => {
+                progress.abandon();
+                return Err(e);
+            }
+        };
+
+        if let Some(error) = status.error {
+            progress.abandon();
+            return Err(SlabError::StreamError(error));
+        }
+
+        match (status.total, status.completed) {
+            (Some(total), completed) => {
+                progress.set_length(total);
+                progress.set_position(completed.unwrap_or(0));
+            }
+            _ => {
+                progress.set_length(0);
+                progress.set_position(0);
+            }
+        }
+        progress.set_message(status.status.clone());
+
+        if status.status == "success" {
+            succeeded = true;
+        }
+    }
+
+    if !succeeded {
+        progress.abandon();
+        return Err(SlabError::StreamError(
+            "Pull ended before Ollama reported success".to_string(),
+        ));
+    }
+
+    progress.finish_and_clear();
+    Ok(())
+}
+
 /// Quick helper to create an info box
 #[allow(dead_code)]
 pub fn info_box(content: &str) -> String {
@@ -193,6 +439,84 @@ pub fn warning_box(content: &str) -> String {
     renderer.render_styled_box("Warning", content, &theme.warning)
 }
 
+/// One row of a `/tree` listing: the connector prefix, the entry name
+/// (directories end in `/`) and, for files, the approximate token count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeLine {
+    pub prefix: String,
+    pub name: String,
+    pub tokens: Option<usize>,
+}
+
+#[derive(Default)]
+struct TreeNode {
+    dirs: BTreeMap<String, TreeNode>,
+    files: BTreeMap<String, usize>,
+}
+
+/// Lay out `(path, tokens)` pairs as an indented tree, directories before
+/// files at each level, both sorted by name
+pub fn file_tree(files: &[(PathBuf, usize)], chars: BoxChars) -> Vec<TreeLine> {
+    let mut root = TreeNode::default();
+    for (path, tokens) in files {
+        let parts: Vec<String> = path
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let Some((file, dirs)) = parts.split_last() else {
+            continue;
+        };
+        let mut node = &mut root;
+        for dir in dirs {
+            node = node.dirs.entry(dir.clone()).or_default();
+        }
+        node.files.insert(file.clone(), *tokens);
+    }
+
+    let mut lines = Vec::new();
+    push_tree_lines(&root, "", chars, &mut lines);
+    lines
+}
+
+fn push_tree_lines(node: &TreeNode, indent: &str, chars: BoxChars, lines: &mut Vec<TreeLine>) {
+    let count = node.dirs.len() + node.files.len();
+    let branch = |i: usize| {
+        let tee = if i + 1 == count {
+            chars.bottom_left
+        } else {
+            chars.left_tee
+        };
+        format!("{}{}{}{} ", indent, tee, chars.horizontal, chars.horizontal)
+    };
+
+    for (i, (name, child)) in node.dirs.iter().enumerate() {
+        let name = if name.ends_with('/') {
+            name.clone()
+        } else {
+            format!("{}/", name)
+        };
+        lines.push(TreeLine {
+            prefix: branch(i),
+            name,
+            tokens: None,
+        });
+        let child_indent = if i + 1 == count {
+            format!("{}    ", indent)
+        } else {
+            format!("{}{}   ", indent, chars.vertical)
+        };
+        push_tree_lines(child, &child_indent, chars, lines);
+    }
+    for (i, (name, tokens)) in node.files.iter().enumerate() {
+        lines.push(TreeLine {
+            prefix: branch(node.dirs.len() + i),
+            name: name.clone(),
+            tokens: Some(*tokens),
+        });
+    }
+}
+
 /// Create a simple horizontal divider
 #[allow(dead_code)]
 pub fn divider(width: usize) -> String {
@@ -235,11 +559,63 @@ pub mod spinners {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_file_tree_dirs_before_files() {
+        let files = vec![
+            (PathBuf::from("src/main.rs"), 120),
+            (PathBuf::from("README.md"), 40),
+            (PathBuf::from("src/repl/input.rs"), 80),
+            (PathBuf::from("./Cargo.toml"), 10),
+        ];
+        let rendered: Vec<String> = file_tree(&files, BoxStyle::Sharp.chars())
+            .into_iter()
+            .map(|l| match l.tokens {
+                Some(t) => format!("{}{} ({})", l.prefix, l.name, t),
+                None => format!("{}{}", l.prefix, l.name),
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "├── src/",
+                "│   ├── repl/",
+                "│   │   └── input.rs (80)",
+                "│   └── main.rs (120)",
+                "├── Cargo.toml (10)",
+                "└── README.md (40)",
+            ]
+        );
+    }
+
     #[test]
     fn test_truncate_str() {
         assert_eq!(truncate_str("hello", 10), "hello");
         assert_eq!(truncate_str("hello world", 8), "hello...");
         assert_eq!(truncate_str("hi", 2), "hi");
+        // Multibyte characters at the cut point stay whole
+        assert_eq!(truncate_str("héllo wörld", 8), "héllo...");
+        assert_eq!(truncate_str("日本語のテキスト", 6), "日本語...");
+        assert_eq!(truncate_str("🦀🦀🦀", 3), "🦀🦀🦀");
+    }
+
+    #[test]
+    fn test_plain_box_has_no_ansi() {
+        let theme = Theme::default();
+        let renderer = BoxRenderer::new(BoxStyle::Rounded, theme.clone())
+            .with_width(20)
+            .with_plain(true);
+        let titled = renderer.render_titled_box(Some("Commands"), "/help - Show help");
+        let styled = renderer.render_styled_box("Error", "boom", &theme.error);
+
+        for rendered in [titled, styled] {
+            assert!(
+                !rendered.contains('\x1b'),
+                "unexpected ANSI in {:?}",
+                rendered
+            );
+            assert!(rendered.is_ascii());
+            assert!(rendered.starts_with('+'));
+        }
     }
 
     #[test]
@@ -253,4 +629,36 @@ mod tests {
         assert!(result.starts_with("─ Title "));
         assert_eq!(result.chars().count(), 20);
     }
+
+    #[test]
+    fn test_render_markdown_tables_aligns_columns() {
+        let renderer = BoxRenderer::new(BoxStyle::Rounded, Theme::default()).with_plain(true);
+        let text = "Results:\n| Name | Score |\n|---|--:|\n| alice | 7 |\n| bob | 10 |\nDone.";
+        let rendered = render_markdown_tables(text, &renderer);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "Results:");
+        assert_eq!(lines.last(), Some(&"Done."));
+        let table = &lines[1..lines.len() - 1];
+        assert_eq!(
+            table,
+            &[
+                "+-------+-------+",
+                "| Name  | Score |",
+                "+-------+-------+",
+                "| alice |     7 |",
+                "| bob   |    10 |",
+                "+-------+-------+",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_tables_leaves_other_text() {
+        let renderer = BoxRenderer::new(BoxStyle::Rounded, Theme::default()).with_plain(true);
+        let text = "a | b\n```\n| x | y |\n|---|---|\n```\n";
+        assert_eq!(render_markdown_tables(text, &renderer), text);
+        assert!(!has_markdown_table(text));
+        assert!(has_markdown_table("| x | y |\n| - | - |\n| 1 | 2 |"));
+    }
 }